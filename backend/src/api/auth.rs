@@ -0,0 +1,416 @@
+/*
+    Authentication of the admin routes (`/api/v1/admin/...`). A request is accepted with either:
+    - `Authorization: Bearer <key>` with a key of ADMIN_API_KEYS, matched by its SHA-256 hash in
+      constant time
+    - when ADMIN_SIGNATURE_AUTH is on and the route is the one of a vault, an EIP-191 signature
+      of the request (see `signature_message`) by the vault owner, in the `X-Admin-Signature`,
+      `X-Admin-Nonce` and `X-Admin-Timestamp` headers. A signed request is accepted once
+    Every admin request, accepted or not, is written to the audit log.
+*/
+use std::str::FromStr;
+
+use actix_web::{
+    HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::header::{AUTHORIZATION, HeaderMap},
+    middleware::Next,
+    web::{self, Bytes},
+};
+use alloy::primitives::{Address, B256, Signature, keccak256};
+use color_eyre::eyre::{Result, eyre};
+use dashmap::{DashMap, mapref::entry::Entry};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use tracing::{error, info, warn};
+
+use crate::{
+    config::{ADMIN_SIGNATURE_MAX_AGE_SECONDS, CONFIG},
+    core::vault::YielderaVault,
+    state::AppState,
+    types::{AdminApiKey, AdminAuditEntry, ApiErrorResponse},
+};
+
+const ADMIN_PATH_PREFIX: &str = "/api/v1/admin/";
+const ADMIN_VAULTS_PATH_PREFIX: &str = "/api/v1/admin/vaults/";
+const SIGNATURE_HEADER: &str = "x-admin-signature";
+const TIMESTAMP_HEADER: &str = "x-admin-timestamp";
+const NONCE_HEADER: &str = "x-admin-nonce";
+
+/// Signed admin requests already accepted, by signer and signed message hash, kept until their
+/// timestamp expires
+#[derive(Default)]
+pub struct UsedSignatures(DashMap<(Address, B256), i64>);
+
+impl UsedSignatures {
+    /// Accept a signed request once, the expired entries are dropped on the way
+    pub fn claim(
+        &self,
+        signer: Address,
+        message_hash: B256,
+        timestamp: i64,
+        now: i64,
+    ) -> Result<()> {
+        self.0
+            .retain(|_, timestamp| (now - *timestamp).abs() <= ADMIN_SIGNATURE_MAX_AGE_SECONDS);
+
+        match self.0.entry((signer, message_hash)) {
+            Entry::Occupied(_) => Err(eyre!("Signature already used")),
+            Entry::Vacant(entry) => {
+                entry.insert(timestamp);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Middleware of the admin routes, the other routes go through untouched
+pub async fn admin_auth<B: MessageBody + 'static>(
+    mut req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+    if !routed_path(&req).starts_with(ADMIN_PATH_PREFIX) {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    let app_state = req
+        .app_data::<web::Data<AppState>>()
+        .map(|app_state| app_state.clone().into_inner());
+    let mut entry = AdminAuditEntry {
+        timestamp: chrono::Utc::now().timestamp(),
+        principal: None,
+        method: req.method().to_string(),
+        path: routed_path(&req).to_string(),
+        status: 401,
+        error: None,
+    };
+
+    let principal = match authenticate(&mut req, app_state.as_deref()).await {
+        Ok(principal) => principal,
+        Err(e) => {
+            entry.error = Some(e.to_string());
+            audit(app_state.as_deref(), &entry);
+
+            let response = HttpResponse::Unauthorized().json(ApiErrorResponse {
+                message: "Unauthorized".to_string(),
+                error: e.to_string(),
+            });
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    };
+    entry.principal = Some(principal);
+
+    let response = next.call(req).await;
+    entry.status = match &response {
+        Ok(response) => response.status().as_u16(),
+        Err(e) => e.as_response_error().status_code().as_u16(),
+    };
+    audit(app_state.as_deref(), &entry);
+
+    Ok(response?.map_into_left_body())
+}
+
+/// Principal of the request: `api_key:<name>` or `owner:<address>`
+async fn authenticate(req: &mut ServiceRequest, app_state: Option<&AppState>) -> Result<String> {
+    if let Some(key) = bearer_token(req.headers()) {
+        return match_api_key(&CONFIG.admin_api_keys, key)
+            .map(|name| format!("api_key:{}", name))
+            .ok_or_else(|| eyre!("Invalid API key"));
+    }
+
+    if req.headers().contains_key(SIGNATURE_HEADER) {
+        if !CONFIG.admin_signature_auth {
+            return Err(eyre!("Signature authentication is disabled"));
+        }
+        let app_state = app_state.ok_or_else(|| eyre!("App state is not configured"))?;
+
+        let owner = verify_owner_signature(req, app_state).await?;
+        return Ok(format!("owner:{}", owner));
+    }
+
+    Err(eyre!("Missing admin credentials"))
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+/// Name of the API key matching `key`. All the keys are compared, in constant time
+pub fn match_api_key<'a>(api_keys: &'a [AdminApiKey], key: &str) -> Option<&'a str> {
+    let hash: [u8; 32] = Sha256::digest(key.as_bytes()).into();
+
+    let mut matched = None;
+    for api_key in api_keys {
+        if bool::from(api_key.hash.ct_eq(&hash)) {
+            matched = Some(api_key.name.as_str());
+        }
+    }
+
+    matched
+}
+
+/// Check the request signature against the owner of the vault of the route, returns the owner
+async fn verify_owner_signature(req: &mut ServiceRequest, app_state: &AppState) -> Result<Address> {
+    let vault_address = vault_of_path(routed_path(req))
+        .ok_or_else(|| eyre!("Signature authentication is only accepted on the vault routes"))?;
+
+    let signature = header_str(req.headers(), SIGNATURE_HEADER)?;
+    let nonce = header_str(req.headers(), NONCE_HEADER)?.to_string();
+    if nonce.is_empty() {
+        return Err(eyre!("Empty {} header", NONCE_HEADER));
+    }
+    let timestamp = header_str(req.headers(), TIMESTAMP_HEADER)?
+        .parse::<i64>()
+        .map_err(|e| eyre!("Invalid {} header: {}", TIMESTAMP_HEADER, e))?;
+    let signature = Signature::from_str(signature)?;
+
+    // The body is signed too, put it back for the handler
+    let body = req
+        .extract::<Bytes>()
+        .await
+        .map_err(|e| eyre!("Failed to read the request body: {}", e))?;
+    req.set_payload(Payload::from(body.clone()));
+
+    let now = chrono::Utc::now().timestamp();
+    let message = signature_message(
+        req.method().as_str(),
+        routed_path(req),
+        &nonce,
+        timestamp,
+        &body,
+    );
+    let signer = recover_signer(&signature, &message, timestamp, now)?;
+
+    let owner = YielderaVault::new(vault_address, &app_state.evm_provider)
+        .owner()
+        .call()
+        .await?;
+
+    if signer != owner {
+        return Err(eyre!(
+            "Signer {} is not the owner of vault {}",
+            signer,
+            vault_address
+        ));
+    }
+
+    app_state
+        .used_signatures
+        .claim(signer, keccak256(&message), timestamp, now)?;
+
+    Ok(signer)
+}
+
+/// Path the request is routed on: the raw path with its percent-encoded characters decoded (but
+/// `/`, `%` and `+`), so `/api/v1/%61dmin/...` is seen as an admin route as it is by the router
+fn routed_path(req: &ServiceRequest) -> &str {
+    req.match_info().as_str()
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str> {
+    headers
+        .get(name)
+        .ok_or_else(|| eyre!("Missing {} header", name))?
+        .to_str()
+        .map_err(|e| eyre!("Invalid {} header: {}", name, e))
+}
+
+/// Vault address of a `/api/v1/admin/vaults/{address}/...` route
+fn vault_of_path(path: &str) -> Option<Address> {
+    let address = path
+        .strip_prefix(ADMIN_VAULTS_PATH_PREFIX)?
+        .split('/')
+        .next()?;
+
+    Address::from_str(address).ok()
+}
+
+/// Message signed with `personal_sign` (EIP-191) to authenticate an admin request
+pub fn signature_message(
+    method: &str,
+    path: &str,
+    nonce: &str,
+    timestamp: i64,
+    body: &[u8],
+) -> String {
+    format!(
+        "Yieldera admin request\n{} {}\nNonce: {}\nTimestamp: {}\nBody: {}",
+        method,
+        path,
+        nonce,
+        timestamp,
+        keccak256(body)
+    )
+}
+
+/// Signer of an admin request, rejected when its timestamp is too far from `now`
+fn recover_signer(
+    signature: &Signature,
+    message: &str,
+    timestamp: i64,
+    now: i64,
+) -> Result<Address> {
+    if (now - timestamp).abs() > ADMIN_SIGNATURE_MAX_AGE_SECONDS {
+        return Err(eyre!(
+            "Signature timestamp {} is more than {}s away from now",
+            timestamp,
+            ADMIN_SIGNATURE_MAX_AGE_SECONDS
+        ));
+    }
+
+    Ok(signature.recover_address_from_msg(message)?)
+}
+
+/// Log the admin request and store it, a storage failure is only logged
+fn audit(app_state: Option<&AppState>, entry: &AdminAuditEntry) {
+    match &entry.principal {
+        Some(principal) => info!(
+            "Admin request {} {} by {}: {}",
+            entry.method, entry.path, principal, entry.status
+        ),
+        None => warn!(
+            "Admin request {} {} rejected: {}",
+            entry.method,
+            entry.path,
+            entry.error.as_deref().unwrap_or_default()
+        ),
+    }
+
+    if let Some(app_state) = app_state
+        && let Err(e) = app_state.db.insert_audit_entry(entry)
+    {
+        error!("Failed to store the admin audit entry: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App, HttpResponse,
+        http::StatusCode,
+        middleware,
+        test::{TestRequest, call_service, init_service},
+        web,
+    };
+    use alloy::{
+        primitives::{Address, address, keccak256},
+        signers::{SignerSync, local::PrivateKeySigner},
+    };
+    use sha2::{Digest, Sha256};
+
+    use crate::types::AdminApiKey;
+
+    use super::{
+        UsedSignatures, admin_auth, match_api_key, recover_signer, signature_message, vault_of_path,
+    };
+
+    #[actix_web::test]
+    async fn test_admin_auth_on_encoded_paths() {
+        let app = init_service(
+            App::new()
+                .wrap(middleware::from_fn(admin_auth))
+                .route(
+                    "/api/v1/admin/vaults/associate-tokens",
+                    web::post().to(HttpResponse::Ok),
+                )
+                .route("/api/v1/vaults", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        for path in [
+            "/api/v1/admin/vaults/associate-tokens",
+            "/api/v1/%61dmin/vaults/associate-tokens",
+            "/api/v1/admin/vault%73/associate-tokens",
+        ] {
+            let response = call_service(&app, TestRequest::post().uri(path).to_request()).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{}", path);
+        }
+
+        // Public routes need no credentials
+        let response =
+            call_service(&app, TestRequest::get().uri("/api/v1/vaults").to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_match_api_key() {
+        let api_keys = vec![
+            AdminApiKey {
+                name: "ops".to_string(),
+                hash: Sha256::digest(b"ops-secret").into(),
+            },
+            AdminApiKey {
+                name: "ci".to_string(),
+                hash: Sha256::digest(b"ci-secret").into(),
+            },
+        ];
+
+        assert_eq!(match_api_key(&api_keys, "ci-secret"), Some("ci"));
+        assert_eq!(match_api_key(&api_keys, "ops-secret"), Some("ops"));
+        assert_eq!(match_api_key(&api_keys, "ops-secret "), None);
+        assert_eq!(match_api_key(&[], "ops-secret"), None);
+    }
+
+    #[test]
+    fn test_vault_of_path() {
+        assert_eq!(
+            vault_of_path("/api/v1/admin/vaults/0x00000000000000000000000000000000000000aa/pause"),
+            Some(address!("0x00000000000000000000000000000000000000aa"))
+        );
+        assert_eq!(vault_of_path("/api/v1/admin/vaults/associate-tokens"), None);
+        assert_eq!(vault_of_path("/api/v1/admin/audit-log"), None);
+    }
+
+    #[test]
+    fn test_recover_signer() {
+        let signer = PrivateKeySigner::random();
+        let (method, path, body) = ("POST", "/api/v1/admin/vaults/0xabc/rebalance", b"{}");
+        let message = signature_message(method, path, "1", 1_000, body);
+        let signature = signer.sign_message_sync(message.as_bytes()).unwrap();
+
+        assert_eq!(
+            recover_signer(&signature, &message, 1_000, 1_100).unwrap(),
+            signer.address()
+        );
+
+        // Another body, route or nonce recovers another address
+        for message in [
+            signature_message(method, path, "1", 1_000, b"{\"a\":1}"),
+            signature_message(method, path, "2", 1_000, body),
+        ] {
+            assert_ne!(
+                recover_signer(&signature, &message, 1_000, 1_100).unwrap(),
+                signer.address()
+            );
+        }
+
+        // Expired
+        assert!(recover_signer(&signature, &message, 1_000, 2_000).is_err());
+    }
+
+    #[test]
+    fn test_used_signatures() {
+        let used_signatures = UsedSignatures::default();
+        let signer = address!("0x00000000000000000000000000000000000000aa");
+        let first = keccak256(signature_message("POST", "/pause", "1", 1_000, b""));
+        let second = keccak256(signature_message("POST", "/pause", "2", 1_000, b""));
+
+        used_signatures.claim(signer, first, 1_000, 1_000).unwrap();
+        // Replayed
+        assert!(used_signatures.claim(signer, first, 1_000, 1_100).is_err());
+        // Another nonce, or the same message of another signer
+        used_signatures.claim(signer, second, 1_000, 1_100).unwrap();
+        used_signatures
+            .claim(Address::ZERO, first, 1_000, 1_100)
+            .unwrap();
+
+        // Dropped once expired
+        used_signatures.claim(signer, first, 1_400, 1_400).unwrap();
+        assert_eq!(used_signatures.0.len(), 1);
+    }
+}
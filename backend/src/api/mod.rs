@@ -1,15 +1,34 @@
+pub mod auth;
+
 use std::str::FromStr;
 
-use actix_web::{HttpResponse, Responder, get, post, web};
-use alloy::primitives::Address;
+use actix_web::{HttpResponse, Responder, ResponseError, get, http::StatusCode, post, web};
+use alloy::primitives::{Address, U256};
+use dashmap::mapref::one::Ref;
 use rig::completion::Prompt;
 use tracing::info;
 
 use crate::{
+    backtest::{self, BacktestReport},
     config::CONFIG,
-    core::vault::YielderaVault,
+    core::{
+        self,
+        control::{VaultCommand, VaultControl},
+        preflight::TransactionError,
+        vault::YielderaVault,
+    },
+    db::{rebalances::DEFAULT_PAGE_SIZE, snapshots::DEFAULT_HISTORY_SECONDS},
+    error::YielderaError,
     state::AppState,
-    types::{AdminAssociateVaultTokensRequest, ApiErrorResponse, ChatRequest, VaultDetails},
+    types::{
+        AdminAuditPage, AdminForceRebalanceRequest, AdminTransactionResponse, ApiErrorResponse,
+        ApproveTxRequest, BacktestRequest, ChatRequest, CircuitBreaker, DepositQuote,
+        DepositQuoteRequest, DepositTxRequest, PaginationQuery, RebalanceHistoryPage,
+        RebalancePlan, RebalanceStatus, SnapshotInterval, TimeRangeQuery, TokenAmount,
+        UnsignedTransaction, UserPosition, VaultControlStatus, VaultDetails, VaultEventsPage,
+        VaultHistory, VaultHistoryQuery, VaultStats, WithdrawQuote, WithdrawQuoteRequest,
+        WithdrawTxRequest,
+    },
 };
 
 #[utoipa::path(
@@ -47,29 +66,17 @@ async fn handle_get_all_vaults(app_state: web::Data<AppState>) -> impl Responder
 }
 
 #[utoipa::path(
-    request_body = AdminAssociateVaultTokensRequest,
     responses(
         (status = 200, description = "Associate vault tokens", body = bool),
         (status = 401, description = "Unauthorized", body = ApiErrorResponse),
+        (status = 422, description = "Transaction reverted", body = ApiErrorResponse),
         (status = 500, description = "Internal server error", body = ApiErrorResponse),
+        (status = 502, description = "RPC node error", body = ApiErrorResponse),
+        (status = 504, description = "Transaction not mined", body = ApiErrorResponse),
     )
 )]
 #[post("/api/v1/admin/vaults/associate-tokens")]
-async fn handle_admin_associate_vault_tokens(
-    app_state: web::Data<AppState>,
-    body: web::Json<AdminAssociateVaultTokensRequest>,
-) -> impl Responder {
-    let password = body.password.clone();
-
-    let admin_password = CONFIG.admin_password.clone();
-
-    if password != admin_password {
-        return HttpResponse::Unauthorized().json(ApiErrorResponse {
-            message: "Unauthorized".to_string(),
-            error: "Wrong admin password".to_string(),
-        });
-    }
-
+async fn handle_admin_associate_vault_tokens(app_state: web::Data<AppState>) -> impl Responder {
     let all_vaults = app_state.all_vaults.clone();
 
     for (address, vault_details) in all_vaults {
@@ -86,52 +93,27 @@ async fn handle_admin_associate_vault_tokens(
         if !vault_details.is_vault_tokens_associated {
             let vault_contract = YielderaVault::new(vault_address, &app_state.evm_provider);
 
-            let associate_tx = match vault_contract.associateVaultTokens().send().await {
-                Ok(tx) => tx,
-                Err(e) => {
-                    return HttpResponse::InternalServerError().json(ApiErrorResponse {
-                        message: format!(
-                            "Failed to simulate associate vault tokens for : {:?}",
-                            address
-                        ),
-                        error: e.to_string(),
-                    });
-                }
-            };
-
-            let associate_tx_receipt = match associate_tx.get_receipt().await {
-                Ok(receipt) => receipt,
-                Err(e) => {
-                    return HttpResponse::InternalServerError().json(ApiErrorResponse {
-                        message: format!(
-                            "Failed to get associate vault tokens receipt for : {:?}",
-                            address
-                        ),
-                        error: e.to_string(),
-                    });
-                }
-            };
-
-            if !associate_tx_receipt.status() {
-                return HttpResponse::InternalServerError().json(ApiErrorResponse {
-                    message: format!(
-                        "Failed to associate vault tokens for : {:?}. Status retunned false",
-                        address
-                    ),
-                    error: format!(
-                        "Associate vault tokens transaction failed with receipt: {:?}",
-                        associate_tx_receipt
-                    ),
-                });
-            }
+            let associate_result =
+                match core::preflight::prepare(vault_contract.associateVaultTokens()).await {
+                    Ok(request) => app_state
+                        .tx_manager
+                        .submit(request)
+                        .await
+                        .and_then(|receipt| core::preflight::check_receipt(&receipt)),
+                    Err(e) => Err(e),
+                };
 
-            let mut new_vault_details = vault_details;
-            new_vault_details.is_vault_tokens_associated = true;
+            if let Err(e) = associate_result {
+                return error_response(
+                    &format!("Failed to associate vault tokens for : {:?}", address),
+                    e,
+                );
+            }
 
             // update the vault details in the app state
-            app_state
-                .all_vaults
-                .insert(address.to_string(), new_vault_details);
+            if let Some(mut vault_details) = app_state.all_vaults.get_mut(&address) {
+                vault_details.is_vault_tokens_associated = true;
+            }
 
             info!(
                 "Associated vault tokens Successfully for vault: {:?}",
@@ -143,6 +125,241 @@ async fn handle_admin_associate_vault_tokens(
     HttpResponse::Ok().body("true")
 }
 
+#[utoipa::path(
+    params(
+        PaginationQuery,
+    ),
+    responses(
+        (status = 200, description = "Admin requests, most recent first", body = AdminAuditPage),
+        (status = 401, description = "Unauthorized", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[get("/api/v1/admin/audit-log")]
+async fn handle_admin_get_audit_log(
+    app_state: web::Data<AppState>,
+    query: web::Query<PaginationQuery>,
+) -> impl Responder {
+    match app_state.db.list_audit_entries(
+        query.page.unwrap_or(1),
+        query.page_size.unwrap_or(DEFAULT_PAGE_SIZE),
+    ) {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(e) => error_response("Failed to read the audit log", e),
+    }
+}
+
+#[utoipa::path(
+    params(
+        ("address" = String, Path, description = "Vault address"),
+    ),
+    responses(
+        (status = 200, description = "Reset the vault circuit breaker, its rebalances resume", body = CircuitBreaker),
+        (status = 401, description = "Unauthorized", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[post("/api/v1/admin/vaults/{address}/circuit-breaker/reset")]
+async fn handle_admin_reset_circuit_breaker(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let vault_details = match managed_vault(&app_state, &path.into_inner()) {
+        Ok(vault_details) => vault_details,
+        Err(response) => return response,
+    };
+
+    match app_state.db.reset_circuit_breaker(&vault_details.address) {
+        Ok(breaker) => {
+            info!("Circuit breaker of vault {} reset", vault_details.address);
+            HttpResponse::Ok().json(breaker)
+        }
+        Err(e) => error_response("Failed to reset the circuit breaker", e),
+    }
+}
+
+#[utoipa::path(
+    params(
+        ("address" = String, Path, description = "Vault address"),
+    ),
+    responses(
+        (status = 200, description = "Pause the vault evaluations, the position is left as it is", body = VaultControlStatus),
+        (status = 401, description = "Unauthorized", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+    )
+)]
+#[post("/api/v1/admin/vaults/{address}/pause")]
+async fn handle_admin_pause_vault(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    set_vault_paused(&app_state, &path.into_inner(), true)
+}
+
+#[utoipa::path(
+    params(
+        ("address" = String, Path, description = "Vault address"),
+    ),
+    responses(
+        (status = 200, description = "Resume the vault evaluations from the next trigger", body = VaultControlStatus),
+        (status = 401, description = "Unauthorized", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+    )
+)]
+#[post("/api/v1/admin/vaults/{address}/resume")]
+async fn handle_admin_resume_vault(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    set_vault_paused(&app_state, &path.into_inner(), false)
+}
+
+#[utoipa::path(
+    params(
+        ("address" = String, Path, description = "Vault address"),
+    ),
+    responses(
+        (status = 202, description = "Evaluate the vault now instead of waiting for its next trigger", body = VaultControlStatus),
+        (status = 401, description = "Unauthorized", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 409, description = "Vault is paused", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[post("/api/v1/admin/vaults/{address}/evaluate")]
+async fn handle_admin_evaluate_vault(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let vault_details = match managed_vault(&app_state, &path.into_inner()) {
+        Ok(vault_details) => vault_details,
+        Err(response) => return response,
+    };
+
+    let control = match vault_control(&app_state, &vault_details.address) {
+        Ok(control) => control,
+        Err(response) => return response,
+    };
+
+    if control.is_paused() {
+        return HttpResponse::Conflict().json(ApiErrorResponse {
+            message: "Vault is paused".to_string(),
+            error: format!(
+                "Resume vault {} before evaluating it",
+                vault_details.address
+            ),
+        });
+    }
+
+    match control.send(VaultCommand::EvaluateNow) {
+        Ok(()) => HttpResponse::Accepted().json(VaultControlStatus {
+            vault_address: vault_details.address,
+            paused: false,
+        }),
+        Err(e) => error_response("Failed to request the vault evaluation", e),
+    }
+}
+
+#[utoipa::path(
+    request_body = AdminForceRebalanceRequest,
+    params(
+        ("address" = String, Path, description = "Vault address"),
+    ),
+    responses(
+        (status = 200, description = "Rebalance the vault to an explicit tick range, bypassing the strategy and the guardrails", body = AdminTransactionResponse),
+        (status = 400, description = "Invalid tick range", body = ApiErrorResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 409, description = "Spot price too far from the TWAP", body = ApiErrorResponse),
+        (status = 422, description = "Transaction reverted", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+        (status = 502, description = "RPC node error", body = ApiErrorResponse),
+        (status = 504, description = "Transaction not mined", body = ApiErrorResponse),
+    )
+)]
+#[post("/api/v1/admin/vaults/{address}/rebalance")]
+async fn handle_admin_force_rebalance(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<AdminForceRebalanceRequest>,
+) -> impl Responder {
+    let vault_details = match managed_vault(&app_state, &path.into_inner()) {
+        Ok(vault_details) => vault_details,
+        Err(response) => return response,
+    };
+
+    if let Err(e) = core::vault_spawn::check_tick_range(
+        body.lower_tick,
+        body.upper_tick,
+        vault_details.pool.tick_spacing,
+    ) {
+        return HttpResponse::BadRequest().json(ApiErrorResponse {
+            message: "Invalid tick range".to_string(),
+            error: e.to_string(),
+        });
+    }
+
+    let control = match vault_control(&app_state, &vault_details.address) {
+        Ok(control) => control,
+        Err(response) => return response,
+    };
+
+    let result = control
+        .request(|reply| VaultCommand::ForceRebalance {
+            lower_tick: body.lower_tick,
+            upper_tick: body.upper_tick,
+            reply,
+        })
+        .await;
+
+    match result {
+        Ok(tx_hash) => HttpResponse::Ok().json(AdminTransactionResponse { tx_hash }),
+        Err(e) => error_response("Failed to rebalance the vault", e),
+    }
+}
+
+#[utoipa::path(
+    params(
+        ("address" = String, Path, description = "Vault address"),
+    ),
+    responses(
+        (status = 200, description = "Pause the vault and remove all its liquidity, the tokens stay in the vault", body = AdminTransactionResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 422, description = "Transaction reverted", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+        (status = 502, description = "RPC node error", body = ApiErrorResponse),
+        (status = 504, description = "Transaction not mined", body = ApiErrorResponse),
+    )
+)]
+#[post("/api/v1/admin/vaults/{address}/burn-all-liquidity")]
+async fn handle_admin_burn_all_liquidity(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let vault_details = match managed_vault(&app_state, &path.into_inner()) {
+        Ok(vault_details) => vault_details,
+        Err(response) => return response,
+    };
+
+    let control = match vault_control(&app_state, &vault_details.address) {
+        Ok(control) => control,
+        Err(response) => return response,
+    };
+
+    // Without the pause the next evaluation would mint a new position
+    control.set_paused(true);
+
+    match control
+        .request(|reply| VaultCommand::BurnAllLiquidity { reply })
+        .await
+    {
+        Ok(tx_hash) => HttpResponse::Ok().json(AdminTransactionResponse { tx_hash }),
+        Err(e) => error_response("Failed to burn the vault liquidity", e),
+    }
+}
+
 #[utoipa::path(
     responses(
         (status = 200, description = "Chat", body = String),
@@ -170,3 +387,767 @@ async fn handle_chat(
         }),
     }
 }
+
+#[utoipa::path(
+    request_body = BacktestRequest,
+    params(
+        ("address" = String, Path, description = "Vault address"),
+    ),
+    responses(
+        (status = 200, description = "Backtest a strategy on the vault pool", body = BacktestReport),
+        (status = 400, description = "Invalid strategy (remote strategies such as ai are not backtested), candles or config", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[post("/api/v1/vaults/{address}/backtest")]
+async fn handle_backtest_vault(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<BacktestRequest>,
+) -> impl Responder {
+    let vault_address = path.into_inner();
+
+    let vault_details = match managed_vault(&app_state, &vault_address) {
+        Ok(vault_details) => vault_details,
+        Err(response) => return response,
+    };
+
+    let vault_config = CONFIG.toml_config.vault_config(&vault_address);
+
+    let strategy_name = body
+        .strategy
+        .clone()
+        .unwrap_or(CONFIG.toml_config.strategy_for(&vault_address).to_string());
+
+    let strategy_params = body
+        .strategy_params
+        .clone()
+        .or(vault_config.map(|vault_config| vault_config.strategy_params.clone()))
+        .unwrap_or_default();
+
+    let strategy = match app_state.strategies.build(&strategy_name, &strategy_params) {
+        Ok(strategy) => strategy,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(ApiErrorResponse {
+                message: format!("Failed to build strategy {}", strategy_name),
+                error: e.to_string(),
+            });
+        }
+    };
+
+    if strategy.is_remote() {
+        return HttpResponse::BadRequest().json(ApiErrorResponse {
+            message: format!("Strategy {} can not be backtested", strategy_name),
+            error: "Its decisions call a remote service".to_string(),
+        });
+    }
+
+    let candles = match (&body.candles, &body.candles_file) {
+        (Some(candles), _) => candles.clone(),
+        (None, Some(candles_file)) => match backtest::load_candles_from_data_dir(candles_file) {
+            Ok(candles) => candles,
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ApiErrorResponse {
+                    message: format!("Failed to load the candles file {}", candles_file),
+                    error: e.to_string(),
+                });
+            }
+        },
+        (None, None) => match backtest::load_candles_from_coingecko(&vault_details).await {
+            Ok(candles) => candles,
+            Err(e) => {
+                return error_response("Failed to load the pool candles from coingecko", e);
+            }
+        },
+    };
+
+    match backtest::run_backtest(strategy.as_ref(), &vault_details, &candles, &body.config).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => HttpResponse::BadRequest().json(ApiErrorResponse {
+            message: "Failed to run the backtest".to_string(),
+            error: e.to_string(),
+        }),
+    }
+}
+
+#[utoipa::path(
+    description = "Admin route: the plan runs the vault strategy, which calls a paid remote AI service for the `ai` strategy, so it is not open to anonymous callers",
+    params(
+        ("address" = String, Path, description = "Vault address"),
+    ),
+    responses(
+        (status = 200, description = "Dry run of the vault rebalance, no transaction is sent. `blocked_reason` is set when the rebalance limits or the TWAP check would postpone it", body = RebalancePlan),
+        (status = 401, description = "Unauthorized", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[get("/api/v1/admin/vaults/{address}/rebalance-plan")]
+async fn handle_admin_get_rebalance_plan(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let vault_address = path.into_inner();
+
+    let mut vault_details = match managed_vault(&app_state, &vault_address) {
+        Ok(vault_details) => vault_details,
+        Err(response) => return response,
+    };
+    let Some(vault_config) = CONFIG.toml_config.vault_config(&vault_address) else {
+        return error_response(
+            "Vault not found",
+            YielderaError::VaultNotFound(vault_address),
+        );
+    };
+
+    if let Err(e) =
+        core::vault::update_vault_live(&app_state.evm_provider, &mut vault_details).await
+    {
+        return error_response("Failed to update the vault live data", e);
+    }
+
+    let mut plan =
+        match core::vault_spawn::compute_rebalance_plan(&vault_details, vault_config, &app_state)
+            .await
+        {
+            Ok(plan) => plan,
+            Err(e) => return error_response("Failed to compute the rebalance plan", e),
+        };
+
+    if plan.rebalance_required {
+        match core::vault_spawn::blocked_reason(&app_state, &vault_details, vault_config).await {
+            Ok(blocked_reason) => plan.blocked_reason = blocked_reason,
+            Err(e) => return error_response("Failed to check the rebalance limits", e),
+        }
+    }
+
+    HttpResponse::Ok().json(plan)
+}
+
+#[utoipa::path(
+    params(
+        ("address" = String, Path, description = "Vault address"),
+        PaginationQuery,
+    ),
+    responses(
+        (status = 200, description = "Rebalance attempts of the vault, most recent first", body = RebalanceHistoryPage),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[get("/api/v1/vaults/{address}/rebalances")]
+async fn handle_get_vault_rebalances(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<PaginationQuery>,
+) -> impl Responder {
+    let vault_address = path.into_inner();
+
+    if let Err(response) = managed_vault(&app_state, &vault_address) {
+        return response;
+    }
+
+    match app_state.db.list_rebalances(
+        &vault_address,
+        query.page.unwrap_or(1),
+        query.page_size.unwrap_or(DEFAULT_PAGE_SIZE),
+    ) {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(e) => error_response("Failed to read the rebalance history", e),
+    }
+}
+
+#[utoipa::path(
+    params(
+        ("address" = String, Path, description = "Vault address"),
+        VaultHistoryQuery,
+    ),
+    responses(
+        (status = 200, description = "Vault TVL, share price and range time series, oldest first", body = VaultHistory),
+        (status = 400, description = "Invalid time range", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[get("/api/v1/vaults/{address}/history")]
+async fn handle_get_vault_history(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<VaultHistoryQuery>,
+) -> impl Responder {
+    let vault_address = path.into_inner();
+
+    if let Err(response) = managed_vault(&app_state, &vault_address) {
+        return response;
+    }
+
+    let to = query.to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let from = query.from.unwrap_or(to - DEFAULT_HISTORY_SECONDS);
+
+    if from > to {
+        return HttpResponse::BadRequest().json(ApiErrorResponse {
+            message: "Invalid time range".to_string(),
+            error: format!("from ({}) is after to ({})", from, to),
+        });
+    }
+
+    match app_state.db.vault_history(
+        &vault_address,
+        from,
+        to,
+        query.interval.unwrap_or(SnapshotInterval::OneHour),
+    ) {
+        Ok(history) => HttpResponse::Ok().json(history),
+        Err(e) => error_response("Failed to read the vault history", e),
+    }
+}
+
+#[utoipa::path(
+    params(
+        ("address" = String, Path, description = "Vault address"),
+        TimeRangeQuery,
+    ),
+    responses(
+        (status = 200, description = "Fee APR, net APY, time in range and rebalance count of the vault over the period", body = VaultStats),
+        (status = 400, description = "Invalid time range", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[get("/api/v1/vaults/{address}/stats")]
+async fn handle_get_vault_stats(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<TimeRangeQuery>,
+) -> impl Responder {
+    let vault_address = path.into_inner();
+
+    let vault_details = match managed_vault(&app_state, &vault_address) {
+        Ok(vault_details) => vault_details,
+        Err(response) => return response,
+    };
+
+    let to = query.to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let from = query.from.unwrap_or(to - DEFAULT_HISTORY_SECONDS);
+
+    if from > to {
+        return HttpResponse::BadRequest().json(ApiErrorResponse {
+            message: "Invalid time range".to_string(),
+            error: format!("from ({}) is after to ({})", from, to),
+        });
+    }
+
+    let stats = app_state
+        .db
+        .vault_snapshots(&vault_address, from, to)
+        .and_then(|snapshots| {
+            let rebalance_count = app_state.db.count_rebalances(
+                &vault_address,
+                RebalanceStatus::Success,
+                from,
+                to,
+            )?;
+
+            Ok(VaultStats::from_snapshots(
+                &vault_address,
+                from,
+                to,
+                &snapshots,
+                vault_details.fees.performance_fee,
+                rebalance_count,
+            ))
+        });
+
+    match stats {
+        Ok(stats) => HttpResponse::Ok().json(stats),
+        Err(e) => error_response("Failed to compute the vault stats", e),
+    }
+}
+
+#[utoipa::path(
+    params(
+        ("address" = String, Path, description = "Vault address"),
+        PaginationQuery,
+    ),
+    responses(
+        (status = 200, description = "Indexed events of the vault, most recent first", body = VaultEventsPage),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[get("/api/v1/vaults/{address}/events")]
+async fn handle_get_vault_events(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<PaginationQuery>,
+) -> impl Responder {
+    let vault_address = path.into_inner();
+
+    if let Err(response) = managed_vault(&app_state, &vault_address) {
+        return response;
+    }
+
+    match app_state.db.list_events(
+        &vault_address,
+        query.page.unwrap_or(1),
+        query.page_size.unwrap_or(DEFAULT_PAGE_SIZE),
+    ) {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(e) => error_response("Failed to read the vault events", e),
+    }
+}
+
+#[utoipa::path(
+    params(
+        ("address" = String, Path, description = "Account address"),
+    ),
+    responses(
+        (status = 200, description = "Shares, redeemable amounts, cost basis and PnL versus HODL of the account in each vault", body = Vec<UserPosition>),
+        (status = 400, description = "Invalid address", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[get("/api/v1/users/{address}/positions")]
+async fn handle_get_user_positions(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let account = match Address::from_str(&path.into_inner()) {
+        Ok(account) => account,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(ApiErrorResponse {
+                message: "Invalid address".to_string(),
+                error: e.to_string(),
+            });
+        }
+    };
+
+    match core::positions::get_user_positions(&app_state, account).await {
+        Ok(positions) => HttpResponse::Ok().json(positions),
+        Err(e) => error_response("Failed to get the user positions", e),
+    }
+}
+
+#[utoipa::path(
+    request_body = DepositQuoteRequest,
+    params(
+        ("address" = String, Path, description = "Vault address"),
+    ),
+    responses(
+        (status = 200, description = "Shares minted by the deposit and the native HBAR value to send", body = DepositQuote),
+        (status = 400, description = "Invalid amounts", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[post("/api/v1/vaults/{address}/quote-deposit")]
+async fn handle_quote_deposit(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<DepositQuoteRequest>,
+) -> impl Responder {
+    let vault_address = path.into_inner();
+
+    let vault_details = match managed_vault(&app_state, &vault_address) {
+        Ok(vault_details) => vault_details,
+        Err(response) => return response,
+    };
+
+    let (amount0, amount1) =
+        match parse_deposit_amounts(&vault_details, &body.amount0, &body.amount1) {
+            Ok(amounts) => amounts,
+            Err(response) => return response,
+        };
+
+    match core::quotes::quote_deposit(&app_state.evm_provider, &vault_details, amount0, amount1)
+        .await
+    {
+        Ok(quote) => HttpResponse::Ok().json(quote),
+        Err(e) => error_response("Failed to quote the deposit", e),
+    }
+}
+
+#[utoipa::path(
+    request_body = WithdrawQuoteRequest,
+    params(
+        ("address" = String, Path, description = "Vault address"),
+    ),
+    responses(
+        (status = 200, description = "Token amounts returned for the shares", body = WithdrawQuote),
+        (status = 400, description = "Invalid shares", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[post("/api/v1/vaults/{address}/quote-withdraw")]
+async fn handle_quote_withdraw(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<WithdrawQuoteRequest>,
+) -> impl Responder {
+    let vault_address = path.into_inner();
+
+    let vault_details = match managed_vault(&app_state, &vault_address) {
+        Ok(vault_details) => vault_details,
+        Err(response) => return response,
+    };
+
+    let shares = match parse_shares(&vault_details, &body.shares) {
+        Ok(shares) => shares,
+        Err(response) => return response,
+    };
+
+    match core::quotes::quote_withdraw(&app_state.evm_provider, &vault_details, shares).await {
+        Ok(quote) => HttpResponse::Ok().json(quote),
+        Err(e) => error_response("Failed to quote the withdraw", e),
+    }
+}
+
+#[utoipa::path(
+    request_body = DepositTxRequest,
+    params(
+        ("address" = String, Path, description = "Vault address"),
+    ),
+    responses(
+        (status = 200, description = "Unsigned deposit transaction", body = UnsignedTransaction),
+        (status = 400, description = "Invalid address or amounts", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[post("/api/v1/vaults/{address}/transactions/deposit")]
+async fn handle_build_deposit_tx(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<DepositTxRequest>,
+) -> impl Responder {
+    let vault_address = path.into_inner();
+
+    let vault_details = match managed_vault(&app_state, &vault_address) {
+        Ok(vault_details) => vault_details,
+        Err(response) => return response,
+    };
+
+    let (from, receiver) = match parse_sender_and_receiver(&body.from, body.receiver.as_deref()) {
+        Ok(addresses) => addresses,
+        Err(response) => return response,
+    };
+
+    let (amount0, amount1) =
+        match parse_deposit_amounts(&vault_details, &body.amount0, &body.amount1) {
+            Ok(amounts) => amounts,
+            Err(response) => return response,
+        };
+
+    match core::transactions::build_deposit_tx(
+        &app_state.evm_provider,
+        &vault_details,
+        from,
+        receiver,
+        amount0,
+        amount1,
+    )
+    .await
+    {
+        Ok(transaction) => HttpResponse::Ok().json(transaction),
+        Err(e) => error_response("Failed to build the deposit transaction", e),
+    }
+}
+
+#[utoipa::path(
+    request_body = WithdrawTxRequest,
+    params(
+        ("address" = String, Path, description = "Vault address"),
+    ),
+    responses(
+        (status = 200, description = "Unsigned withdraw transaction", body = UnsignedTransaction),
+        (status = 400, description = "Invalid address or shares", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[post("/api/v1/vaults/{address}/transactions/withdraw")]
+async fn handle_build_withdraw_tx(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<WithdrawTxRequest>,
+) -> impl Responder {
+    let vault_address = path.into_inner();
+
+    let vault_details = match managed_vault(&app_state, &vault_address) {
+        Ok(vault_details) => vault_details,
+        Err(response) => return response,
+    };
+
+    let (from, receiver) = match parse_sender_and_receiver(&body.from, body.receiver.as_deref()) {
+        Ok(addresses) => addresses,
+        Err(response) => return response,
+    };
+
+    let shares = match parse_shares(&vault_details, &body.shares) {
+        Ok(shares) => shares,
+        Err(response) => return response,
+    };
+
+    match core::transactions::build_withdraw_tx(
+        &app_state.evm_provider,
+        &vault_details,
+        from,
+        receiver,
+        shares,
+    )
+    .await
+    {
+        Ok(transaction) => HttpResponse::Ok().json(transaction),
+        Err(e) => error_response("Failed to build the withdraw transaction", e),
+    }
+}
+
+#[utoipa::path(
+    request_body = ApproveTxRequest,
+    params(
+        ("address" = String, Path, description = "Vault address, the spender"),
+    ),
+    responses(
+        (status = 200, description = "Unsigned ERC20 approve transaction", body = UnsignedTransaction),
+        (status = 400, description = "Invalid address, token or amount", body = ApiErrorResponse),
+        (status = 404, description = "Vault not found", body = ApiErrorResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorResponse),
+    )
+)]
+#[post("/api/v1/vaults/{address}/transactions/approve")]
+async fn handle_build_approve_tx(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    body: web::Json<ApproveTxRequest>,
+) -> impl Responder {
+    let vault_address = path.into_inner();
+
+    let vault_details = match managed_vault(&app_state, &vault_address) {
+        Ok(vault_details) => vault_details,
+        Err(response) => return response,
+    };
+
+    let (from, _) = match parse_sender_and_receiver(&body.from, None) {
+        Ok(addresses) => addresses,
+        Err(response) => return response,
+    };
+
+    let Some(token) = [&vault_details.pool.token0, &vault_details.pool.token1]
+        .into_iter()
+        .find(|token| token.address.eq_ignore_ascii_case(&body.token))
+        .cloned()
+    else {
+        return HttpResponse::BadRequest().json(ApiErrorResponse {
+            message: "Invalid token".to_string(),
+            error: format!("{} is not a token of the vault", body.token),
+        });
+    };
+
+    // The WHBAR side is deposited with the transaction value, there is nothing to approve
+    if token.is_native_wrapper {
+        return HttpResponse::BadRequest().json(ApiErrorResponse {
+            message: "Invalid token".to_string(),
+            error: format!(
+                "{} is deposited as native HBAR, no approval needed",
+                token.symbol
+            ),
+        });
+    }
+
+    let amount = match parse_amount(&body.amount, token.decimals, "Invalid amount") {
+        Ok(amount) if !amount.is_zero() => amount,
+        Ok(_) => {
+            return HttpResponse::BadRequest().json(ApiErrorResponse {
+                message: "Invalid amount".to_string(),
+                error: "Amount must be above 0".to_string(),
+            });
+        }
+        Err(response) => return response,
+    };
+
+    match core::transactions::build_approve_tx(
+        &app_state.evm_provider,
+        &vault_details,
+        from,
+        &token,
+        amount,
+    )
+    .await
+    {
+        Ok(transaction) => HttpResponse::Ok().json(transaction),
+        Err(e) => error_response("Failed to build the approve transaction", e),
+    }
+}
+
+/// Parse the signer of a transaction and its receiver, which defaults to the signer
+fn parse_sender_and_receiver(
+    from: &str,
+    receiver: Option<&str>,
+) -> Result<(Address, Address), HttpResponse> {
+    let parse = |address: &str| {
+        Address::from_str(address).map_err(|e| {
+            HttpResponse::BadRequest().json(ApiErrorResponse {
+                message: "Invalid address".to_string(),
+                error: format!("{}: {}", address, e),
+            })
+        })
+    };
+
+    let from = parse(from)?;
+    let receiver = match receiver {
+        Some(receiver) => parse(receiver)?,
+        None => from,
+    };
+
+    Ok((from, receiver))
+}
+
+/// Raw amount of a request in the token decimals, 400 when it can not be parsed
+fn parse_amount(amount: &TokenAmount, decimals: u8, message: &str) -> Result<U256, HttpResponse> {
+    amount.to_raw(decimals).map_err(|e| {
+        HttpResponse::BadRequest().json(ApiErrorResponse {
+            message: message.to_string(),
+            error: e.to_string(),
+        })
+    })
+}
+
+/// Raw deposit amounts of a request, 400 when invalid or both zero
+fn parse_deposit_amounts(
+    vault_details: &VaultDetails,
+    amount0: &TokenAmount,
+    amount1: &TokenAmount,
+) -> Result<(U256, U256), HttpResponse> {
+    let amount0 = parse_amount(
+        amount0,
+        vault_details.pool.token0.decimals,
+        "Invalid amounts",
+    )?;
+    let amount1 = parse_amount(
+        amount1,
+        vault_details.pool.token1.decimals,
+        "Invalid amounts",
+    )?;
+
+    if amount0.is_zero() && amount1.is_zero() {
+        return Err(HttpResponse::BadRequest().json(ApiErrorResponse {
+            message: "Invalid amounts".to_string(),
+            error: "At least one amount must be above 0".to_string(),
+        }));
+    }
+
+    Ok((amount0, amount1))
+}
+
+/// Raw shares of a request, 400 when invalid or zero
+fn parse_shares(vault_details: &VaultDetails, shares: &TokenAmount) -> Result<U256, HttpResponse> {
+    let shares = parse_amount(shares, vault_details.decimals, "Invalid shares")?;
+
+    if shares.is_zero() {
+        return Err(HttpResponse::BadRequest().json(ApiErrorResponse {
+            message: "Invalid shares".to_string(),
+            error: "Shares must be above 0".to_string(),
+        }));
+    }
+
+    Ok(shares)
+}
+
+/// Managed vault of a request path, 404 when unknown
+fn managed_vault(app_state: &AppState, vault_address: &str) -> Result<VaultDetails, HttpResponse> {
+    find_vault(app_state, vault_address).ok_or_else(|| {
+        error_response(
+            "Vault not found",
+            YielderaError::VaultNotFound(vault_address.to_string()),
+        )
+    })
+}
+
+/// Control of the vault loop, 404 when no loop was started for the vault
+fn vault_control<'a>(
+    app_state: &'a AppState,
+    vault_address: &str,
+) -> Result<Ref<'a, String, VaultControl>, HttpResponse> {
+    app_state
+        .vault_controls
+        .get(&vault_address.to_lowercase())
+        .ok_or_else(|| {
+            HttpResponse::NotFound().json(ApiErrorResponse {
+                message: "Vault loop not found".to_string(),
+                error: format!("No management loop running for vault {}", vault_address),
+            })
+        })
+}
+
+fn set_vault_paused(app_state: &AppState, vault_address: &str, paused: bool) -> HttpResponse {
+    let vault_details = match managed_vault(app_state, vault_address) {
+        Ok(vault_details) => vault_details,
+        Err(response) => return response,
+    };
+
+    let control = match vault_control(app_state, &vault_details.address) {
+        Ok(control) => control,
+        Err(response) => return response,
+    };
+
+    control.set_paused(paused);
+    info!(
+        "Vault {} {}",
+        vault_details.address,
+        if paused { "paused" } else { "resumed" }
+    );
+
+    HttpResponse::Ok().json(VaultControlStatus {
+        vault_address: vault_details.address,
+        paused,
+    })
+}
+
+impl ResponseError for YielderaError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            YielderaError::VaultNotFound(_) => StatusCode::NOT_FOUND,
+            YielderaError::Blocked(_) => StatusCode::CONFLICT,
+            YielderaError::Transaction(TransactionError::NotMined { .. }) => {
+                StatusCode::GATEWAY_TIMEOUT
+            }
+            YielderaError::Transaction(TransactionError::Rpc(_))
+            | YielderaError::Rpc(_)
+            | YielderaError::Strategy { .. }
+            | YielderaError::Notification(_) => StatusCode::BAD_GATEWAY,
+            YielderaError::Transaction(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            YielderaError::Math(_)
+            | YielderaError::Config(_)
+            | YielderaError::Database(_)
+            | YielderaError::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ApiErrorResponse {
+            message: "Request failed".to_string(),
+            error: self.to_string(),
+        })
+    }
+}
+
+/// Response of a failed request, its status code depends on the kind of error
+fn error_response(message: &str, error: impl Into<YielderaError>) -> HttpResponse {
+    let error = error.into();
+
+    HttpResponse::build(error.status_code()).json(ApiErrorResponse {
+        message: message.to_string(),
+        error: error.to_string(),
+    })
+}
+
+/// Find a managed vault by address, ignoring the address case
+fn find_vault(app_state: &AppState, vault_address: &str) -> Option<VaultDetails> {
+    app_state
+        .all_vaults
+        .iter()
+        .find(|entry| entry.key().eq_ignore_ascii_case(vault_address))
+        .map(|entry| entry.value().clone())
+}
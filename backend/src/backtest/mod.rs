@@ -0,0 +1,783 @@
+/*
+    Backtesting engine for the range strategies.
+    It replays OHLCV candles (price1 = token1 per token0) through a strategy and simulates the vault position:
+    - the position value is computed with the uniswap v3 liquidity math at each candle close
+    - the fees are estimated from the candle volume (read in token1), the pool fee and the share of the in range liquidity owned by the vault
+    - every rebalance pays the swap fee on the swapped amount plus a fixed transaction cost
+    All values are reported in token1.
+*/
+
+use std::{fs, path::Path};
+
+use alloy::primitives::{U256, utils::format_units};
+use color_eyre::eyre::Result;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    config::{BACKTEST_DATA_DIR, BACKTEST_MAX_CANDLES},
+    core,
+    helpers::math::{
+        self,
+        uniswap_v3::{
+            liquidity_math::get_amounts_for_liquidity, tick_math::get_sqrt_ratio_at_tick,
+        },
+    },
+    strategies::Strategy,
+    types::{CoingeckoOhlcvRes, MarketContext, OhlcvEntry, Position, VaultDetails, VaultTVL},
+};
+
+/// Liquidity used to measure the token amounts of one "unit" of liquidity on a range
+const LIQUIDITY_UNIT: u128 = 1_000_000_000_000_000_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BacktestConfig {
+    /// Vault balances at the start of the backtest
+    pub initial_amount0: f64,
+    pub initial_amount1: f64,
+    /// Share (0 to 1) of the pool in range liquidity owned by the vault position
+    #[serde(default = "default_liquidity_share")]
+    pub liquidity_share: f64,
+    /// Fixed cost of a rebalance transaction, in token1
+    #[serde(default)]
+    pub rebalance_cost1: f64,
+    /// While the position is in range, skip the rebalance if the uncollected fees are below these amounts
+    #[serde(default)]
+    pub min_fees0: f64,
+    #[serde(default)]
+    pub min_fees1: f64,
+    /// Number of past candles handed to the strategy
+    #[serde(default = "default_lookback_candles")]
+    pub lookback_candles: usize,
+    /// Ask the strategy for a decision every `decision_interval` candles
+    #[serde(default = "default_decision_interval")]
+    pub decision_interval: usize,
+}
+
+fn default_liquidity_share() -> f64 {
+    0.01
+}
+
+fn default_lookback_candles() -> usize {
+    180
+}
+
+fn default_decision_interval() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BacktestReport {
+    pub strategy: String,
+    pub candles: usize,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+    pub start_price: f64,
+    pub end_price: f64,
+    pub rebalances: u32,
+    /// Fees earned by the position, in token1
+    pub fees_earned: f64,
+    /// Swap fees and transaction costs paid on rebalances, in token1
+    pub rebalance_costs: f64,
+    /// Loss of the position against HODL without fees and rebalance costs, in %
+    pub impermanent_loss: f64,
+    /// Position value plus uncollected fees at the end, in token1
+    pub final_value: f64,
+    /// Value of the initial balances at the end price, in token1
+    pub hodl_value: f64,
+    /// Return of the vault against its initial value, in %
+    pub net_return: f64,
+    /// Return of the vault against HODL, in %
+    pub net_return_vs_hodl: f64,
+    /// Share of the candles where the position was in range, in %
+    pub time_in_range: f64,
+}
+
+/// Position held by the simulated vault
+#[derive(Debug, Clone)]
+struct SimulatedPosition {
+    lower_tick: i32,
+    upper_tick: i32,
+    liquidity: u128,
+}
+
+/// Replay the candles through the strategy, starting from the given vault (pool, tokens and tick spacing)
+pub async fn run_backtest(
+    strategy: &dyn Strategy,
+    vault_details: &VaultDetails,
+    candles: &[OhlcvEntry],
+    config: &BacktestConfig,
+) -> Result<BacktestReport> {
+    if candles.is_empty() {
+        return Err(color_eyre::eyre::eyre!("No candles to backtest"));
+    }
+    if candles.len() > BACKTEST_MAX_CANDLES {
+        return Err(color_eyre::eyre::eyre!(
+            "Too many candles to backtest: {} (max {})",
+            candles.len(),
+            BACKTEST_MAX_CANDLES
+        ));
+    }
+    if config.decision_interval == 0 {
+        return Err(color_eyre::eyre::eyre!(
+            "The decision interval must be at least 1 candle"
+        ));
+    }
+
+    let mut candles = candles.to_vec();
+    candles.sort_by_key(|candle| candle.timestamp());
+
+    let token0_decimals = vault_details.pool.token0.decimals;
+    let token1_decimals = vault_details.pool.token1.decimals;
+    // pool fee is stored in %
+    let fee_rate = vault_details.pool.fee / 100.0;
+    let decision_interval = config.decision_interval;
+
+    let start_price = candles[0].close();
+    let end_price = candles[candles.len() - 1].close();
+
+    let mut amount0 = config.initial_amount0;
+    let mut amount1 = config.initial_amount1;
+    let mut position: Option<SimulatedPosition> = None;
+    let mut uncollected_fees0 = 0.0;
+    let mut uncollected_fees1 = 0.0;
+
+    let mut rebalances = 0;
+    let mut fees_earned = 0.0;
+    let mut rebalance_costs = 0.0;
+    let mut candles_in_range = 0;
+
+    for (index, candle) in candles.iter().enumerate() {
+        let price = candle.close();
+
+        if price <= 0.0 {
+            warn!(
+                "Skipping candle {} with a non positive price",
+                candle.timestamp()
+            );
+            continue;
+        }
+
+        let current_tick = math::price1_to_tick(price, token0_decimals, token1_decimals);
+
+        // Value the current position at the candle close
+        if let Some(current_position) = &position {
+            (amount0, amount1) = position_amounts(
+                current_position,
+                current_tick,
+                token0_decimals,
+                token1_decimals,
+            )?;
+        }
+
+        if index % decision_interval == 0 {
+            let simulated_vault = simulate_vault(
+                vault_details,
+                position.as_ref(),
+                price,
+                current_tick,
+                (amount0, amount1),
+                (uncollected_fees0, uncollected_fees1),
+            )?;
+
+            let lookback_start = (index + 1).saturating_sub(config.lookback_candles);
+            let market = MarketContext {
+                ohlcv: Some(candles[lookback_start..=index].to_vec()),
+            };
+
+            let decision = strategy.decide(&simulated_vault, &market).await?;
+            let new_range = (
+                decision.tick_range.lower_tick,
+                decision.tick_range.upper_tick,
+            );
+
+            let is_same_range = position
+                .as_ref()
+                .is_some_and(|current| (current.lower_tick, current.upper_tick) == new_range);
+
+            let is_in_range = position.as_ref().is_some_and(|current| {
+                math::is_tick_in_range(current_tick, current.lower_tick, current.upper_tick)
+            });
+
+            let is_low_fees = is_in_range
+                && (uncollected_fees0 < config.min_fees0 || uncollected_fees1 < config.min_fees1);
+
+            if !decision.rebalance_required || is_same_range || is_low_fees {
+                debug!(
+                    "Backtest keeps the current position at {}",
+                    candle.timestamp()
+                );
+            } else if new_range.0 >= new_range.1 {
+                warn!(
+                    "Strategy {} returned an invalid range {:?} at {}",
+                    strategy.name(),
+                    new_range,
+                    candle.timestamp()
+                );
+            } else {
+                // Collect the fees, then swap the balances to the new range ratio
+                let total_value =
+                    amount0 * price + amount1 + uncollected_fees0 * price + uncollected_fees1;
+
+                let target_value0 = range_value0_ratio(
+                    current_tick,
+                    new_range.0,
+                    new_range.1,
+                    token0_decimals,
+                    token1_decimals,
+                    price,
+                )? * total_value;
+                let swapped_value = (target_value0 - (amount0 + uncollected_fees0) * price).abs();
+                let cost = swapped_value * fee_rate + config.rebalance_cost1;
+                let deployed_value = (total_value - cost).max(0.0);
+
+                let new_position = deploy_value(
+                    deployed_value,
+                    current_tick,
+                    new_range.0,
+                    new_range.1,
+                    token0_decimals,
+                    token1_decimals,
+                    price,
+                )?;
+
+                (amount0, amount1) = position_amounts(
+                    &new_position,
+                    current_tick,
+                    token0_decimals,
+                    token1_decimals,
+                )?;
+
+                debug!(
+                    "Backtest rebalance at {} to range {:?}, cost: {}",
+                    candle.timestamp(),
+                    new_range,
+                    cost
+                );
+
+                position = Some(new_position);
+                uncollected_fees0 = 0.0;
+                uncollected_fees1 = 0.0;
+                rebalances += 1;
+                rebalance_costs += cost;
+            }
+        }
+
+        // Accrue the candle fees on the part of the candle price range covered by the position
+        if let Some(current_position) = &position {
+            let in_range_fraction =
+                in_range_fraction(candle, current_position, token0_decimals, token1_decimals)?;
+
+            if math::is_tick_in_range(
+                current_tick,
+                current_position.lower_tick,
+                current_position.upper_tick,
+            ) {
+                candles_in_range += 1;
+            }
+
+            let candle_fees =
+                candle.volume() * fee_rate * config.liquidity_share * in_range_fraction;
+
+            // Swaps happen in both directions, so fees are split between the two tokens
+            uncollected_fees0 += candle_fees / 2.0 / price;
+            uncollected_fees1 += candle_fees / 2.0;
+            fees_earned += candle_fees;
+        }
+    }
+
+    let final_value =
+        amount0 * end_price + amount1 + uncollected_fees0 * end_price + uncollected_fees1;
+    let initial_value = config.initial_amount0 * start_price + config.initial_amount1;
+    let hodl_value = config.initial_amount0 * end_price + config.initial_amount1;
+
+    let (impermanent_loss, net_return_vs_hodl) = if hodl_value > 0.0 {
+        (
+            ((final_value - fees_earned + rebalance_costs) / hodl_value - 1.0) * 100.0,
+            (final_value / hodl_value - 1.0) * 100.0,
+        )
+    } else {
+        (0.0, 0.0)
+    };
+
+    let net_return = if initial_value > 0.0 {
+        (final_value / initial_value - 1.0) * 100.0
+    } else {
+        0.0
+    };
+
+    Ok(BacktestReport {
+        strategy: strategy.name().to_string(),
+        candles: candles.len(),
+        start_timestamp: candles[0].timestamp(),
+        end_timestamp: candles[candles.len() - 1].timestamp(),
+        start_price,
+        end_price,
+        rebalances,
+        fees_earned,
+        rebalance_costs,
+        impermanent_loss,
+        final_value,
+        hodl_value,
+        net_return,
+        net_return_vs_hodl,
+        time_in_range: candles_in_range as f64 / candles.len() as f64 * 100.0,
+    })
+}
+
+/// Build the vault state seen by the strategy at a given candle
+fn simulate_vault(
+    vault_details: &VaultDetails,
+    position: Option<&SimulatedPosition>,
+    price: f64,
+    current_tick: i32,
+    (amount0, amount1): (f64, f64),
+    (fees0, fees1): (f64, f64),
+) -> Result<VaultDetails> {
+    let mut simulated_vault = vault_details.clone();
+
+    simulated_vault.pool.current_tick = current_tick;
+    simulated_vault.pool.sqrt_price_x96 = get_sqrt_ratio_at_tick(current_tick)?;
+    simulated_vault.pool.price1 = price;
+    simulated_vault.pool.price0 = 1.0 / price;
+
+    match position {
+        Some(position) => {
+            simulated_vault.is_active = true;
+            simulated_vault.lower_tick = position.lower_tick;
+            simulated_vault.upper_tick = position.upper_tick;
+            simulated_vault.position = Position {
+                tick_lower: position.lower_tick,
+                tick_upper: position.upper_tick,
+                liquidity: position.liquidity,
+                amount0,
+                amount1,
+                fees0,
+                fees1,
+            };
+        }
+        None => {
+            simulated_vault.is_active = false;
+            simulated_vault.lower_tick = 0;
+            simulated_vault.upper_tick = 0;
+            simulated_vault.position = Position::default();
+        }
+    }
+
+    simulated_vault.tvl = VaultTVL {
+        tvl0: amount0 + fees0,
+        tvl1: amount1 + fees1,
+    };
+
+    Ok(simulated_vault)
+}
+
+/// Token amounts of the position at the given tick
+fn position_amounts(
+    position: &SimulatedPosition,
+    current_tick: i32,
+    token0_decimals: u8,
+    token1_decimals: u8,
+) -> Result<(f64, f64)> {
+    liquidity_amounts(
+        position.liquidity,
+        current_tick,
+        position.lower_tick,
+        position.upper_tick,
+        token0_decimals,
+        token1_decimals,
+    )
+}
+
+fn liquidity_amounts(
+    liquidity: u128,
+    current_tick: i32,
+    lower_tick: i32,
+    upper_tick: i32,
+    token0_decimals: u8,
+    token1_decimals: u8,
+) -> Result<(f64, f64)> {
+    let (amount0, amount1): (U256, U256) = get_amounts_for_liquidity(
+        get_sqrt_ratio_at_tick(current_tick)?,
+        get_sqrt_ratio_at_tick(lower_tick)?,
+        get_sqrt_ratio_at_tick(upper_tick)?,
+        liquidity,
+    )?;
+
+    let amount0: f64 = format_units(amount0, token0_decimals)?.parse()?;
+    let amount1: f64 = format_units(amount1, token1_decimals)?.parse()?;
+
+    Ok((amount0, amount1))
+}
+
+/// Token amounts and token1 value of one liquidity unit on a range at the given tick
+fn range_unit_amounts(
+    current_tick: i32,
+    lower_tick: i32,
+    upper_tick: i32,
+    token0_decimals: u8,
+    token1_decimals: u8,
+    price: f64,
+) -> Result<(f64, f64, f64)> {
+    let (unit_amount0, unit_amount1) = liquidity_amounts(
+        LIQUIDITY_UNIT,
+        current_tick,
+        lower_tick,
+        upper_tick,
+        token0_decimals,
+        token1_decimals,
+    )?;
+
+    let unit_value = unit_amount0 * price + unit_amount1;
+
+    if unit_value <= 0.0 {
+        return Err(color_eyre::eyre::eyre!(
+            "Range [{}, {}] has no value at tick {}",
+            lower_tick,
+            upper_tick,
+            current_tick
+        ));
+    }
+
+    Ok((unit_amount0, unit_amount1, unit_value))
+}
+
+/// Share of the position value held in token0 for a range at the given tick
+fn range_value0_ratio(
+    current_tick: i32,
+    lower_tick: i32,
+    upper_tick: i32,
+    token0_decimals: u8,
+    token1_decimals: u8,
+    price: f64,
+) -> Result<f64> {
+    let (unit_amount0, _, unit_value) = range_unit_amounts(
+        current_tick,
+        lower_tick,
+        upper_tick,
+        token0_decimals,
+        token1_decimals,
+        price,
+    )?;
+
+    Ok(unit_amount0 * price / unit_value)
+}
+
+/// Open a position worth `value` (in token1) on the given range
+fn deploy_value(
+    value: f64,
+    current_tick: i32,
+    lower_tick: i32,
+    upper_tick: i32,
+    token0_decimals: u8,
+    token1_decimals: u8,
+    price: f64,
+) -> Result<SimulatedPosition> {
+    let (_, _, unit_value) = range_unit_amounts(
+        current_tick,
+        lower_tick,
+        upper_tick,
+        token0_decimals,
+        token1_decimals,
+        price,
+    )?;
+
+    let liquidity = (value / unit_value * LIQUIDITY_UNIT as f64) as u128;
+
+    Ok(SimulatedPosition {
+        lower_tick,
+        upper_tick,
+        liquidity,
+    })
+}
+
+/// Fraction of the candle price range [low, high] covered by the position range
+fn in_range_fraction(
+    candle: &OhlcvEntry,
+    position: &SimulatedPosition,
+    token0_decimals: u8,
+    token1_decimals: u8,
+) -> Result<f64> {
+    let lower_price = math::tick_to_price(position.lower_tick, token0_decimals, token1_decimals)?;
+    let upper_price = math::tick_to_price(position.upper_tick, token0_decimals, token1_decimals)?;
+
+    let low = candle.low().min(candle.close());
+    let high = candle.high().max(candle.close());
+
+    if high - low <= f64::EPSILON {
+        let is_in_range = low >= lower_price && low <= upper_price;
+        return Ok(if is_in_range { 1.0 } else { 0.0 });
+    }
+
+    let overlap = high.min(upper_price) - low.max(lower_price);
+
+    Ok((overlap / (high - low)).clamp(0.0, 1.0))
+}
+
+/// Load candles from a local `.csv` (timestamp,open,high,low,close,volume) or `.json` file.
+/// JSON files can hold either a list of candles or a raw CoinGecko OHLCV response.
+pub fn load_candles_from_file(path: &Path) -> Result<Vec<OhlcvEntry>> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    match extension.as_str() {
+        "csv" => {
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(true)
+                .from_path(path)?;
+            let mut candles = Vec::new();
+
+            for record in reader.records() {
+                let record = record?;
+
+                if record.len() < 6 {
+                    return Err(color_eyre::eyre::eyre!(
+                        "Invalid candle row {:?}, expected timestamp,open,high,low,close,volume",
+                        record
+                    ));
+                }
+
+                candles.push(OhlcvEntry(
+                    record[0].trim().parse()?,
+                    record[1].trim().parse()?,
+                    record[2].trim().parse()?,
+                    record[3].trim().parse()?,
+                    record[4].trim().parse()?,
+                    record[5].trim().parse()?,
+                ));
+            }
+
+            Ok(candles)
+        }
+        "json" => {
+            let raw = fs::read_to_string(path)?;
+
+            if let Ok(coingecko_res) = serde_json::from_str::<CoingeckoOhlcvRes>(&raw) {
+                return Ok(coingecko_res.data.attributes.ohlcv_list);
+            }
+
+            Ok(serde_json::from_str::<Vec<OhlcvEntry>>(&raw)?)
+        }
+        _ => Err(color_eyre::eyre::eyre!(
+            "Unsupported candles file {:?}, expected a .csv or .json file",
+            path
+        )),
+    }
+}
+
+/// Load a candles file by name from the `BACKTEST_DATA_DIR` directory
+pub fn load_candles_from_data_dir(file_name: &str) -> Result<Vec<OhlcvEntry>> {
+    let is_plain_file_name = Path::new(file_name)
+        .file_name()
+        .is_some_and(|name| name == file_name);
+
+    if !is_plain_file_name {
+        return Err(color_eyre::eyre::eyre!(
+            "Invalid candles file name {:?}, expected a file name inside {}",
+            file_name,
+            BACKTEST_DATA_DIR
+        ));
+    }
+
+    load_candles_from_file(&Path::new(BACKTEST_DATA_DIR).join(file_name))
+}
+
+/// Load the daily candles of the vault pool from CoinGecko
+pub async fn load_candles_from_coingecko(vault_details: &VaultDetails) -> Result<Vec<OhlcvEntry>> {
+    let ohlcv_res =
+        core::coingecko::get_pool_ohlcv_data(&vault_details.pool.address, vault_details).await?;
+
+    Ok(ohlcv_res.data.attributes.ohlcv_list)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use crate::{
+        strategies::basic::BasicStrategy,
+        types::{Pool, Token, VaultFees},
+    };
+
+    use super::*;
+
+    fn test_vault() -> VaultDetails {
+        let token = |symbol: &str, decimals: u8| Token {
+            address: format!("0x{}", symbol),
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            decimals,
+            is_native_wrapper: false,
+        };
+
+        VaultDetails {
+            address: "0xvault".to_string(),
+            pool: Pool {
+                address: "0xpool".to_string(),
+                token0: token("WHBAR", 8),
+                token1: token("USDC", 6),
+                fee: 0.3,
+                tick_spacing: 60,
+                current_tick: 0,
+                sqrt_price_x96: U256::ZERO,
+                price1: 0.0,
+                price0: 0.0,
+            },
+            name: "Yieldera Vault".to_string(),
+            symbol: "YV".to_string(),
+            decimals: 18,
+            total_supply: 0.0,
+            lower_tick: 0,
+            upper_tick: 0,
+            is_active: false,
+            is_vault_tokens_associated: true,
+            position: Position::default(),
+            tvl: VaultTVL {
+                tvl0: 0.0,
+                tvl1: 0.0,
+            },
+            fees: VaultFees::default(),
+        }
+    }
+
+    fn test_config() -> BacktestConfig {
+        BacktestConfig {
+            initial_amount0: 1000.0,
+            initial_amount1: 200.0,
+            liquidity_share: 0.01,
+            rebalance_cost1: 0.1,
+            min_fees0: 0.0,
+            min_fees1: 0.0,
+            lookback_candles: 30,
+            decision_interval: 1,
+        }
+    }
+
+    fn candle(timestamp: i64, price: f64, volume: f64) -> OhlcvEntry {
+        OhlcvEntry(
+            timestamp,
+            price,
+            price * 1.001,
+            price * 0.999,
+            price,
+            volume,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_backtest_flat_price() {
+        let strategy = BasicStrategy { range_percent: 5.0 };
+        let candles: Vec<OhlcvEntry> = (0..30)
+            .map(|day| candle(day * 86_400, 0.2, 10_000.0))
+            .collect();
+
+        let report = run_backtest(&strategy, &test_vault(), &candles, &test_config())
+            .await
+            .unwrap();
+
+        // Only the first position is opened, the range never changes afterwards
+        assert_eq!(report.rebalances, 1);
+        assert_eq!(report.candles, 30);
+        assert_eq!(report.time_in_range, 100.0);
+        // 30 candles * 10_000 volume * 0.3% fee * 1% share
+        assert!((report.fees_earned - 9.0).abs() < 1e-9);
+        assert!(report.final_value > report.hodl_value);
+        assert!(report.net_return_vs_hodl > 0.0);
+        // Without price move there is no impermanent loss
+        assert!(report.impermanent_loss.abs() < 0.1);
+    }
+
+    #[tokio::test]
+    async fn test_backtest_trending_price() {
+        let strategy = BasicStrategy { range_percent: 1.0 };
+        let candles: Vec<OhlcvEntry> = (0..30)
+            .map(|day| candle(day * 86_400, 0.2 * 1.02f64.powi(day as i32), 10_000.0))
+            .collect();
+
+        let report = run_backtest(&strategy, &test_vault(), &candles, &test_config())
+            .await
+            .unwrap();
+
+        // The range follows the price every candle
+        assert_eq!(report.rebalances, 30);
+        assert!(report.rebalance_costs >= 30.0 * 0.1);
+        // Selling token0 on the way up loses against holding it
+        assert!(report.impermanent_loss < 0.0);
+        assert!(report.hodl_value > report.final_value - report.fees_earned);
+    }
+
+    #[tokio::test]
+    async fn test_backtest_sorts_candles_and_rejects_invalid() {
+        let strategy = BasicStrategy { range_percent: 5.0 };
+        let mut candles: Vec<OhlcvEntry> = (0..10)
+            .map(|day| candle(day * 86_400, 0.2 + day as f64 * 0.001, 1_000.0))
+            .collect();
+        candles.reverse();
+
+        let report = run_backtest(&strategy, &test_vault(), &candles, &test_config())
+            .await
+            .unwrap();
+
+        assert_eq!(report.start_timestamp, 0);
+        assert_eq!(report.end_timestamp, 9 * 86_400);
+
+        let result = run_backtest(&strategy, &test_vault(), &[], &test_config()).await;
+        assert!(result.is_err());
+
+        let config = BacktestConfig {
+            decision_interval: 0,
+            ..test_config()
+        };
+        let result = run_backtest(&strategy, &test_vault(), &candles, &config).await;
+        assert!(result.is_err());
+
+        let too_many = vec![candle(0, 0.2, 1_000.0); BACKTEST_MAX_CANDLES + 1];
+        let result = run_backtest(&strategy, &test_vault(), &too_many, &test_config()).await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_load_candles_from_file() {
+        let dir = std::env::temp_dir().join(format!("yieldera_backtest_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let csv_path = dir.join("candles.csv");
+        fs::write(
+            &csv_path,
+            "timestamp,open,high,low,close,volume\n1700000000,0.2,0.21,0.19,0.205,1500.5\n1700086400,0.205,0.22,0.2,0.21,900\n",
+        )
+        .unwrap();
+
+        let candles = load_candles_from_file(&csv_path).unwrap();
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[0].timestamp(), 1700000000);
+        assert_eq!(candles[0].close(), 0.205);
+        assert_eq!(candles[1].volume(), 900.0);
+
+        let json_path = dir.join("candles.json");
+        fs::write(&json_path, "[[1700000000,0.2,0.21,0.19,0.205,1500.5]]").unwrap();
+        let candles = load_candles_from_file(&json_path).unwrap();
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].high(), 0.21);
+
+        let coingecko_path = dir.join("coingecko.json");
+        fs::write(
+            &coingecko_path,
+            r#"{"data":{"id":"pool","attributes":{"ohlcv_list":[[1700000000,0.2,0.21,0.19,0.205,1500.5]]}}}"#,
+        )
+        .unwrap();
+        let candles = load_candles_from_file(&coingecko_path).unwrap();
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].low(), 0.19);
+
+        assert!(load_candles_from_file(&dir.join("candles.txt")).is_err());
+        assert!(load_candles_from_data_dir("../candles.csv").is_err());
+        assert!(load_candles_from_data_dir("/tmp/candles.csv").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,9 +1,10 @@
 use std::fs;
 
+use color_eyre::eyre::{Result, eyre};
 use dotenvy::dotenv;
 use once_cell::sync::Lazy;
 
-use crate::types::TomlConfig;
+use crate::types::{AdminApiKey, TomlConfig};
 
 pub const RPC_URL: &str = "https://testnet.hashio.io/api";
 pub const CHAIN_ID: u64 = 296;
@@ -19,11 +20,14 @@ pub struct Config {
     pub is_mainnet: bool,
     pub toml_config: TomlConfig,
     pub admin_email: String,
-    pub admin_password: String,
+    pub admin_api_keys: Vec<AdminApiKey>,
+    /// Accept the EIP-191 signatures of the vault owners on the admin routes of their vault
+    pub admin_signature_auth: bool,
     pub mailer_username: String,
     pub mailer_password: String,
     pub is_execute: bool,
     pub coingecko_api_key: String,
+    pub database_path: String,
 }
 
 impl Config {
@@ -37,12 +41,19 @@ impl Config {
             == "mainnet";
 
         let is_execute = std::env::var("IS_EXECUTE").unwrap_or("false".to_string()) == "true";
-        let admin_password = std::env::var("ADMIN_PASSWORD").expect("ADMIN_PASSWORD is not set");
+        let admin_api_keys = parse_admin_api_keys(
+            &std::env::var("ADMIN_API_KEYS").expect("ADMIN_API_KEYS is not set"),
+        )
+        .expect("Invalid ADMIN_API_KEYS");
+        let admin_signature_auth =
+            std::env::var("ADMIN_SIGNATURE_AUTH").unwrap_or("false".to_string()) == "true";
         let admin_email = std::env::var("ADMIN_EMAIL").expect("ADMIN_EMAIL is not set");
         let mailer_username = std::env::var("MAILER_USERNAME").expect("MAILER_USERNAME is not set");
         let mailer_password = std::env::var("MAILER_PASSWORD").expect("MAILER_PASSWORD is not set");
         let coingecko_api_key =
             std::env::var("COINGEKO_API_KEY").expect("COINGEKO_API_KEY is not set");
+        let database_path =
+            std::env::var("DATABASE_PATH").unwrap_or(DEFAULT_DATABASE_PATH.to_string());
 
         // Load config from toml file based on the environment (mainnet or testnet)
         let toml_config_file_path = if is_mainnet {
@@ -61,18 +72,175 @@ impl Config {
             is_mainnet,
             toml_config,
             admin_email,
-            admin_password,
+            admin_api_keys,
+            admin_signature_auth,
             mailer_username,
             mailer_password,
             is_execute,
             coingecko_api_key,
+            database_path,
         }
     }
 }
 
+/// `ADMIN_API_KEYS` value: comma separated `name:sha256_hex` entries, the hex SHA-256 of each
+/// key (`echo -n "$KEY" | sha256sum`)
+pub fn parse_admin_api_keys(raw: &str) -> Result<Vec<AdminApiKey>> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (name, hash) = entry
+                .split_once(':')
+                .ok_or_else(|| eyre!("API key entry {} is not name:sha256_hex", entry))?;
+
+            let hash: [u8; 32] =
+                alloy::primitives::hex::decode(hash.trim().trim_start_matches("0x"))?
+                    .try_into()
+                    .map_err(|_| eyre!("API key hash of {} is not 32 bytes", name))?;
+
+            Ok(AdminApiKey {
+                name: name.trim().to_string(),
+                hash,
+            })
+        })
+        .collect()
+}
+
 // Define a globally accessible static Config instance
 pub static CONFIG: Lazy<Config> = Lazy::new(Config::load);
 
 // Other constants
 pub const FEE_FACTOR: f64 = 10_000.0;
 pub const MONITOR_VAULT_INTERVAL_SECONDS: u64 = 60 * 1; // 1 hour in seconds
+pub const DEFAULT_STRATEGY: &str = "ai"; // strategy of the vault blocks that do not set their own
+pub const BACKTEST_DATA_DIR: &str = "backtest_data"; // local candles files (.csv or .json) for backtests
+pub const BACKTEST_MAX_CANDLES: usize = 5_000; // candles replayed by one backtest
+pub const DEFAULT_DATABASE_PATH: &str = "data/yieldera.db"; // sqlite file, overridden by DATABASE_PATH
+pub const UNSENT_REBALANCE_RETENTION_SECONDS: i64 = 30 * 24 * 60 * 60; // skipped, dry run and blocked attempts are pruned after 30 days
+pub const POOL_STATE_BITMAP_WORDS: i16 = 2; // tick bitmap words loaded on each side of the current tick
+pub const INDEXER_BLOCK_RANGE: u64 = 1_000; // max blocks per eth_getLogs request of the event indexer
+pub const INDEXER_POLL_INTERVAL_SECONDS: u64 = 15; // wait between two indexer runs once caught up
+pub const RPC_REQUEST_TIMEOUT_SECONDS: u64 = 20; // an RPC request taking longer fails over
+pub const RPC_MAX_RETRIES: u32 = 4; // retries of a failed RPC read, on the next healthy endpoint
+pub const RPC_RETRY_BASE_DELAY_MS: u64 = 250; // first RPC retry wait, doubled on each retry
+pub const RPC_RETRY_MAX_DELAY_MS: u64 = 5_000;
+pub const RPC_ENDPOINT_COOLDOWN_SECONDS: u64 = 30; // a failing endpoint is used last for this long
+pub const TRIGGER_POLL_INTERVAL_SECONDS: u64 = 5; // swap logs polling of the `swap` trigger
+pub const TRIGGER_MIN_GAP_SECONDS: u64 = 30; // min wait between two evaluations of a vault
+pub const INIT_VAULT_MAX_RETRIES: u32 = 5; // retries of a vault details fetch at startup
+pub const INIT_VAULT_RETRY_BASE_DELAY_SECONDS: u64 = 2;
+pub const GAS_LIMIT_MARGIN_PERCENT: u64 = 20; // added to the gas estimate of the vault transactions
+pub const TX_QUEUE_SIZE: usize = 32; // pending transactions per signer of the transaction manager
+pub const TX_RECEIPT_TIMEOUT_SECONDS: u64 = 60; // a transaction not mined by then is replaced
+pub const TX_RECEIPT_POLL_INTERVAL_SECONDS: u64 = 2;
+pub const TX_GAS_BUMP_PERCENT: u128 = 20; // fee increase of a replacement transaction
+pub const TX_MAX_REPLACEMENTS: u32 = 3; // replacements before giving up on a transaction
+pub const ADMIN_SIGNATURE_MAX_AGE_SECONDS: i64 = 300; // admin signatures older or newer than this are rejected
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::Severity,
+        types::{RebalanceTrigger, TomlConfig},
+    };
+
+    use super::parse_admin_api_keys;
+
+    #[test]
+    fn test_parse_admin_api_keys() {
+        let hash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        let keys = parse_admin_api_keys(&format!("ops:{}, ci:0x{},", hash, hash)).unwrap();
+
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].name, "ops");
+        assert_eq!(keys[1].name, "ci");
+        assert_eq!(keys[0].hash, keys[1].hash);
+        assert_eq!(keys[0].hash[0], 0x9f);
+
+        assert!(parse_admin_api_keys("").unwrap().is_empty());
+        assert!(parse_admin_api_keys(hash).is_err());
+        assert!(parse_admin_api_keys("ops:abcd").is_err());
+    }
+
+    #[test]
+    fn test_parse_network_toml_configs() {
+        for raw in [include_str!("testnet.toml"), include_str!("mainnet.toml")] {
+            let toml_config: TomlConfig = toml::from_str(raw).unwrap();
+
+            assert!(!toml_config.vaults.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_vault_config_defaults_and_overrides() {
+        let raw = r#"
+rpc_url = "https://testnet.hashio.io/api"
+chain_id = 296
+non_fungible_position_manager_address = "0x000000000000000000000000000000000013f618"
+hbar_evm_address = "0x0000000000000000000000000000000000003ad2"
+multicall_address = "0xcA11bde05977b3631167028862bE2a173976CA11"
+
+[[vaults]]
+address = "0xAAAA"
+
+[[vaults]]
+address = "0xBBBB"
+strategy = "basic"
+monitor_interval_seconds = 300
+min_fees0 = 1.5
+execute = false
+alert_recipients = ["ops@yieldera.io"]
+trigger = "swap"
+trigger_edge_percent = 5.0
+max_rebalances_per_day = 4
+twap_window_seconds = 0
+alert_min_severity = "critical"
+
+[vaults.strategy_params]
+range_percent = 2.5
+"#;
+        let toml_config: TomlConfig = toml::from_str(raw).unwrap();
+
+        assert_eq!(
+            toml_config.multicall_address,
+            Some(alloy::primitives::address!(
+                "0xcA11bde05977b3631167028862bE2a173976CA11"
+            ))
+        );
+
+        let first = toml_config.vault_config("0xaaaa").unwrap();
+        assert_eq!(
+            first.monitor_interval_seconds,
+            super::MONITOR_VAULT_INTERVAL_SECONDS
+        );
+        assert_eq!(first.min_fees0, 0.01);
+        assert_eq!(first.rebalance_value_hbar, 0.2);
+        assert_eq!(first.execute, None);
+        assert_eq!(first.trigger, RebalanceTrigger::Interval);
+        assert_eq!(first.min_rebalance_interval_seconds, 3600);
+        assert_eq!(first.max_rebalances_per_day, 12);
+        assert_eq!(first.max_consecutive_failures, 3);
+        assert_eq!(first.twap_window_seconds, 600);
+        assert_eq!(first.max_twap_deviation_percent, 2.0);
+        assert_eq!(first.alert_min_severity, Severity::Warning);
+        assert_eq!(toml_config.strategy_for("0xAAAA"), "ai");
+
+        let second = toml_config.vault_config("0xBBBB").unwrap();
+        assert_eq!(second.monitor_interval_seconds, 300);
+        assert_eq!(second.min_fees0, 1.5);
+        assert_eq!(second.min_fees1, 0.01);
+        assert_eq!(second.execute, Some(false));
+        assert_eq!(second.trigger, RebalanceTrigger::Swap);
+        assert_eq!(second.trigger_edge_percent, 5.0);
+        assert_eq!(second.max_rebalances_per_day, 4);
+        assert_eq!(second.twap_window_seconds, 0);
+        assert_eq!(second.alert_min_severity, Severity::Critical);
+        assert_eq!(second.alert_recipients, vec!["ops@yieldera.io".to_string()]);
+        assert_eq!(toml_config.strategy_for("0xBBBB"), "basic");
+        assert_eq!(
+            second.strategy_params.get("range_percent"),
+            Some(&toml::Value::Float(2.5))
+        );
+    }
+}
@@ -0,0 +1,137 @@
+/*
+    Admin control of the vault management loops. Each loop owns the receiving end of a command
+    channel and runs the manual actions on its own task, so they never overlap with an
+    evaluation of the same vault. The pause flag is kept in memory: a restart resumes all vaults.
+*/
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use color_eyre::eyre::{Result, eyre};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::state::AppState;
+
+/// Commands waiting for a busy loop, beyond that the admin requests fail
+const COMMAND_CHANNEL_CAPACITY: usize = 8;
+
+#[derive(Debug)]
+pub enum VaultCommand {
+    /// Evaluate the vault now instead of waiting for the next trigger
+    EvaluateNow,
+    /// Rebalance to an explicit tick range, bypassing the strategy. Replies the transaction hash
+    ForceRebalance {
+        lower_tick: i32,
+        upper_tick: i32,
+        reply: oneshot::Sender<Result<String>>,
+    },
+    /// Remove all the vault liquidity. Replies the transaction hash
+    BurnAllLiquidity {
+        reply: oneshot::Sender<Result<String>>,
+    },
+}
+
+#[derive(Debug)]
+pub struct VaultControl {
+    sender: mpsc::Sender<VaultCommand>,
+    paused: AtomicBool,
+}
+
+impl VaultControl {
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    /// Queue a command for the vault loop
+    pub fn send(&self, command: VaultCommand) -> Result<()> {
+        self.sender
+            .try_send(command)
+            .map_err(|e| eyre!("Vault loop cannot take the command: {}", e))
+    }
+
+    /// Queue a command built around a reply channel and wait for the loop to run it
+    pub async fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<Result<T>>) -> VaultCommand,
+    ) -> Result<T> {
+        let (reply, response) = oneshot::channel();
+        self.send(command(reply))?;
+
+        response
+            .await
+            .map_err(|_| eyre!("Vault loop stopped before running the command"))?
+    }
+}
+
+/// Create the control of a vault and return the command receiver for its loop
+pub fn register_vault(app_state: &AppState, vault_address: &str) -> mpsc::Receiver<VaultCommand> {
+    let (sender, receiver) = mpsc::channel(COMMAND_CHANNEL_CAPACITY);
+
+    app_state.vault_controls.insert(
+        vault_address.to_lowercase(),
+        VaultControl {
+            sender,
+            paused: AtomicBool::new(false),
+        },
+    );
+
+    receiver
+}
+
+/// True when an admin paused the vault, its evaluations are skipped
+pub fn is_paused(app_state: &AppState, vault_address: &str) -> bool {
+    app_state
+        .vault_controls
+        .get(&vault_address.to_lowercase())
+        .is_some_and(|control| control.is_paused())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use tokio::sync::mpsc;
+
+    use super::{VaultCommand, VaultControl};
+
+    #[tokio::test]
+    async fn test_request_is_answered_by_the_loop() {
+        let (sender, mut receiver) = mpsc::channel(1);
+        let control = VaultControl {
+            sender,
+            paused: AtomicBool::new(false),
+        };
+
+        let vault_loop = tokio::spawn(async move {
+            while let Some(command) = receiver.recv().await {
+                if let VaultCommand::ForceRebalance {
+                    lower_tick,
+                    upper_tick,
+                    reply,
+                } = command
+                {
+                    reply
+                        .send(Ok(format!("{}..{}", lower_tick, upper_tick)))
+                        .unwrap();
+                }
+            }
+        });
+
+        let result = control
+            .request(|reply| VaultCommand::ForceRebalance {
+                lower_tick: -60,
+                upper_tick: 60,
+                reply,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result, "-60..60");
+
+        // A stopped loop rejects the commands
+        vault_loop.abort();
+        let _ = vault_loop.await;
+        assert!(control.send(VaultCommand::EvaluateNow).is_err());
+    }
+}
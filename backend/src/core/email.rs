@@ -1,36 +1,61 @@
-use color_eyre::eyre::{Context, Result};
 use lettre::{Message, SmtpTransport, Transport, transport::smtp::authentication::Credentials};
 use tracing::info;
 
-use crate::config::CONFIG;
+use crate::{config::CONFIG, error::YielderaError};
 
-pub async fn init_mailer() -> Result<SmtpTransport> {
+pub async fn init_mailer() -> Result<SmtpTransport, YielderaError> {
     let mail_credentials = Credentials::new(
         CONFIG.mailer_username.clone(),
         CONFIG.mailer_password.clone(),
     );
 
     let mailer = SmtpTransport::relay("smtp.gmail.com")
-        .context("Failed to create SMTP transport")?
+        .map_err(|e| {
+            YielderaError::Notification(format!("Failed to create SMTP transport: {}", e))
+        })?
         .credentials(mail_credentials)
         .build();
 
     Ok(mailer)
 }
 
+/// Send an email to the given recipients, or to the admin email if no recipient is given
 pub async fn send_email_notification(
     subject: &str,
     body: String,
+    recipients: &[String],
     mailer: &SmtpTransport,
-) -> Result<()> {
-    let email = Message::builder()
-        .from(CONFIG.mailer_username.parse()?)
-        .to(CONFIG.admin_email.parse()?)
+) -> Result<(), YielderaError> {
+    let notification_error = |e: &dyn std::error::Error| {
+        YielderaError::Notification(format!("Failed to send email '{}': {}", subject, e))
+    };
+
+    let mut email_builder = Message::builder().from(
+        CONFIG
+            .mailer_username
+            .parse()
+            .map_err(|e| notification_error(&e))?,
+    );
+
+    if recipients.is_empty() {
+        email_builder = email_builder.to(CONFIG
+            .admin_email
+            .parse()
+            .map_err(|e| notification_error(&e))?);
+    } else {
+        for recipient in recipients {
+            email_builder =
+                email_builder.to(recipient.parse().map_err(|e| notification_error(&e))?);
+        }
+    }
+
+    let email = email_builder
         .subject(subject.to_string())
-        .body(body)?;
+        .body(body)
+        .map_err(|e| notification_error(&e))?;
 
     // Send the email using the configured SMTP server
-    mailer.send(&email)?;
+    mailer.send(&email).map_err(|e| notification_error(&e))?;
 
     info!("Email '{}' sent successfully!", subject);
     Ok(())
@@ -0,0 +1,150 @@
+/*
+    Rebalance guardrails, checked before sending a rebalance transaction: a cooldown between two
+    transactions, a daily budget (count and HBAR spent over the last 24 hours) and a circuit
+    breaker pausing the vault after consecutive failed transactions until an admin reset.
+*/
+use alloy::{primitives::utils::format_units, rpc::types::TransactionReceipt};
+use color_eyre::eyre::Result;
+
+use crate::{db::Db, types::VaultConfig};
+
+const DAY_SECONDS: i64 = 24 * 60 * 60;
+
+/// Reason why the vault cannot send a rebalance transaction at `now`, None when it can
+pub fn check_guardrails(db: &Db, vault_config: &VaultConfig, now: i64) -> Result<Option<String>> {
+    let vault_address = vault_config.address.as_str();
+
+    let breaker = db.circuit_breaker(vault_address)?;
+    if breaker.is_tripped() {
+        return Ok(Some(format!(
+            "Circuit breaker tripped after {} consecutive failed rebalances (last error: {}), an admin reset is required",
+            breaker.consecutive_failures,
+            breaker.reason.as_deref().unwrap_or_default()
+        )));
+    }
+
+    if let Some(last_timestamp) = db.last_rebalance_timestamp(vault_address)? {
+        let elapsed = now - last_timestamp;
+        if elapsed < vault_config.min_rebalance_interval_seconds as i64 {
+            return Ok(Some(format!(
+                "Last rebalance was {}s ago, min interval is {}s",
+                elapsed, vault_config.min_rebalance_interval_seconds
+            )));
+        }
+    }
+
+    let (count, hbar_spent) = db.rebalance_spending(vault_address, now - DAY_SECONDS)?;
+    if count >= vault_config.max_rebalances_per_day {
+        return Ok(Some(format!(
+            "{} rebalances over the last 24h, max is {}",
+            count, vault_config.max_rebalances_per_day
+        )));
+    }
+
+    if hbar_spent + vault_config.rebalance_value_hbar > vault_config.max_daily_hbar_spent {
+        return Ok(Some(format!(
+            "{:.4} HBAR spent by rebalances over the last 24h, max is {}",
+            hbar_spent, vault_config.max_daily_hbar_spent
+        )));
+    }
+
+    Ok(None)
+}
+
+/// HBAR paid by a rebalance transaction: the gas fee and the value sent
+pub fn hbar_spent(receipt: &TransactionReceipt, value_hbar: f64) -> Result<f64> {
+    let gas_fee = receipt.gas_used as u128 * receipt.effective_gas_price;
+
+    Ok(format_units(gas_fee, 18)?.parse::<f64>()? + value_hbar)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::Db,
+        types::{Position, RebalancePlan, RebalanceRecord, RebalanceStatus, VaultConfig},
+    };
+
+    use super::check_guardrails;
+
+    fn vault_config() -> VaultConfig {
+        let mut config: VaultConfig = toml::from_str(r#"address = "0xabc""#).unwrap();
+        config.min_rebalance_interval_seconds = 600;
+        config.max_rebalances_per_day = 2;
+        config.max_daily_hbar_spent = 5.0;
+        config.rebalance_value_hbar = 0.2;
+        config
+    }
+
+    fn insert_rebalance(db: &Db, timestamp: i64, hbar_spent: f64) {
+        let plan = RebalancePlan {
+            vault_address: "0xabc".to_string(),
+            strategy: "basic".to_string(),
+            rebalance_required: true,
+            skip_reason: None,
+            blocked_reason: None,
+            current_tick: 0,
+            current_lower_tick: -60,
+            current_upper_tick: 60,
+            lower_tick: -120,
+            upper_tick: 120,
+            price1: 1.0,
+            lower_price1: 0.99,
+            upper_price1: 1.01,
+            swap: None,
+            expected_liquidity: 0,
+            expected_amount0: 0.0,
+            expected_amount1: 0.0,
+            analysis: None,
+        };
+        let mut record =
+            RebalanceRecord::from_plan(&plan, RebalanceStatus::Success, &Position::default());
+        record.timestamp = timestamp;
+        record.hbar_spent = hbar_spent;
+        db.insert_rebalance(&record).unwrap();
+    }
+
+    #[test]
+    fn test_cooldown_and_daily_limits() {
+        let db = Db::open(":memory:").unwrap();
+        let mut config = vault_config();
+        let now = 1_000_000;
+
+        assert_eq!(check_guardrails(&db, &config, now).unwrap(), None);
+
+        // Cooldown
+        insert_rebalance(&db, now - 300, 1.0);
+        assert!(check_guardrails(&db, &config, now).unwrap().is_some());
+        assert_eq!(check_guardrails(&db, &config, now + 300).unwrap(), None);
+
+        // Daily count, the rebalances older than 24h are ignored
+        insert_rebalance(&db, now - 3_000, 1.0);
+        assert!(check_guardrails(&db, &config, now + 300).unwrap().is_some());
+        assert_eq!(check_guardrails(&db, &config, now + 86_000).unwrap(), None);
+
+        // Daily HBAR budget, counting the value of the next rebalance
+        config.max_rebalances_per_day = 10;
+        config.max_daily_hbar_spent = 2.1;
+        assert!(check_guardrails(&db, &config, now + 300).unwrap().is_some());
+        config.max_daily_hbar_spent = 2.2;
+        assert_eq!(check_guardrails(&db, &config, now + 300).unwrap(), None);
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let db = Db::open(":memory:").unwrap();
+        let config = vault_config();
+        let now = 1_000_000;
+
+        db.record_rebalance_outcome("0xabc", Some("reverted"), 2, now)
+            .unwrap();
+        assert_eq!(check_guardrails(&db, &config, now).unwrap(), None);
+
+        db.record_rebalance_outcome("0xabc", Some("reverted"), 2, now)
+            .unwrap();
+        assert!(check_guardrails(&db, &config, now).unwrap().is_some());
+
+        db.reset_circuit_breaker("0xabc").unwrap();
+        assert_eq!(check_guardrails(&db, &config, now).unwrap(), None);
+    }
+}
@@ -0,0 +1,451 @@
+/*
+    Event indexer: follows the logs of each managed vault from its start block (the configured
+    one or the vault deployment block), decodes the YielderaVault events and the share transfers
+    and stores them with the last indexed block, so a restart resumes where it stopped.
+*/
+use std::{collections::HashMap, str::FromStr};
+
+use alloy::{
+    primitives::Address,
+    providers::Provider,
+    rpc::types::{Filter, Log},
+    sol_types::SolEvent,
+};
+use color_eyre::eyre::{Result, eyre};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::{INDEXER_BLOCK_RANGE, INDEXER_POLL_INTERVAL_SECONDS},
+    core::vault::YielderaVault,
+    types::{VaultConfig, VaultEvent, VaultEventData, WebAppState},
+};
+
+/// Index the events of a vault forever, errors are logged and the range is retried
+pub async fn start_vault_indexer(vault_config: &VaultConfig, app_state: WebAppState) -> Result<()> {
+    let vault_address = vault_config.address.as_str();
+
+    info!(
+        "Event indexer started for vault address: {:?}",
+        vault_address
+    );
+
+    loop {
+        if let Err(e) = index_vault_events(vault_config, &app_state).await {
+            error!(
+                "Event indexer for vault {} failed with error: {:?}",
+                vault_address, e
+            );
+        }
+
+        tokio::time::sleep(std::time::Duration::from_secs(
+            INDEXER_POLL_INTERVAL_SECONDS,
+        ))
+        .await;
+    }
+}
+
+/// Index the vault events from the cursor up to the latest block
+async fn index_vault_events(vault_config: &VaultConfig, app_state: &WebAppState) -> Result<()> {
+    let vault_address = vault_config.address.as_str();
+    let provider = &app_state.evm_provider;
+
+    let latest_block = provider.get_block_number().await?;
+
+    let mut from_block = match app_state.db.indexer_cursor(vault_address)? {
+        Some(last_block) => last_block + 1,
+        None => {
+            let (start_block, is_deployment_block) =
+                indexer_start(provider, vault_config, latest_block).await?;
+            info!(
+                "Indexing vault {} from block {} (deployment block: {})",
+                vault_address, start_block, is_deployment_block
+            );
+            app_state
+                .db
+                .set_indexer_start(vault_address, start_block, is_deployment_block)?;
+            start_block
+        }
+    };
+
+    while from_block <= latest_block {
+        let to_block = latest_block.min(from_block + INDEXER_BLOCK_RANGE - 1);
+
+        let events = get_vault_events(provider, vault_address, from_block, to_block).await?;
+
+        debug!(
+            "Indexed {} events of vault {} in blocks {}..={}",
+            events.len(),
+            vault_address,
+            from_block,
+            to_block
+        );
+
+        app_state
+            .db
+            .insert_events(vault_address, &events, to_block)?;
+
+        from_block = to_block + 1;
+    }
+
+    Ok(())
+}
+
+/// Block to index the vault from and whether it is its deployment block. A configured start
+/// block is the deployment block only when the node shows no code on the block before it
+async fn indexer_start<P: Provider>(
+    provider: &P,
+    vault_config: &VaultConfig,
+    latest_block: u64,
+) -> Result<(u64, bool)> {
+    let address = Address::from_str(&vault_config.address)?;
+
+    match vault_config.indexer_start_block {
+        Some(0) => Ok((0, true)),
+        Some(start_block) => Ok((
+            start_block,
+            matches!(
+                has_code(provider, address, start_block - 1).await,
+                Ok(false)
+            ),
+        )),
+        None => Ok((
+            deployment_block(provider, address, latest_block).await?,
+            true,
+        )),
+    }
+}
+
+/// First block the vault has code at, found by bisection. Fails when the node does not serve the
+/// code of past blocks, `indexer_start_block` must then be set in the vault config
+pub async fn deployment_block<P: Provider>(
+    provider: &P,
+    address: Address,
+    latest_block: u64,
+) -> Result<u64> {
+    if !has_code(provider, address, latest_block).await? {
+        return Err(eyre!(
+            "No contract at {} on block {}",
+            address,
+            latest_block
+        ));
+    }
+    if has_code(provider, address, 0).await? {
+        return Err(eyre!(
+            "The node returns the code of {} on block 0, set indexer_start_block to the vault deployment block",
+            address
+        ));
+    }
+
+    // No code at `without_code`, code at `with_code`
+    let (mut without_code, mut with_code) = (0, latest_block);
+    while with_code - without_code > 1 {
+        let block = without_code + (with_code - without_code) / 2;
+        if has_code(provider, address, block).await? {
+            with_code = block;
+        } else {
+            without_code = block;
+        }
+    }
+
+    Ok(with_code)
+}
+
+async fn has_code<P: Provider>(provider: &P, address: Address, block: u64) -> Result<bool> {
+    Ok(!provider
+        .get_code_at(address)
+        .number(block)
+        .await?
+        .is_empty())
+}
+
+/// Fetch and decode the vault events of a block range
+pub async fn get_vault_events<P: Provider>(
+    provider: &P,
+    vault_address: &str,
+    from_block: u64,
+    to_block: u64,
+) -> Result<Vec<VaultEvent>> {
+    let filter = Filter::new()
+        .address(Address::from_str(vault_address)?)
+        .from_block(from_block)
+        .to_block(to_block);
+
+    let logs = provider.get_logs(&filter).await?;
+
+    let mut block_timestamps: HashMap<u64, Option<i64>> = HashMap::new();
+    let mut events = Vec::new();
+
+    for log in logs {
+        // A log that can not be decoded (another event with the same signature) is skipped, it
+        // must not hold the cursor back
+        let event = match decode_vault_event(&log) {
+            Ok(Some(event)) => event,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!(
+                    "Skipping an undecodable log of vault {} (tx {}, log index {}): {:?}",
+                    vault_address,
+                    log.transaction_hash.unwrap_or_default(),
+                    log.log_index.unwrap_or_default(),
+                    e
+                );
+                continue;
+            }
+        };
+
+        let block_number = log
+            .block_number
+            .ok_or_else(|| eyre!("Log without block number"))?;
+
+        let block_timestamp = match log.block_timestamp {
+            Some(timestamp) => Some(timestamp as i64),
+            None => match block_timestamps.get(&block_number) {
+                Some(timestamp) => *timestamp,
+                None => {
+                    // Not every node returns the block timestamp with the logs
+                    let timestamp = provider
+                        .get_block_by_number(block_number.into())
+                        .await?
+                        .map(|block| block.header.timestamp as i64);
+                    block_timestamps.insert(block_number, timestamp);
+                    timestamp
+                }
+            },
+        };
+
+        events.push(VaultEvent {
+            vault_address: vault_address.to_lowercase(),
+            block_number,
+            block_timestamp,
+            tx_hash: log
+                .transaction_hash
+                .ok_or_else(|| eyre!("Log without transaction hash"))?
+                .to_string(),
+            log_index: log
+                .log_index
+                .ok_or_else(|| eyre!("Log without log index"))?,
+            event,
+        });
+    }
+
+    Ok(events)
+}
+
+/// Decode a vault log, None for the events that are not indexed (approvals, ownership...). The
+/// share mints and burns are covered by the deposits and withdrawals, only the transfers between
+/// two accounts are kept
+pub fn decode_vault_event(log: &Log) -> Result<Option<VaultEventData>> {
+    let Some(topic0) = log.topic0() else {
+        return Ok(None);
+    };
+
+    let event = match *topic0 {
+        YielderaVault::Deposit::SIGNATURE_HASH => {
+            let event = log.log_decode::<YielderaVault::Deposit>()?.inner.data;
+            VaultEventData::Deposit {
+                sender: event.sender.to_string(),
+                to: event.to.to_string(),
+                shares: event.shares,
+                amount0: event.amount0,
+                amount1: event.amount1,
+            }
+        }
+        YielderaVault::Withdraw::SIGNATURE_HASH => {
+            let event = log.log_decode::<YielderaVault::Withdraw>()?.inner.data;
+            VaultEventData::Withdraw {
+                sender: event.sender.to_string(),
+                to: event.to.to_string(),
+                shares: event.shares,
+                amount0: event.amount0,
+                amount1: event.amount1,
+            }
+        }
+        YielderaVault::Rebalance::SIGNATURE_HASH => {
+            let event = log.log_decode::<YielderaVault::Rebalance>()?.inner.data;
+            VaultEventData::Rebalance {
+                tick: event.tick.as_i32(),
+                total_amount0: event.totalAmount0,
+                total_amount1: event.totalAmount1,
+                fee_amount0: event.feeAmount0,
+                fee_amount1: event.feeAmount1,
+                total_supply: event.totalSupply,
+            }
+        }
+        YielderaVault::MintLiquidity::SIGNATURE_HASH => {
+            let event = log.log_decode::<YielderaVault::MintLiquidity>()?.inner.data;
+            VaultEventData::MintLiquidity {
+                sender: event.sender.to_string(),
+                liquidity: event.liquidity,
+                amount0: event.amount0,
+                amount1: event.amount1,
+            }
+        }
+        YielderaVault::BurnAllLiquidity::SIGNATURE_HASH => {
+            let event = log
+                .log_decode::<YielderaVault::BurnAllLiquidity>()?
+                .inner
+                .data;
+            VaultEventData::BurnAllLiquidity {
+                sender: event.sender.to_string(),
+                amount0: event.amount0,
+                amount1: event.amount1,
+            }
+        }
+        YielderaVault::CollectFees::SIGNATURE_HASH => {
+            let event = log.log_decode::<YielderaVault::CollectFees>()?.inner.data;
+            VaultEventData::CollectFees {
+                sender: event.sender.to_string(),
+                fees0: event.fees0,
+                fees1: event.fees1,
+            }
+        }
+        YielderaVault::Transfer_0::SIGNATURE_HASH => {
+            let event = log.log_decode::<YielderaVault::Transfer_0>()?.inner.data;
+            if event.from.is_zero() || event.to.is_zero() {
+                return Ok(None);
+            }
+            VaultEventData::Transfer {
+                from: event.from.to_string(),
+                to: event.to.to_string(),
+                value: event.value,
+            }
+        }
+        YielderaVault::AssociateToken::SIGNATURE_HASH => {
+            let event = log
+                .log_decode::<YielderaVault::AssociateToken>()?
+                .inner
+                .data;
+            VaultEventData::AssociateToken {
+                token: event.token.to_string(),
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(event))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{Address, B256, Bytes, LogData, U256, aliases::I24},
+        providers::{ProviderBuilder, mock::Asserter},
+        rpc::types::Log,
+        sol_types::SolEvent,
+    };
+
+    use crate::{core::vault::YielderaVault, types::VaultEventData};
+
+    use super::{decode_vault_event, get_vault_events};
+
+    fn rpc_log(data: LogData) -> Log {
+        Log {
+            inner: alloy::primitives::Log {
+                address: Address::ZERO,
+                data,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_decode_vault_events() {
+        let sender = Address::repeat_byte(1);
+        let to = Address::repeat_byte(2);
+
+        let deposit = YielderaVault::Deposit {
+            sender,
+            to,
+            shares: U256::from(100),
+            amount0: U256::from(1),
+            amount1: U256::from(2),
+        };
+        assert_eq!(
+            decode_vault_event(&rpc_log(deposit.encode_log_data())).unwrap(),
+            Some(VaultEventData::Deposit {
+                sender: sender.to_string(),
+                to: to.to_string(),
+                shares: U256::from(100),
+                amount0: U256::from(1),
+                amount1: U256::from(2),
+            })
+        );
+
+        let rebalance = YielderaVault::Rebalance {
+            tick: I24::try_from(-120).unwrap(),
+            totalAmount0: U256::from(10),
+            totalAmount1: U256::from(20),
+            feeAmount0: U256::from(1),
+            feeAmount1: U256::from(2),
+            totalSupply: U256::from(30),
+        };
+        assert!(matches!(
+            decode_vault_event(&rpc_log(rebalance.encode_log_data())).unwrap(),
+            Some(VaultEventData::Rebalance { tick: -120, .. })
+        ));
+
+        let transfer = YielderaVault::Transfer_0 {
+            from: sender,
+            to,
+            value: U256::from(1),
+        };
+        assert_eq!(
+            decode_vault_event(&rpc_log(transfer.encode_log_data())).unwrap(),
+            Some(VaultEventData::Transfer {
+                from: sender.to_string(),
+                to: to.to_string(),
+                value: U256::from(1),
+            })
+        );
+
+        // Share mints are covered by the deposits
+        let mint = YielderaVault::Transfer_0 {
+            from: Address::ZERO,
+            to,
+            value: U256::from(1),
+        };
+        assert_eq!(
+            decode_vault_event(&rpc_log(mint.encode_log_data())).unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_vault_events_skips_undecodable_logs() {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+
+        let deposit = YielderaVault::Deposit {
+            sender: Address::repeat_byte(1),
+            to: Address::repeat_byte(2),
+            shares: U256::from(100),
+            amount0: U256::from(1),
+            amount1: U256::from(2),
+        };
+        // Same signature, but the data does not decode
+        let undecodable = LogData::new_unchecked(
+            deposit.encode_log_data().topics().to_vec(),
+            Bytes::from_static(&[1, 2, 3]),
+        );
+
+        let logs: Vec<Log> = [undecodable, deposit.encode_log_data()]
+            .into_iter()
+            .enumerate()
+            .map(|(index, data)| Log {
+                block_number: Some(10),
+                block_timestamp: Some(1_000),
+                transaction_hash: Some(B256::repeat_byte(index as u8)),
+                log_index: Some(index as u64),
+                ..rpc_log(data)
+            })
+            .collect();
+        asserter.push_success(&logs);
+
+        let events = get_vault_events(&provider, &Address::ZERO.to_string(), 0, 10)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].log_index, 1);
+        assert!(matches!(events[0].event, VaultEventData::Deposit { .. }));
+    }
+}
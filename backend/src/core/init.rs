@@ -5,7 +5,10 @@ use std::{
     time::Duration,
 };
 
-use alloy::{providers::ProviderBuilder, signers::local::PrivateKeySigner};
+use alloy::{
+    providers::ProviderBuilder, rpc::client::RpcClient, signers::local::PrivateKeySigner,
+    transports::http::Http,
+};
 
 use color_eyre::eyre::Result;
 use mcp_core::{client::ClientBuilder, transport::ClientSseTransportBuilder};
@@ -20,39 +23,83 @@ use tokio::time::sleep;
 use tracing::info;
 
 use crate::{
-    config::CONFIG,
-    core,
+    config::{
+        CONFIG, INIT_VAULT_MAX_RETRIES, INIT_VAULT_RETRY_BASE_DELAY_SECONDS,
+        RPC_REQUEST_TIMEOUT_SECONDS,
+    },
+    core::{
+        self,
+        rpc::{FailoverTransport, RetryConfig, with_retries},
+    },
     types::{EvmProvider, WebAppState},
 };
 
 pub async fn init_evm_provider() -> Result<EvmProvider> {
     let private_key = CONFIG.private_key.as_str();
     let chain_id = CONFIG.toml_config.chain_id;
-    let rpc_url = CONFIG.toml_config.rpc_url.as_str();
 
     let evm_signer = PrivateKeySigner::from_str(private_key)?;
 
-    // Init provider with the specified rpc url in config
+    // One http transport per configured rpc url, the main one first
+    let http_client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(RPC_REQUEST_TIMEOUT_SECONDS))
+        .build()?;
+    let endpoints = std::iter::once(&CONFIG.toml_config.rpc_url)
+        .chain(&CONFIG.toml_config.fallback_rpc_urls)
+        .map(|rpc_url| {
+            Ok((
+                rpc_url.clone(),
+                Http::with_client(http_client.clone(), rpc_url.parse()?),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let transport = FailoverTransport::new(endpoints, RetryConfig::default());
+    let client = RpcClient::builder().transport(transport, false);
+
     let evm_provider = ProviderBuilder::new()
         .with_chain_id(chain_id)
         .wallet(evm_signer)
-        .connect(rpc_url)
-        .await?;
+        .connect_client(client);
 
     Ok(evm_provider)
 }
 
 pub async fn init_all_vaults(app_state: &WebAppState) -> Result<()> {
     let provider = &app_state.evm_provider;
-    let all_vaults_addresses = CONFIG.toml_config.vaults.clone();
+    let all_vaults_addresses = CONFIG
+        .toml_config
+        .vaults
+        .iter()
+        .map(|vault| vault.address.clone())
+        .collect::<Vec<String>>();
     let all_vaults = &app_state.all_vaults;
 
-    // TODO: add retry mechanism for each vault fetch
+    let retry = RetryConfig {
+        max_retries: INIT_VAULT_MAX_RETRIES,
+        base_delay: Duration::from_secs(INIT_VAULT_RETRY_BASE_DELAY_SECONDS),
+        max_delay: Duration::from_secs(INIT_VAULT_RETRY_BASE_DELAY_SECONDS * 16),
+        ..RetryConfig::default()
+    };
+
     for vault_address in all_vaults_addresses {
+        // Make sure the strategy configured for the vault exists before starting it
+        if let Some(vault_config) = CONFIG.toml_config.vault_config(&vault_address) {
+            let strategy_name = CONFIG.toml_config.strategy_for(&vault_address);
+            app_state
+                .strategies
+                .build(strategy_name, &vault_config.strategy_params)?;
+        }
+
         // Fetch vault details and store them into the app state
         info!("Fetching vault details for address: {:?}...", vault_address);
 
-        let vault_details = core::vault::get_vault_details(provider, &vault_address).await?;
+        let vault_details = with_retries(
+            &format!("Fetching vault details for address {}", vault_address),
+            retry,
+            || core::vault::get_vault_details(provider, &vault_address),
+        )
+        .await?;
 
         all_vaults.insert(vault_address.clone(), vault_details);
 
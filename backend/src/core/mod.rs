@@ -1,6 +1,19 @@
+pub mod control;
 pub mod email;
+pub mod guardrails;
+pub mod indexer;
 pub mod init;
+pub mod multicall;
+pub mod oracle;
+pub mod pool_state;
+pub mod positions;
+pub mod preflight;
+pub mod quotes;
+pub mod rpc;
+pub mod stats;
+pub mod transactions;
+pub mod trigger;
+pub mod tx_manager;
 pub mod vault;
 pub mod vault_spawn;
 pub mod coingecko;
-pub mod csv_logger;
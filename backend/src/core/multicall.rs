@@ -0,0 +1,79 @@
+/*
+    Batched contract reads. With a Multicall3 contract configured the calls go in a single
+    `aggregate` eth_call, otherwise they are sent in parallel. Both paths return the same
+    tuple of decoded values, in the order of the calls.
+*/
+
+/// `batch_calls!(&provider, multicall_address, call0, call1, ...)` with `multicall_address` an
+/// `Option<Address>` and the calls contract call builders (`vault.name()`, ...). Evaluates to a
+/// future of `Result<(Return0, Return1, ...)>`. At most 16 calls per batch
+macro_rules! batch_calls {
+    ($provider:expr, $multicall_address:expr, $($call:expr),+ $(,)?) => {
+        async {
+            match $multicall_address {
+                Some(address) => alloy::providers::Provider::multicall($provider)
+                    .address(address)
+                    $(.add($call))+
+                    .aggregate()
+                    .await
+                    .map_err(color_eyre::eyre::Report::from),
+                None => tokio::try_join!($(async {
+                    let call = $call;
+                    call.call().await
+                }),+)
+                .map_err(color_eyre::eyre::Report::from),
+            }
+        }
+    };
+}
+
+pub(crate) use batch_calls;
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{Address, Bytes, U256},
+        providers::{ProviderBuilder, bindings::IMulticall3, mock::Asserter},
+        sol_types::{SolCall, SolValue},
+    };
+
+    use crate::core::vault::ERC20;
+
+    #[tokio::test]
+    async fn test_batch_calls_parallel_and_multicall() {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        let token = ERC20::new(Address::repeat_byte(1), &provider);
+
+        // Without multicall contract: one eth_call per call
+        asserter.push_success(&Bytes::from(U256::from(8).abi_encode()));
+        asserter.push_success(&Bytes::from(U256::from(1_000).abi_encode()));
+        let (decimals, total_supply) =
+            batch_calls!(&provider, None, token.decimals(), token.totalSupply())
+                .await
+                .unwrap();
+        assert_eq!(decimals, 8);
+        assert_eq!(total_supply, U256::from(1_000));
+
+        // With multicall contract: a single aggregate eth_call
+        let aggregate =
+            IMulticall3::aggregateCall::abi_encode_returns(&IMulticall3::aggregateReturn {
+                blockNumber: U256::from(1),
+                returnData: vec![
+                    U256::from(6).abi_encode().into(),
+                    U256::from(2_000).abi_encode().into(),
+                ],
+            });
+        asserter.push_success(&Bytes::from(aggregate));
+        let (decimals, total_supply) = batch_calls!(
+            &provider,
+            Some(Address::repeat_byte(2)),
+            token.decimals(),
+            token.totalSupply()
+        )
+        .await
+        .unwrap();
+        assert_eq!(decimals, 6);
+        assert_eq!(total_supply, U256::from(2_000));
+    }
+}
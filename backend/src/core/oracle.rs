@@ -0,0 +1,201 @@
+/*
+    Pool TWAP check: the spot tick of `slot0` can be moved within a block, the time weighted
+    average tick of the pool oracle (`observe`) can't. A rebalance is only sent while both agree,
+    as `OracleLibrary.consult` of the contracts computes it.
+*/
+use std::str::FromStr;
+
+use alloy::{
+    primitives::Address,
+    providers::Provider,
+    sol_types::{Revert, SolError},
+};
+use color_eyre::eyre::{Result, eyre};
+
+use crate::{
+    core::vault::UniswapV3Pool,
+    types::{VaultConfig, VaultDetails},
+};
+
+/// Spot and TWAP ticks of the pool over the last `window_seconds`. None when the pool oracle
+/// does not cover the window: it keeps a single observation, or none as old as the window (the
+/// `OLD` revert of `observe`)
+pub async fn get_spot_and_twap_ticks<P: Provider>(
+    provider: &P,
+    pool_address: &str,
+    window_seconds: u32,
+) -> Result<Option<(i32, i32)>> {
+    let pool = UniswapV3Pool::new(Address::from_str(pool_address)?, provider);
+
+    let slot0 = pool.slot0().call().await?;
+    if slot0.observationCardinality < 2 {
+        return Ok(None);
+    }
+
+    let observation = match pool.observe(vec![window_seconds, 0]).call().await {
+        Ok(observation) => observation,
+        Err(e) => {
+            let is_old = e.as_revert_data().is_some_and(|data| {
+                Revert::abi_decode(&data).is_ok_and(|revert| revert.reason == "OLD")
+            });
+            if is_old {
+                return Ok(None);
+            }
+            return Err(eyre!(
+                "Failed to read the oracle of pool {} over {}s: {}",
+                pool_address,
+                window_seconds,
+                e
+            ));
+        }
+    };
+
+    let [start, end] = observation.tickCumulatives[..] else {
+        return Err(eyre!(
+            "Pool {} returned an invalid observation",
+            pool_address
+        ));
+    };
+
+    Ok(Some((
+        slot0.tick.as_i32(),
+        mean_tick(start.as_i64(), end.as_i64(), window_seconds)?,
+    )))
+}
+
+/// Arithmetic mean tick between two tick cumulatives, rounded to negative infinity
+pub fn mean_tick(start_cumulative: i64, end_cumulative: i64, window_seconds: u32) -> Result<i32> {
+    if window_seconds == 0 {
+        return Err(eyre!("TWAP window must not be empty"));
+    }
+
+    Ok(i32::try_from(
+        (end_cumulative - start_cumulative).div_euclid(window_seconds as i64),
+    )?)
+}
+
+/// Gap between the prices of two ticks, in %
+pub fn tick_deviation_percent(spot_tick: i32, twap_tick: i32) -> f64 {
+    (1.0001f64.powi(spot_tick - twap_tick) - 1.0).abs() * 100.0
+}
+
+/// Reason to postpone the rebalance of the vault when its pool spot price is away from the TWAP
+/// or the pool oracle can't serve the TWAP, None when they agree or the check is disabled
+pub async fn check_twap<P: Provider>(
+    provider: &P,
+    vault_details: &VaultDetails,
+    vault_config: &VaultConfig,
+) -> Result<Option<String>> {
+    if vault_config.twap_window_seconds == 0 {
+        return Ok(None);
+    }
+
+    let Some((spot_tick, twap_tick)) = get_spot_and_twap_ticks(
+        provider,
+        &vault_details.pool.address,
+        vault_config.twap_window_seconds,
+    )
+    .await?
+    else {
+        return Ok(Some(format!(
+            "The oracle of pool {} has no observation {}s old. Call increaseObservationCardinalityNext on the pool \
+            to keep more observations, or set twap_window_seconds = 0 to disable the check",
+            vault_details.pool.address, vault_config.twap_window_seconds
+        )));
+    };
+
+    let deviation = tick_deviation_percent(spot_tick, twap_tick);
+    if deviation > vault_config.max_twap_deviation_percent {
+        return Ok(Some(format!(
+            "Spot tick {} is {:.2}% away from the {}s TWAP tick {}, max is {}%",
+            spot_tick,
+            deviation,
+            vault_config.twap_window_seconds,
+            twap_tick,
+            vault_config.max_twap_deviation_percent
+        )));
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{Address, U160, aliases::I24},
+        providers::{ProviderBuilder, mock::Asserter},
+        sol_types::{Revert, SolCall, SolError},
+    };
+
+    use crate::core::vault::UniswapV3Pool;
+
+    use super::{get_spot_and_twap_ticks, mean_tick, tick_deviation_percent};
+
+    fn slot0(observation_cardinality: u16) -> String {
+        let slot0 = UniswapV3Pool::slot0Call::abi_encode_returns(&UniswapV3Pool::slot0Return {
+            sqrtPriceX96: U160::from(1),
+            tick: I24::try_from(120).unwrap(),
+            observationIndex: 0,
+            observationCardinality: observation_cardinality,
+            observationCardinalityNext: observation_cardinality,
+            feeProtocol: 0,
+            unlocked: true,
+        });
+        alloy::hex::encode_prefixed(slot0)
+    }
+
+    #[tokio::test]
+    async fn test_oracle_not_covering_the_window() {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        let pool_address = Address::repeat_byte(1).to_string();
+
+        // A single observation: `observe` is not called
+        asserter.push_success(&slot0(1));
+        let ticks = get_spot_and_twap_ticks(&provider, &pool_address, 600).await;
+        assert_eq!(ticks.unwrap(), None);
+
+        // No observation old enough
+        asserter.push_success(&slot0(10));
+        let old = Revert::from("OLD").abi_encode();
+        asserter.push_failure(
+            serde_json::from_value(serde_json::json!({
+                "code": 3,
+                "message": "execution reverted: OLD",
+                "data": alloy::hex::encode_prefixed(old),
+            }))
+            .unwrap(),
+        );
+        let ticks = get_spot_and_twap_ticks(&provider, &pool_address, 600).await;
+        assert_eq!(ticks.unwrap(), None);
+
+        // Other failures are errors
+        asserter.push_success(&slot0(10));
+        asserter.push_failure_msg("connection reset");
+        let ticks = get_spot_and_twap_ticks(&provider, &pool_address, 600).await;
+        assert!(ticks.is_err());
+    }
+
+    #[test]
+    fn test_mean_tick() {
+        assert_eq!(mean_tick(0, 6_000, 600).unwrap(), 10);
+        assert_eq!(mean_tick(1_000, -5_000, 600).unwrap(), -10);
+
+        // Rounded down like OracleLibrary.consult
+        assert_eq!(mean_tick(0, 6_001, 600).unwrap(), 10);
+        assert_eq!(mean_tick(0, -6_001, 600).unwrap(), -11);
+
+        assert!(mean_tick(0, 6_000, 0).is_err());
+    }
+
+    #[test]
+    fn test_tick_deviation_percent() {
+        assert_eq!(tick_deviation_percent(120, 120), 0.0);
+
+        // 100 ticks are about 1% of price
+        let deviation = tick_deviation_percent(-50, 50);
+        assert!((deviation - 0.995).abs() < 0.001, "{}", deviation);
+        let deviation = tick_deviation_percent(50, -50);
+        assert!((deviation - 1.005).abs() < 0.001, "{}", deviation);
+    }
+}
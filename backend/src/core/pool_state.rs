@@ -0,0 +1,493 @@
+/*
+    Local mirror of a Uniswap V3 pool (sqrt price, active liquidity and the initialized ticks
+    around the current price) used to quote swaps off-chain with the exact on-chain math.
+*/
+use std::{collections::HashMap, str::FromStr};
+
+use alloy::{
+    primitives::{Address, I256, U256, aliases::I24},
+    providers::{Provider, WalletProvider},
+};
+use color_eyre::eyre::{Result, eyre};
+use tracing::debug;
+
+use crate::{
+    config::POOL_STATE_BITMAP_WORDS,
+    core::vault::UniswapV3Pool,
+    helpers::math::uniswap_v3::{
+        U256_1, bit_math,
+        liquidity_math::add_delta,
+        swap_math::compute_swap_step,
+        tick::{Tick, tick_spacing_to_max_liquidity_per_tick, update},
+        tick_bitmap::{flip_tick, next_initialized_tick_within_one_word, position},
+        tick_math::{
+            MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK, get_sqrt_ratio_at_tick,
+            get_tick_at_sqrt_ratio,
+        },
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct PoolState {
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+    /// Pool fee in hundredths of a bip (3000 = 0.3%)
+    pub fee: u32,
+    pub tick_spacing: i32,
+    pub tick_bitmap: HashMap<i16, U256>,
+    pub ticks: HashMap<i32, Tick>,
+    /// Range of bitmap words loaded in the mirror, a swap walking outside of it can't be quoted
+    pub min_word: i16,
+    pub max_word: i16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapSimulation {
+    /// Amount of token in paid to the pool, fee included
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub fee_amount: U256,
+    pub sqrt_price_x96_after: U256,
+    pub tick_after: i32,
+}
+
+impl PoolState {
+    /// Simulate a swap receiving exactly `amount_out`, returns the amount in needed and the post-swap price
+    pub fn simulate_exact_output(
+        &self,
+        amount_out: U256,
+        zero_for_one: bool,
+    ) -> Result<SwapSimulation> {
+        let amount_specified = -I256::try_from(amount_out)?;
+        self.swap(amount_specified, zero_for_one)
+    }
+
+    /// Port of UniswapV3Pool.swap without the price limit: positive amount is exact input, negative is exact output
+    fn swap(&self, amount_specified: I256, zero_for_one: bool) -> Result<SwapSimulation> {
+        if amount_specified.is_zero() {
+            return Err(eyre!("Swap amount can not be zero"));
+        }
+
+        let exact_input = amount_specified > I256::ZERO;
+        let sqrt_price_limit_x96 = if zero_for_one {
+            MIN_SQRT_RATIO + U256_1
+        } else {
+            MAX_SQRT_RATIO - U256_1
+        };
+
+        let mut amount_specified_remaining = amount_specified;
+        let mut amount_in = U256::ZERO;
+        let mut amount_out = U256::ZERO;
+        let mut fee_amount = U256::ZERO;
+
+        let mut sqrt_price_x96 = self.sqrt_price_x96;
+        let mut tick = self.tick;
+        let mut liquidity = self.liquidity;
+
+        while !amount_specified_remaining.is_zero() && sqrt_price_x96 != sqrt_price_limit_x96 {
+            let sqrt_price_start_x96 = sqrt_price_x96;
+
+            self.ensure_word_loaded(tick, zero_for_one)?;
+
+            let (tick_next, initialized) = next_initialized_tick_within_one_word(
+                &self.tick_bitmap,
+                tick,
+                self.tick_spacing,
+                zero_for_one,
+            )?;
+            let tick_next = tick_next.clamp(MIN_TICK, MAX_TICK);
+            let sqrt_price_next_x96 = get_sqrt_ratio_at_tick(tick_next)?;
+
+            let sqrt_price_target_x96 = if (zero_for_one
+                && sqrt_price_next_x96 < sqrt_price_limit_x96)
+                || (!zero_for_one && sqrt_price_next_x96 > sqrt_price_limit_x96)
+            {
+                sqrt_price_limit_x96
+            } else {
+                sqrt_price_next_x96
+            };
+
+            let (sqrt_price_after_step_x96, step_amount_in, step_amount_out, step_fee_amount) =
+                compute_swap_step(
+                    sqrt_price_x96,
+                    sqrt_price_target_x96,
+                    liquidity,
+                    amount_specified_remaining,
+                    self.fee,
+                )?;
+            sqrt_price_x96 = sqrt_price_after_step_x96;
+
+            if exact_input {
+                amount_specified_remaining -= I256::from_raw(step_amount_in + step_fee_amount);
+            } else {
+                amount_specified_remaining += I256::from_raw(step_amount_out);
+            }
+
+            amount_in += step_amount_in + step_fee_amount;
+            amount_out += step_amount_out;
+            fee_amount += step_fee_amount;
+
+            if sqrt_price_x96 == sqrt_price_next_x96 {
+                // Crossing an initialized tick updates the active liquidity with its liquidityNet
+                if initialized {
+                    let liquidity_net = self
+                        .ticks
+                        .get(&tick_next)
+                        .map(|tick| tick.liquidity_net)
+                        .unwrap_or_default();
+                    let liquidity_net = if zero_for_one {
+                        -liquidity_net
+                    } else {
+                        liquidity_net
+                    };
+                    liquidity = add_delta(liquidity, liquidity_net)?;
+                }
+
+                tick = if zero_for_one {
+                    tick_next - 1
+                } else {
+                    tick_next
+                };
+            } else if sqrt_price_x96 != sqrt_price_start_x96 {
+                tick = get_tick_at_sqrt_ratio(sqrt_price_x96)?;
+            }
+        }
+
+        if !amount_specified_remaining.is_zero() {
+            return Err(eyre!(
+                "Not enough liquidity in the pool to fill the swap. Remaining amount: {}",
+                amount_specified_remaining
+            ));
+        }
+
+        Ok(SwapSimulation {
+            amount_in,
+            amount_out,
+            fee_amount,
+            sqrt_price_x96_after: sqrt_price_x96,
+            tick_after: tick,
+        })
+    }
+
+    /// Remove the liquidity of a position from the mirror, as a burn of it would. Its ticks outside
+    /// of the loaded words are left as is, the quotes never cross them
+    pub fn remove_position(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+    ) -> Result<()> {
+        if liquidity == 0 {
+            return Ok(());
+        }
+
+        let liquidity_delta = -i128::try_from(liquidity)?;
+        let max_liquidity = tick_spacing_to_max_liquidity_per_tick(self.tick_spacing);
+
+        for (tick, upper) in [(tick_lower, false), (tick_upper, true)] {
+            if !self.ticks.contains_key(&tick) {
+                continue;
+            }
+
+            let flipped = update(
+                &mut self.ticks,
+                tick,
+                self.tick,
+                liquidity_delta,
+                U256::ZERO,
+                U256::ZERO,
+                upper,
+                max_liquidity,
+            )?;
+
+            if flipped {
+                flip_tick(&mut self.tick_bitmap, tick, self.tick_spacing)?;
+                self.ticks.remove(&tick);
+            }
+        }
+
+        if tick_lower <= self.tick && self.tick < tick_upper {
+            self.liquidity = add_delta(self.liquidity, liquidity_delta)?;
+        }
+
+        Ok(())
+    }
+
+    /// The next initialized tick search reads a single bitmap word, make sure we loaded it
+    fn ensure_word_loaded(&self, tick: i32, lte: bool) -> Result<()> {
+        let compressed = tick.div_euclid(self.tick_spacing);
+        let (word_pos, _) = position(if lte { compressed } else { compressed + 1 });
+
+        if word_pos < self.min_word || word_pos > self.max_word {
+            return Err(eyre!(
+                "Swap moves the price out of the loaded ticks (word {} not in [{}, {}])",
+                word_pos,
+                self.min_word,
+                self.max_word
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Load the pool state with the initialized ticks of POOL_STATE_BITMAP_WORDS words on each side of the current tick
+pub async fn get_pool_state<P>(provider: &P, pool_address: &str) -> Result<PoolState>
+where
+    P: Provider + WalletProvider,
+{
+    let pool_contract = UniswapV3Pool::new(Address::from_str(pool_address)?, provider);
+
+    let slot0 = pool_contract.slot0().call().await?;
+    let sqrt_price_x96 = U256::from(slot0.sqrtPriceX96);
+    let tick = slot0.tick.as_i32();
+    let liquidity = pool_contract.liquidity().call().await?;
+    let fee = pool_contract.fee().call().await?.to::<u32>();
+    let tick_spacing = pool_contract.tickSpacing().call().await?.as_i32();
+
+    let (current_word, _) = position(tick.div_euclid(tick_spacing));
+    let min_word = current_word.saturating_sub(POOL_STATE_BITMAP_WORDS);
+    let max_word = current_word.saturating_add(POOL_STATE_BITMAP_WORDS);
+
+    let mut tick_bitmap = HashMap::new();
+    let mut ticks = HashMap::new();
+
+    for word_pos in min_word..=max_word {
+        let word = pool_contract.tickBitmap(word_pos).call().await?;
+        tick_bitmap.insert(word_pos, word);
+
+        // Fetch the liquidityNet of each initialized tick in the word
+        let mut remaining_bits = word;
+        while !remaining_bits.is_zero() {
+            let bit_pos = bit_math::least_significant_bit(remaining_bits)?;
+            remaining_bits &= remaining_bits - U256_1;
+
+            let initialized_tick = ((word_pos as i32) * 256 + bit_pos as i32) * tick_spacing;
+            let tick_info = pool_contract
+                .ticks(I24::try_from(initialized_tick)?)
+                .call()
+                .await?;
+
+            ticks.insert(
+                initialized_tick,
+                Tick {
+                    liquidity_gross: tick_info.liquidityGross,
+                    liquidity_net: tick_info.liquidityNet,
+                    fee_growth_outside_0_x_128: tick_info.feeGrowthOutside0X128,
+                    fee_growth_outside_1_x_128: tick_info.feeGrowthOutside1X128,
+                    initialized: tick_info.initialized,
+                },
+            );
+        }
+    }
+
+    debug!(
+        "Loaded pool {} state: tick {}, liquidity {}, {} initialized ticks in words [{}, {}]",
+        pool_address,
+        tick,
+        liquidity,
+        ticks.len(),
+        min_word,
+        max_word
+    );
+
+    Ok(PoolState {
+        sqrt_price_x96,
+        tick,
+        liquidity,
+        fee,
+        tick_spacing,
+        tick_bitmap,
+        ticks,
+        min_word,
+        max_word,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy::primitives::{I256, U256};
+
+    use super::PoolState;
+    use crate::helpers::math::uniswap_v3::{
+        sqrt_price_math::_get_amount_1_delta,
+        swap_math::compute_swap_step,
+        tick::{tick_spacing_to_max_liquidity_per_tick, update},
+        tick_bitmap::flip_tick,
+        tick_math::get_sqrt_ratio_at_tick,
+    };
+
+    const L_WIDE: u128 = 2_000_000_000_000_000_000;
+    const L_NARROW: u128 = 3_000_000_000_000_000_000;
+
+    fn add_position(state: &mut PoolState, tick_lower: i32, tick_upper: i32, liquidity: u128) {
+        let max_liquidity = tick_spacing_to_max_liquidity_per_tick(state.tick_spacing);
+        for (tick, upper) in [(tick_lower, false), (tick_upper, true)] {
+            let flipped = update(
+                &mut state.ticks,
+                tick,
+                state.tick,
+                liquidity as i128,
+                U256::ZERO,
+                U256::ZERO,
+                upper,
+                max_liquidity,
+            )
+            .unwrap();
+            if flipped {
+                flip_tick(&mut state.tick_bitmap, tick, state.tick_spacing).unwrap();
+            }
+        }
+        if tick_lower <= state.tick && state.tick < tick_upper {
+            state.liquidity += liquidity;
+        }
+    }
+
+    fn empty_pool_state() -> PoolState {
+        PoolState {
+            sqrt_price_x96: get_sqrt_ratio_at_tick(0).unwrap(),
+            tick: 0,
+            liquidity: 0,
+            fee: 3000,
+            tick_spacing: 60,
+            tick_bitmap: HashMap::new(),
+            ticks: HashMap::new(),
+            min_word: -1,
+            max_word: 0,
+        }
+    }
+
+    // Pool at tick 0 with a wide [-600, 600] position and a narrow [-120, 120] one
+    fn init_pool_state() -> PoolState {
+        let mut state = empty_pool_state();
+        add_position(&mut state, -600, 600, L_WIDE);
+        add_position(&mut state, -120, 120, L_NARROW);
+        state
+    }
+
+    #[test]
+    fn test_simulate_exact_output_within_tick() {
+        let state = init_pool_state();
+        let amount_out = U256::from(1_000_000_000_000_000u64);
+
+        let simulation = state.simulate_exact_output(amount_out, true).unwrap();
+
+        // Same as a single swap step with the active liquidity
+        let (sqrt_price_next, amount_in, step_amount_out, fee_amount) = compute_swap_step(
+            state.sqrt_price_x96,
+            get_sqrt_ratio_at_tick(-120).unwrap(),
+            L_WIDE + L_NARROW,
+            -I256::from_raw(amount_out),
+            state.fee,
+        )
+        .unwrap();
+
+        assert_eq!(simulation.amount_out, amount_out);
+        assert_eq!(step_amount_out, amount_out);
+        assert_eq!(simulation.amount_in, amount_in + fee_amount);
+        assert_eq!(simulation.fee_amount, fee_amount);
+        assert_eq!(simulation.sqrt_price_x96_after, sqrt_price_next);
+        assert!(simulation.sqrt_price_x96_after < state.sqrt_price_x96);
+        assert!(simulation.tick_after < 0 && simulation.tick_after > -120);
+    }
+
+    #[test]
+    fn test_simulate_exact_output_crosses_tick() {
+        let state = init_pool_state();
+        let sqrt_price_edge = get_sqrt_ratio_at_tick(-120).unwrap();
+
+        // Token1 available before reaching the narrow position lower tick
+        let first_step_out = _get_amount_1_delta(
+            sqrt_price_edge,
+            state.sqrt_price_x96,
+            L_WIDE + L_NARROW,
+            false,
+        )
+        .unwrap();
+        let extra_out = U256::from(1_000_000_000_000_000u64);
+        let amount_out = first_step_out + extra_out;
+
+        let simulation = state.simulate_exact_output(amount_out, true).unwrap();
+
+        let (_, first_in, _, first_fee) = compute_swap_step(
+            state.sqrt_price_x96,
+            sqrt_price_edge,
+            L_WIDE + L_NARROW,
+            -I256::from_raw(amount_out),
+            state.fee,
+        )
+        .unwrap();
+        // After crossing -120 only the wide position liquidity is left
+        let (sqrt_price_next, second_in, _, second_fee) = compute_swap_step(
+            sqrt_price_edge,
+            get_sqrt_ratio_at_tick(-600).unwrap(),
+            L_WIDE,
+            -I256::from_raw(extra_out),
+            state.fee,
+        )
+        .unwrap();
+
+        assert_eq!(simulation.amount_out, amount_out);
+        assert_eq!(
+            simulation.amount_in,
+            first_in + first_fee + second_in + second_fee
+        );
+        assert_eq!(simulation.sqrt_price_x96_after, sqrt_price_next);
+        assert!(simulation.tick_after < -120);
+    }
+
+    #[test]
+    fn test_simulate_exact_output_not_enough_liquidity() {
+        let state = init_pool_state();
+
+        // More token0 than the positions hold above the current price
+        let amount_out = U256::from(10u128.pow(21));
+        assert!(state.simulate_exact_output(amount_out, false).is_err());
+    }
+
+    #[test]
+    fn test_remove_position() {
+        // The vault narrow position is most of the pool liquidity
+        let mut with_vault = empty_pool_state();
+        add_position(&mut with_vault, -600, 600, L_WIDE);
+        add_position(&mut with_vault, -120, 120, 10 * L_NARROW);
+
+        let mut without_vault = empty_pool_state();
+        add_position(&mut without_vault, -600, 600, L_WIDE);
+
+        let amount_out = U256::from(10_000_000_000_000_000u64);
+        let amount_in_with_vault = with_vault
+            .simulate_exact_output(amount_out, true)
+            .unwrap()
+            .amount_in;
+
+        with_vault
+            .remove_position(-120, 120, 10 * L_NARROW)
+            .unwrap();
+
+        assert_eq!(with_vault.liquidity, L_WIDE);
+        assert!(!with_vault.ticks.contains_key(&-120));
+        assert!(!with_vault.ticks.contains_key(&120));
+        assert_eq!(with_vault.tick_bitmap, without_vault.tick_bitmap);
+
+        // Same quote as the pool without the vault, with a larger price impact
+        let simulation = with_vault.simulate_exact_output(amount_out, true).unwrap();
+        assert_eq!(
+            simulation,
+            without_vault
+                .simulate_exact_output(amount_out, true)
+                .unwrap()
+        );
+        assert!(simulation.amount_in > amount_in_with_vault);
+
+        // Shared ticks keep the liquidity of the other positions
+        let mut state = init_pool_state();
+        add_position(&mut state, -600, 120, L_NARROW);
+        state.remove_position(-600, 120, L_NARROW).unwrap();
+        assert_eq!(state.ticks[&-600].liquidity_net, L_WIDE as i128);
+        assert_eq!(state.ticks[&120].liquidity_net, -(L_NARROW as i128));
+    }
+}
@@ -0,0 +1,429 @@
+/*
+    Per-account vault positions. Shares and redeemable amounts are read on-chain, the cost
+    basis is rebuilt from the indexed deposits, withdrawals and share transfers (average cost: a
+    withdrawal or a sent transfer removes the same fraction of the cost basis as of the shares).
+*/
+use std::str::FromStr;
+
+use alloy::primitives::{Address, U256, utils::format_units};
+use color_eyre::eyre::Result;
+
+use crate::{
+    core::vault::YielderaVault,
+    types::{EvmProvider, UserPosition, VaultDetails, VaultEvent, VaultEventData, WebAppState},
+};
+
+/// Positions of an account in the managed vaults it holds shares of or has deposited in
+pub async fn get_user_positions(
+    app_state: &WebAppState,
+    account: Address,
+) -> Result<Vec<UserPosition>> {
+    let events = app_state.db.account_events(&account.to_string())?;
+
+    let vaults: Vec<VaultDetails> = app_state
+        .all_vaults
+        .iter()
+        .map(|entry| entry.value().clone())
+        .collect();
+
+    let mut positions = Vec::new();
+
+    for vault in vaults {
+        let vault_events: Vec<VaultEvent> = events
+            .iter()
+            .filter(|event| event.vault_address.eq_ignore_ascii_case(&vault.address))
+            .cloned()
+            .collect();
+
+        let state = get_vault_share_state(&app_state.evm_provider, &vault, account).await?;
+
+        if state.shares == 0.0 && vault_events.is_empty() {
+            continue;
+        }
+
+        let is_indexed_from_deployment = app_state.db.is_indexed_from_deployment(&vault.address)?;
+
+        positions.push(UserPosition::from_events(
+            &vault,
+            &account.to_string(),
+            &state,
+            &vault_events,
+            is_indexed_from_deployment,
+        )?);
+    }
+
+    Ok(positions)
+}
+
+/// Live vault amounts needed to value the shares of an account
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VaultShareState {
+    pub shares: f64,
+    pub total_supply: f64,
+    pub total0: f64,
+    pub total1: f64,
+}
+
+pub async fn get_vault_share_state(
+    provider: &EvmProvider,
+    vault: &VaultDetails,
+    account: Address,
+) -> Result<VaultShareState> {
+    let vault_contract = YielderaVault::new(Address::from_str(&vault.address)?, provider);
+
+    let shares = vault_contract.balanceOf(account).call().await?;
+    let total_supply = vault_contract.totalSupply().call().await?;
+    let total_amounts = vault_contract.getTotalAmounts().call().await?;
+
+    Ok(VaultShareState {
+        shares: format_units(shares, vault.decimals)?.parse()?,
+        total_supply: format_units(total_supply, vault.decimals)?.parse()?,
+        total0: format_units(total_amounts.total0, vault.pool.token0.decimals)?.parse()?,
+        total1: format_units(total_amounts.total1, vault.pool.token1.decimals)?.parse()?,
+    })
+}
+
+impl UserPosition {
+    /// Position of an account from the vault live amounts and its events in the vault. The events
+    /// before the indexer start block are missing when it is not the vault deployment block
+    pub fn from_events(
+        vault: &VaultDetails,
+        account: &str,
+        state: &VaultShareState,
+        events: &[VaultEvent],
+        is_indexed_from_deployment: bool,
+    ) -> Result<Self> {
+        let format0 = |amount: U256| -> Result<f64> {
+            Ok(format_units(amount, vault.pool.token0.decimals)?.parse()?)
+        };
+        let format1 = |amount: U256| -> Result<f64> {
+            Ok(format_units(amount, vault.pool.token1.decimals)?.parse()?)
+        };
+        let format_shares =
+            |shares: U256| -> Result<f64> { Ok(format_units(shares, vault.decimals)?.parse()?) };
+
+        let mut tracked_shares = 0.0;
+        let (mut cost_basis0, mut cost_basis1) = (0.0, 0.0);
+        let (mut deposited0, mut deposited1) = (0.0, 0.0);
+        let (mut withdrawn0, mut withdrawn1) = (0.0, 0.0);
+        let mut has_received_shares = false;
+
+        for event in events {
+            match &event.event {
+                VaultEventData::Deposit {
+                    shares,
+                    amount0,
+                    amount1,
+                    ..
+                } => {
+                    let (amount0, amount1) = (format0(*amount0)?, format1(*amount1)?);
+                    tracked_shares += format_shares(*shares)?;
+                    cost_basis0 += amount0;
+                    cost_basis1 += amount1;
+                    deposited0 += amount0;
+                    deposited1 += amount1;
+                }
+                VaultEventData::Withdraw {
+                    shares,
+                    amount0,
+                    amount1,
+                    ..
+                } => {
+                    let shares = format_shares(*shares)?;
+                    let remaining = if tracked_shares > 0.0 {
+                        (1.0 - shares / tracked_shares).max(0.0)
+                    } else {
+                        0.0
+                    };
+                    tracked_shares = (tracked_shares - shares).max(0.0);
+                    cost_basis0 *= remaining;
+                    cost_basis1 *= remaining;
+                    withdrawn0 += format0(*amount0)?;
+                    withdrawn1 += format1(*amount1)?;
+                }
+                // A transfer to itself moves no shares
+                VaultEventData::Transfer { from, to, .. } if from.eq_ignore_ascii_case(to) => {}
+                VaultEventData::Transfer { from, value, .. }
+                    if from.eq_ignore_ascii_case(account) =>
+                {
+                    let shares = format_shares(*value)?;
+                    let remaining = if tracked_shares > 0.0 {
+                        (1.0 - shares / tracked_shares).max(0.0)
+                    } else {
+                        0.0
+                    };
+                    tracked_shares = (tracked_shares - shares).max(0.0);
+                    cost_basis0 *= remaining;
+                    cost_basis1 *= remaining;
+                }
+                VaultEventData::Transfer { value, .. } => {
+                    // Received shares have no known cost
+                    tracked_shares += format_shares(*value)?;
+                    has_received_shares = true;
+                }
+                _ => {}
+            }
+        }
+
+        // Shares sent to another account take their part of the cost basis with them
+        let is_cost_basis_complete = is_indexed_from_deployment
+            && !has_received_shares
+            && state.shares <= tracked_shares * (1.0 + 1e-9);
+        if is_cost_basis_complete && tracked_shares > 0.0 {
+            cost_basis0 *= state.shares / tracked_shares;
+            cost_basis1 *= state.shares / tracked_shares;
+        }
+
+        let share = if state.total_supply > 0.0 {
+            state.shares / state.total_supply
+        } else {
+            0.0
+        };
+
+        let price1 = vault.pool.price1;
+        let amount0 = state.total0 * share;
+        let amount1 = state.total1 * share;
+        let value_in_token1 = amount0 * price1 + amount1;
+        let hodl_value_in_token1 = cost_basis0 * price1 + cost_basis1;
+        let pnl_in_token1 = value_in_token1 - hodl_value_in_token1;
+        let pnl_percent = if hodl_value_in_token1 > 0.0 {
+            pnl_in_token1 / hodl_value_in_token1 * 100.0
+        } else {
+            0.0
+        };
+
+        Ok(Self {
+            vault_address: vault.address.clone(),
+            vault_symbol: vault.symbol.clone(),
+            token0_symbol: vault.pool.token0.symbol.clone(),
+            token1_symbol: vault.pool.token1.symbol.clone(),
+            shares: state.shares,
+            share_of_vault: share * 100.0,
+            amount0,
+            amount1,
+            value_in_token1,
+            deposited0,
+            deposited1,
+            withdrawn0,
+            withdrawn1,
+            cost_basis0,
+            cost_basis1,
+            hodl_value_in_token1,
+            pnl_in_token1,
+            pnl_percent,
+            is_cost_basis_complete,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{U256, utils::parse_units};
+
+    use crate::types::{
+        Pool, Position, Token, UserPosition, VaultDetails, VaultEvent, VaultEventData, VaultFees,
+        VaultTVL,
+    };
+
+    use super::VaultShareState;
+
+    fn units(amount: &str, decimals: u8) -> U256 {
+        parse_units(amount, decimals).unwrap().into()
+    }
+
+    fn event(event: VaultEventData) -> VaultEvent {
+        VaultEvent {
+            vault_address: "0xvault".to_string(),
+            block_number: 1,
+            block_timestamp: None,
+            tx_hash: "0x1".to_string(),
+            log_index: 0,
+            event,
+        }
+    }
+
+    fn deposit(shares: &str, amount0: &str, amount1: &str) -> VaultEvent {
+        event(VaultEventData::Deposit {
+            sender: "0xuser".to_string(),
+            to: "0xuser".to_string(),
+            shares: units(shares, 18),
+            amount0: units(amount0, 8),
+            amount1: units(amount1, 6),
+        })
+    }
+
+    fn withdraw(shares: &str, amount0: &str, amount1: &str) -> VaultEvent {
+        event(VaultEventData::Withdraw {
+            sender: "0xuser".to_string(),
+            to: "0xuser".to_string(),
+            shares: units(shares, 18),
+            amount0: units(amount0, 8),
+            amount1: units(amount1, 6),
+        })
+    }
+
+    fn vault(price1: f64) -> VaultDetails {
+        let token = |symbol: &str, decimals: u8| Token {
+            address: format!("0x{}", symbol),
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            decimals,
+            is_native_wrapper: false,
+        };
+
+        VaultDetails {
+            address: "0xvault".to_string(),
+            pool: Pool {
+                address: "0xpool".to_string(),
+                token0: token("WHBAR", 8),
+                token1: token("USDC", 6),
+                fee: 0.3,
+                tick_spacing: 60,
+                current_tick: 0,
+                sqrt_price_x96: U256::ZERO,
+                price1,
+                price0: 1.0 / price1,
+            },
+            name: "Yieldera Vault".to_string(),
+            symbol: "YV".to_string(),
+            decimals: 18,
+            total_supply: 0.0,
+            lower_tick: 0,
+            upper_tick: 0,
+            is_active: false,
+            is_vault_tokens_associated: true,
+            position: Position::default(),
+            tvl: VaultTVL {
+                tvl0: 0.0,
+                tvl1: 0.0,
+            },
+            fees: VaultFees::default(),
+        }
+    }
+
+    #[test]
+    fn test_position_average_cost_and_pnl() {
+        // Deposit 100 shares for 10 token0 + 20 token1, then withdraw half of them
+        let events = vec![deposit("100", "10", "20"), withdraw("50", "6", "18")];
+        let state = VaultShareState {
+            shares: 50.0,
+            total_supply: 200.0,
+            total0: 40.0,
+            total1: 100.0,
+        };
+
+        let position =
+            UserPosition::from_events(&vault(2.0), "0xuser", &state, &events, true).unwrap();
+
+        assert_eq!(position.share_of_vault, 25.0);
+        assert_eq!(position.amount0, 10.0);
+        assert_eq!(position.amount1, 25.0);
+        assert_eq!(position.value_in_token1, 45.0);
+        assert_eq!(position.deposited0, 10.0);
+        assert_eq!(position.withdrawn1, 18.0);
+        assert_eq!(position.cost_basis0, 5.0);
+        assert_eq!(position.cost_basis1, 10.0);
+        assert_eq!(position.hodl_value_in_token1, 20.0);
+        assert_eq!(position.pnl_in_token1, 25.0);
+        assert_eq!(position.pnl_percent, 125.0);
+        assert!(position.is_cost_basis_complete);
+    }
+
+    #[test]
+    fn test_position_with_transferred_shares() {
+        let events = vec![deposit("100", "10", "20")];
+
+        // Half of the shares were sent to another account
+        let state = VaultShareState {
+            shares: 50.0,
+            total_supply: 100.0,
+            total0: 10.0,
+            total1: 20.0,
+        };
+        let position =
+            UserPosition::from_events(&vault(1.0), "0xuser", &state, &events, true).unwrap();
+        assert_eq!(position.cost_basis0, 5.0);
+        assert_eq!(position.pnl_in_token1, 0.0);
+        assert!(position.is_cost_basis_complete);
+
+        // Shares received from another account have no known cost
+        let state = VaultShareState {
+            shares: 150.0,
+            ..state
+        };
+        let position =
+            UserPosition::from_events(&vault(1.0), "0xuser", &state, &events, true).unwrap();
+        assert_eq!(position.cost_basis0, 10.0);
+        assert!(!position.is_cost_basis_complete);
+    }
+
+    #[test]
+    fn test_position_with_indexed_transfers() {
+        let transfer = |from: &str, to: &str, shares: &str| {
+            event(VaultEventData::Transfer {
+                from: from.to_string(),
+                to: to.to_string(),
+                value: units(shares, 18),
+            })
+        };
+        let state = VaultShareState {
+            shares: 75.0,
+            total_supply: 100.0,
+            total0: 10.0,
+            total1: 20.0,
+        };
+
+        // A quarter of the shares sent away take their part of the cost basis
+        let events = vec![
+            deposit("100", "10", "20"),
+            transfer("0xUSER", "0xother", "25"),
+        ];
+        let position =
+            UserPosition::from_events(&vault(1.0), "0xuser", &state, &events, true).unwrap();
+        assert_eq!(position.cost_basis0, 7.5);
+        assert_eq!(position.cost_basis1, 15.0);
+        assert!(position.is_cost_basis_complete);
+
+        // Received shares have no known cost
+        let events = vec![
+            deposit("50", "5", "10"),
+            transfer("0xother", "0xuser", "25"),
+        ];
+        let position =
+            UserPosition::from_events(&vault(1.0), "0xuser", &state, &events, true).unwrap();
+        assert_eq!(position.cost_basis0, 5.0);
+        assert!(!position.is_cost_basis_complete);
+
+        // A transfer to itself changes nothing
+        let state = VaultShareState {
+            shares: 100.0,
+            ..state
+        };
+        let events = vec![
+            deposit("100", "10", "20"),
+            transfer("0xUSER", "0xuser", "25"),
+        ];
+        let position =
+            UserPosition::from_events(&vault(1.0), "0xuser", &state, &events, true).unwrap();
+        assert_eq!(position.cost_basis0, 10.0);
+        assert_eq!(position.cost_basis1, 20.0);
+        assert!(position.is_cost_basis_complete);
+    }
+
+    #[test]
+    fn test_position_indexed_after_deployment() {
+        // The deposits before the indexer start block are missing
+        let events = vec![deposit("100", "10", "20")];
+        let state = VaultShareState {
+            shares: 100.0,
+            total_supply: 100.0,
+            total0: 10.0,
+            total1: 20.0,
+        };
+
+        let position =
+            UserPosition::from_events(&vault(1.0), "0xuser", &state, &events, false).unwrap();
+        assert_eq!(position.cost_basis0, 10.0);
+        assert!(!position.is_cost_basis_complete);
+    }
+}
@@ -0,0 +1,203 @@
+/*
+    Pre-flight of the vault transactions: each call is simulated with eth_estimateGas before
+    being sent, with the estimate (plus a margin) as its gas limit. A simulation that reverts
+    is decoded against the vault ABI errors, the Uniswap V3 pool errors and the revert strings
+    into a `TransactionError` and nothing is sent.
+*/
+use alloy::{
+    contract::{CallBuilder, CallDecoder},
+    primitives::Bytes,
+    providers::Provider,
+    rpc::types::{TransactionReceipt, TransactionRequest},
+    sol,
+    sol_types::{SolInterface, decode_revert_reason},
+};
+use thiserror::Error;
+use tracing::debug;
+
+use crate::{config::GAS_LIMIT_MARGIN_PERCENT, core::vault::YielderaVault};
+
+sol! {
+    /// Errors of the Uniswap V3 pools, see `IUniswapV3PoolErrors.sol`
+    interface IUniswapV3PoolErrors {
+        error MF();
+        error LOK();
+        error TLU();
+        error TLM();
+        error TUM();
+        error AI();
+        error M0();
+        error M1();
+        error AS();
+        error IIA();
+        error L();
+        error F0();
+        error F1();
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TransactionError {
+    #[error("Vault reverted with {0}")]
+    Vault(String),
+    #[error("Pool reverted with {code}: {description}")]
+    Pool {
+        code: &'static str,
+        description: &'static str,
+    },
+    #[error("Reverted: {0}")]
+    Revert(String),
+    #[error("Reverted with undecoded data {0}")]
+    UnknownRevert(Bytes),
+    #[error("Transaction {tx_hash} reverted on chain after using {gas_used} gas")]
+    Reverted { tx_hash: String, gas_used: u64 },
+    #[error("Transaction with nonce {nonce} not mined after {attempts} attempts")]
+    NotMined { nonce: u64, attempts: usize },
+    #[error("RPC error: {0}")]
+    Rpc(String),
+}
+
+impl From<alloy::contract::Error> for TransactionError {
+    fn from(e: alloy::contract::Error) -> Self {
+        match e.as_revert_data() {
+            Some(data) => decode_revert(&data),
+            None => TransactionError::Rpc(e.to_string()),
+        }
+    }
+}
+
+/// Decode the revert data of a vault call
+pub fn decode_revert(data: &[u8]) -> TransactionError {
+    if let Ok(error) = YielderaVault::YielderaVaultErrors::abi_decode(data) {
+        return TransactionError::Vault(format!("{:?}", error));
+    }
+
+    if let Ok(error) = IUniswapV3PoolErrors::IUniswapV3PoolErrorsErrors::abi_decode(data) {
+        let (code, description) = pool_error(&error);
+        return TransactionError::Pool { code, description };
+    }
+
+    match decode_revert_reason(data) {
+        Some(reason) => TransactionError::Revert(reason),
+        None => TransactionError::UnknownRevert(Bytes::copy_from_slice(data)),
+    }
+}
+
+fn pool_error(
+    error: &IUniswapV3PoolErrors::IUniswapV3PoolErrorsErrors,
+) -> (&'static str, &'static str) {
+    use IUniswapV3PoolErrors::IUniswapV3PoolErrorsErrors as E;
+
+    match error {
+        E::MF(_) => ("MF", "pool operation failed"),
+        E::LOK(_) => ("LOK", "pool is locked (reentrant call)"),
+        E::TLU(_) => ("TLU", "lower tick must be below the upper tick"),
+        E::TLM(_) => ("TLM", "lower tick is below the min tick"),
+        E::TUM(_) => ("TUM", "upper tick is above the max tick"),
+        E::AI(_) => ("AI", "pool is already initialized"),
+        E::M0(_) => ("M0", "token0 not received for the mint"),
+        E::M1(_) => ("M1", "token1 not received for the mint"),
+        E::AS(_) => ("AS", "swap amount is zero"),
+        E::IIA(_) => ("IIA", "insufficient input amount for the swap"),
+        E::L(_) => ("L", "pool has no liquidity"),
+        E::F0(_) => ("F0", "token0 of the flash loan not repaid"),
+        E::F1(_) => ("F1", "token1 of the flash loan not repaid"),
+    }
+}
+
+/// Gas limit sent with a transaction, the estimate plus the margin
+pub fn gas_limit(estimate: u64) -> u64 {
+    let gas_limit = u128::from(estimate) * u128::from(100 + GAS_LIMIT_MARGIN_PERCENT) / 100;
+    u64::try_from(gas_limit).unwrap_or(u64::MAX)
+}
+
+/// Simulate the call and return its gas limit. A node not returning the revert data of a failed
+/// estimate gets the call again as an eth_call to decode it
+pub async fn simulate<P, D>(call: &CallBuilder<P, D>) -> Result<u64, TransactionError>
+where
+    P: Provider,
+    D: CallDecoder,
+{
+    match call.estimate_gas().await {
+        Ok(estimate) => {
+            debug!("Gas estimate {}", estimate);
+            Ok(gas_limit(estimate))
+        }
+        Err(e) if e.as_revert_data().is_some() => Err(e.into()),
+        Err(e) => match call.call_raw().await {
+            Err(call_error) if call_error.as_revert_data().is_some() => Err(call_error.into()),
+            _ => Err(e.into()),
+        },
+    }
+}
+
+/// Simulate the call and return its transaction with the estimated gas limit, to submit to the
+/// transaction manager
+pub async fn prepare<P, D>(call: CallBuilder<P, D>) -> Result<TransactionRequest, TransactionError>
+where
+    P: Provider,
+    D: CallDecoder,
+{
+    let gas = simulate(&call).await?;
+
+    Ok(call.gas(gas).into_transaction_request())
+}
+
+/// Error of a receipt with a failed status
+pub fn check_receipt(receipt: &TransactionReceipt) -> Result<(), TransactionError> {
+    if receipt.status() {
+        return Ok(());
+    }
+
+    Err(TransactionError::Reverted {
+        tx_hash: receipt.transaction_hash.to_string(),
+        gas_used: receipt.gas_used,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::Address,
+        sol_types::{Revert, SolError},
+    };
+
+    use crate::core::vault::YielderaVault;
+
+    use super::{IUniswapV3PoolErrors, TransactionError, decode_revert, gas_limit};
+
+    #[test]
+    fn test_decode_revert() {
+        let data = YielderaVault::OwnableUnauthorizedAccount {
+            account: Address::repeat_byte(1),
+        }
+        .abi_encode();
+        assert!(matches!(
+            decode_revert(&data),
+            TransactionError::Vault(error) if error.contains("OwnableUnauthorizedAccount")
+        ));
+
+        let data = IUniswapV3PoolErrors::TLU {}.abi_encode();
+        assert!(matches!(
+            decode_revert(&data),
+            TransactionError::Pool { code: "TLU", .. }
+        ));
+
+        let data = Revert::from("Invalid ticks").abi_encode();
+        assert!(matches!(
+            decode_revert(&data),
+            TransactionError::Revert(reason) if reason.contains("Invalid ticks")
+        ));
+
+        assert!(matches!(
+            decode_revert(&[0xde, 0xad, 0xbe, 0xef]),
+            TransactionError::UnknownRevert(_)
+        ));
+    }
+
+    #[test]
+    fn test_gas_limit() {
+        assert_eq!(gas_limit(1_000_000), 1_200_000);
+        assert_eq!(gas_limit(u64::MAX), u64::MAX);
+    }
+}
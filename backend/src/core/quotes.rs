@@ -0,0 +1,278 @@
+/*
+    Deposit and withdraw previews: read the vault state the contract reads and run the share
+    math of helpers::math::vault_shares on it.
+*/
+use std::str::FromStr;
+
+use alloy::{
+    primitives::{Address, U256, keccak256, utils::format_units},
+    providers::Provider,
+};
+use alloy_sol_types::SolValue;
+use color_eyre::eyre::Result;
+
+use crate::{
+    core::vault::{ERC20, UniswapV3Pool, YielderaVault},
+    helpers::{
+        math::vault_shares::{
+            PRECISION, WithdrawState, amounts_for_withdraw, get_quote_at_tick, shares_for_deposit,
+        },
+        vault::deposit_value,
+    },
+    types::{DepositQuote, EvmProvider, Token, VaultDetails, WithdrawQuote},
+};
+
+pub async fn quote_deposit<P: Provider>(
+    provider: &P,
+    vault: &VaultDetails,
+    amount0_raw: U256,
+    amount1_raw: U256,
+) -> Result<DepositQuote> {
+    let token0 = &vault.pool.token0;
+    let token1 = &vault.pool.token1;
+
+    let vault_address = Address::from_str(&vault.address)?;
+    let vault_contract = YielderaVault::new(vault_address, provider);
+
+    let current_tick = vault_contract.currentTick().call().await?.as_i32();
+    let total_amounts = vault_contract.getTotalAmounts().call().await?;
+    let total_supply = vault_contract.totalSupply().call().await?;
+
+    // `deposit` collects the position fees before it reads the total amounts
+    let (fees_owed0, fees_owed1) = position_fees_owed(
+        provider,
+        vault_address,
+        Address::from_str(&vault.pool.address)?,
+    )
+    .await?;
+
+    let token0_is_lower = Address::from_str(&token0.address)? < Address::from_str(&token1.address)?;
+    let spot_price = get_quote_at_tick(current_tick, PRECISION.to(), token0_is_lower)?;
+
+    let shares_raw = shares_for_deposit(
+        amount0_raw,
+        amount1_raw,
+        total_amounts.total0 + fees_owed0,
+        total_amounts.total1 + fees_owed1,
+        total_supply,
+        spot_price,
+    )?;
+
+    let shares: f64 = format_units(shares_raw, vault.decimals)?.parse()?;
+    let total_supply_after: f64 =
+        format_units(total_supply + shares_raw, vault.decimals)?.parse()?;
+
+    // Only one side of a vault can be WHBAR, its amount is paid with the transaction value
+    let value = deposit_value(vault, amount0_raw, amount1_raw);
+    let native_token = [&vault.pool.token0, &vault.pool.token1]
+        .into_iter()
+        .find(|token: &&Token| token.is_native_wrapper)
+        .filter(|_| !value.is_zero())
+        .map(|token| token.symbol.clone());
+
+    Ok(DepositQuote {
+        vault_address: vault.address.clone(),
+        amount0: format_units(amount0_raw, token0.decimals)?.parse()?,
+        amount1: format_units(amount1_raw, token1.decimals)?.parse()?,
+        amount0_raw,
+        amount1_raw,
+        shares,
+        shares_raw,
+        share_of_vault_after: if total_supply_after > 0.0 {
+            shares / total_supply_after * 100.0
+        } else {
+            0.0
+        },
+        native_token,
+        value,
+    })
+}
+
+/// Fees owed to the vault position, zero when the vault has no position
+async fn position_fees_owed<P: Provider>(
+    provider: &P,
+    vault_address: Address,
+    pool_address: Address,
+) -> Result<(U256, U256)> {
+    let vault_contract = YielderaVault::new(vault_address, provider);
+
+    if !vault_contract.isActive().call().await? {
+        return Ok((U256::ZERO, U256::ZERO));
+    }
+
+    let lower_tick = vault_contract.lowerTick().call().await?;
+    let upper_tick = vault_contract.upperTick().call().await?;
+    let position_key = keccak256((vault_address, lower_tick, upper_tick).abi_encode_packed());
+
+    let position = UniswapV3Pool::new(pool_address, provider)
+        .positions(position_key)
+        .call()
+        .await?;
+
+    Ok((
+        U256::from(position.tokensOwed0),
+        U256::from(position.tokensOwed1),
+    ))
+}
+
+pub async fn quote_withdraw(
+    provider: &EvmProvider,
+    vault: &VaultDetails,
+    shares_raw: U256,
+) -> Result<WithdrawQuote> {
+    let vault_address = Address::from_str(&vault.address)?;
+    let vault_contract = YielderaVault::new(vault_address, provider);
+    let pool_contract = UniswapV3Pool::new(Address::from_str(&vault.pool.address)?, provider);
+    let token0_contract = ERC20::new(Address::from_str(&vault.pool.token0.address)?, provider);
+    let token1_contract = ERC20::new(Address::from_str(&vault.pool.token1.address)?, provider);
+
+    let slot0 = pool_contract.slot0().call().await?;
+
+    let state = WithdrawState {
+        total_supply: vault_contract.totalSupply().call().await?,
+        balance0: token0_contract.balanceOf(vault_address).call().await?,
+        balance1: token1_contract.balanceOf(vault_address).call().await?,
+        position_liquidity: vault_contract.getCurrentPosition().call().await?.liquidity,
+        sqrt_price_x96: U256::from_str(slot0.sqrtPriceX96.to_string().as_str())?,
+        lower_tick: vault_contract.lowerTick().call().await?.as_i32(),
+        upper_tick: vault_contract.upperTick().call().await?.as_i32(),
+    };
+
+    let (amount0_raw, amount1_raw) = amounts_for_withdraw(shares_raw, &state)?;
+
+    let native_token = [&vault.pool.token0, &vault.pool.token1]
+        .into_iter()
+        .find(|token: &&Token| token.is_native_wrapper)
+        .map(|token| token.symbol.clone());
+
+    Ok(WithdrawQuote {
+        vault_address: vault.address.clone(),
+        shares: format_units(shares_raw, vault.decimals)?.parse()?,
+        shares_raw,
+        amount0: format_units(amount0_raw, vault.pool.token0.decimals)?.parse()?,
+        amount1: format_units(amount1_raw, vault.pool.token1.decimals)?.parse()?,
+        amount0_raw,
+        amount1_raw,
+        native_token,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{Address, U256, aliases::I24},
+        providers::{ProviderBuilder, mock::Asserter},
+        sol_types::SolCall,
+    };
+
+    use crate::{
+        core::vault::{UniswapV3Pool, YielderaVault},
+        helpers::math::vault_shares::{PRECISION, get_quote_at_tick, shares_for_deposit},
+        types::{Pool, Position, Token, VaultDetails, VaultFees, VaultTVL},
+    };
+
+    use super::quote_deposit;
+
+    fn vault() -> VaultDetails {
+        let token = |address: Address, symbol: &str| Token {
+            address: address.to_string(),
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            decimals: 6,
+            is_native_wrapper: false,
+        };
+
+        VaultDetails {
+            address: Address::repeat_byte(1).to_string(),
+            pool: Pool {
+                address: Address::repeat_byte(2).to_string(),
+                token0: token(Address::repeat_byte(3), "USDC"),
+                token1: token(Address::repeat_byte(4), "USDT"),
+                fee: 0.05,
+                tick_spacing: 10,
+                current_tick: 0,
+                sqrt_price_x96: U256::ZERO,
+                price1: 1.0,
+                price0: 1.0,
+            },
+            name: "Yieldera Vault".to_string(),
+            symbol: "YV".to_string(),
+            decimals: 6,
+            total_supply: 0.0,
+            lower_tick: -60,
+            upper_tick: 60,
+            is_active: true,
+            is_vault_tokens_associated: true,
+            position: Position::default(),
+            tvl: VaultTVL {
+                tvl0: 0.0,
+                tvl1: 0.0,
+            },
+            fees: VaultFees::default(),
+        }
+    }
+
+    /// Responses of the calls of `quote_deposit`, in order
+    fn push_vault_state(asserter: &Asserter, fees_owed: u128) {
+        let tick = |tick: i32| I24::try_from(tick).unwrap();
+
+        asserter.push_success(&YielderaVault::currentTickCall::abi_encode_returns(&tick(
+            0,
+        )));
+        asserter.push_success(&YielderaVault::getTotalAmountsCall::abi_encode_returns(
+            &YielderaVault::getTotalAmountsReturn {
+                total0: U256::from(1_000_000_000u64),
+                total1: U256::from(1_000_000_000u64),
+            },
+        ));
+        asserter.push_success(&YielderaVault::totalSupplyCall::abi_encode_returns(
+            &U256::from(2_000_000_000u64),
+        ));
+        asserter.push_success(&YielderaVault::isActiveCall::abi_encode_returns(&true));
+        asserter.push_success(&YielderaVault::lowerTickCall::abi_encode_returns(&tick(
+            -60,
+        )));
+        asserter.push_success(&YielderaVault::upperTickCall::abi_encode_returns(&tick(60)));
+        asserter.push_success(&UniswapV3Pool::positionsCall::abi_encode_returns(
+            &UniswapV3Pool::positionsReturn {
+                liquidity: 1,
+                feeGrowthInside0LastX128: U256::ZERO,
+                feeGrowthInside1LastX128: U256::ZERO,
+                tokensOwed0: fees_owed,
+                tokensOwed1: fees_owed,
+            },
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_quote_deposit_counts_the_fees_owed() {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        let vault = vault();
+        let amount = U256::from(100_000_000u64);
+
+        push_vault_state(&asserter, 0);
+        let without_fees = quote_deposit(&provider, &vault, amount, amount)
+            .await
+            .unwrap();
+        assert_eq!(without_fees.shares_raw, U256::from(200_000_000u64));
+
+        // The owed fees are collected before the shares are priced
+        push_vault_state(&asserter, 1_000_000_000);
+        let with_fees = quote_deposit(&provider, &vault, amount, amount)
+            .await
+            .unwrap();
+        let spot_price = get_quote_at_tick(0, PRECISION.to(), true).unwrap();
+        let expected = shares_for_deposit(
+            amount,
+            amount,
+            U256::from(2_000_000_000u64),
+            U256::from(2_000_000_000u64),
+            U256::from(2_000_000_000u64),
+            spot_price,
+        )
+        .unwrap();
+        assert_eq!(with_fees.shares_raw, expected);
+        assert_eq!(with_fees.shares_raw, U256::from(100_000_000u64));
+    }
+}
@@ -0,0 +1,370 @@
+/*
+    RPC failover transport: the requests go to the healthiest of the configured endpoints.
+    An endpoint that fails is moved behind the others for a cooldown, then tried again.
+    Reads are retried with an exponential backoff (on the next healthy endpoint), writes are
+    sent once since a timed out transaction may still have been accepted.
+*/
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    rpc::json_rpc::{RequestPacket, ResponsePacket},
+    transports::{TransportError, TransportFut},
+};
+use color_eyre::eyre::Result;
+use tower::Service;
+use tracing::warn;
+
+use crate::config::{
+    RPC_ENDPOINT_COOLDOWN_SECONDS, RPC_MAX_RETRIES, RPC_RETRY_BASE_DELAY_MS, RPC_RETRY_MAX_DELAY_MS,
+};
+
+/// Methods that change the chain state, never retried
+const NON_IDEMPOTENT_METHODS: [&str; 2] = ["eth_sendRawTransaction", "eth_sendTransaction"];
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryConfig {
+    /// Retries of a read after the first attempt
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Time a failing endpoint stays behind the healthy ones
+    pub cooldown: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: RPC_MAX_RETRIES,
+            base_delay: Duration::from_millis(RPC_RETRY_BASE_DELAY_MS),
+            max_delay: Duration::from_millis(RPC_RETRY_MAX_DELAY_MS),
+            cooldown: Duration::from_secs(RPC_ENDPOINT_COOLDOWN_SECONDS),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Wait before the retry number `attempt` (0 based): base * 2^attempt, capped
+    pub fn backoff_delay(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay)
+    }
+}
+
+#[derive(Debug, Default)]
+struct EndpointHealth {
+    consecutive_failures: u32,
+    last_failure: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct Endpoint<S> {
+    url: String,
+    transport: S,
+    health: Mutex<EndpointHealth>,
+}
+
+impl<S> Endpoint<S> {
+    /// 0 for a healthy endpoint, the number of consecutive failures while in cooldown
+    fn penalty(&self, cooldown: Duration) -> u32 {
+        let health = self.health.lock().unwrap();
+
+        match health.last_failure {
+            Some(last_failure) if last_failure.elapsed() < cooldown => health.consecutive_failures,
+            _ => 0,
+        }
+    }
+
+    fn record(&self, is_success: bool) {
+        let mut health = self.health.lock().unwrap();
+
+        if is_success {
+            *health = EndpointHealth::default();
+        } else {
+            health.consecutive_failures += 1;
+            health.last_failure = Some(Instant::now());
+        }
+    }
+}
+
+/// Transport over a list of endpoints, to build the provider RPC client with
+#[derive(Debug, Clone)]
+pub struct FailoverTransport<S> {
+    endpoints: Arc<Vec<Endpoint<S>>>,
+    retry: RetryConfig,
+}
+
+impl<S> FailoverTransport<S>
+where
+    S: Service<
+            RequestPacket,
+            Response = ResponsePacket,
+            Error = TransportError,
+            Future = TransportFut<'static>,
+        > + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    /// Endpoints as (url, transport), in order of preference
+    pub fn new(endpoints: Vec<(String, S)>, retry: RetryConfig) -> Self {
+        Self {
+            endpoints: Arc::new(
+                endpoints
+                    .into_iter()
+                    .map(|(url, transport)| Endpoint {
+                        url,
+                        transport,
+                        health: Mutex::new(EndpointHealth::default()),
+                    })
+                    .collect(),
+            ),
+            retry,
+        }
+    }
+
+    /// Index of the endpoint to use: the least penalized, the first configured on ties
+    fn best_endpoint(&self) -> usize {
+        (0..self.endpoints.len())
+            .min_by_key(|index| (self.endpoints[*index].penalty(self.retry.cooldown), *index))
+            .unwrap_or_default()
+    }
+
+    async fn request(self, request: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let is_idempotent = request
+            .method_names()
+            .all(|method| !NON_IDEMPOTENT_METHODS.contains(&method));
+        let max_retries = if is_idempotent {
+            self.retry.max_retries
+        } else {
+            0
+        };
+
+        let mut attempt = 0;
+        loop {
+            let endpoint = &self.endpoints[self.best_endpoint()];
+
+            let result = endpoint.transport.clone().call(request.clone()).await;
+            endpoint.record(result.is_ok());
+
+            match result {
+                Err(e) if attempt < max_retries => {
+                    let delay = self.retry.backoff_delay(attempt);
+                    warn!(
+                        "RPC request to {} failed ({}), retrying in {:?}",
+                        endpoint.url, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<S> Service<RequestPacket> for FailoverTransport<S>
+where
+    S: Service<
+            RequestPacket,
+            Response = ResponsePacket,
+            Error = TransportError,
+            Future = TransportFut<'static>,
+        > + Clone
+        + Send
+        + Sync
+        + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().request(request))
+    }
+}
+
+/// Run `operation` until it succeeds, retrying at most `retry.max_retries` times with an
+/// exponential backoff. Returns the last error
+pub async fn with_retries<T, F, Fut>(label: &str, retry: RetryConfig, mut operation: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < retry.max_retries => {
+                let delay = retry.backoff_delay(attempt);
+                warn!(
+                    "{} failed (attempt {}/{}): {:?}, retrying in {:?}",
+                    label,
+                    attempt + 1,
+                    retry.max_retries + 1,
+                    e,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicU32, Ordering},
+        },
+        task::{Context, Poll},
+        time::Duration,
+    };
+
+    use alloy::{
+        rpc::json_rpc::{Id, Request, RequestPacket, ResponsePacket},
+        transports::{TransportError, TransportErrorKind, TransportFut},
+    };
+    use color_eyre::eyre::eyre;
+    use tower::Service;
+
+    use super::{FailoverTransport, RetryConfig, with_retries};
+
+    /// Endpoint failing its first `failures` requests, counting the requests it gets
+    #[derive(Debug, Clone)]
+    struct TestEndpoint {
+        failures: u32,
+        calls: Arc<AtomicU32>,
+    }
+
+    impl TestEndpoint {
+        fn new(failures: u32) -> Self {
+            Self {
+                failures,
+                calls: Arc::new(AtomicU32::new(0)),
+            }
+        }
+
+        fn calls(&self) -> u32 {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Service<RequestPacket> for TestEndpoint {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: RequestPacket) -> Self::Future {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let failures = self.failures;
+
+            Box::pin(async move {
+                if call < failures {
+                    return Err(TransportErrorKind::custom_str("endpoint down"));
+                }
+
+                Ok(serde_json::from_str(r#"{"jsonrpc":"2.0","id":0,"result":"0x1"}"#).unwrap())
+            })
+        }
+    }
+
+    fn retry_config() -> RetryConfig {
+        RetryConfig {
+            max_retries: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(4),
+            cooldown: Duration::from_secs(60),
+        }
+    }
+
+    fn request(method: &'static str) -> RequestPacket {
+        RequestPacket::Single(Request::new(method, Id::Number(0), ()).serialize().unwrap())
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let retry = retry_config();
+
+        assert_eq!(retry.backoff_delay(0), Duration::from_millis(1));
+        assert_eq!(retry.backoff_delay(1), Duration::from_millis(2));
+        assert_eq!(retry.backoff_delay(2), Duration::from_millis(4));
+        assert_eq!(retry.backoff_delay(40), Duration::from_millis(4));
+    }
+
+    #[tokio::test]
+    async fn test_failover_and_read_retries() {
+        let main = TestEndpoint::new(u32::MAX);
+        let fallback = TestEndpoint::new(0);
+        let mut transport = FailoverTransport::new(
+            vec![
+                ("main".to_string(), main.clone()),
+                ("fallback".to_string(), fallback.clone()),
+            ],
+            retry_config(),
+        );
+
+        // The failing read is retried on the fallback endpoint
+        assert!(transport.call(request("eth_blockNumber")).await.is_ok());
+        assert_eq!((main.calls(), fallback.calls()), (1, 1));
+
+        // The main endpoint is in cooldown, the next requests skip it
+        assert!(transport.call(request("eth_call")).await.is_ok());
+        assert_eq!((main.calls(), fallback.calls()), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_writes_are_not_retried() {
+        let main = TestEndpoint::new(1);
+        let fallback = TestEndpoint::new(0);
+        let mut transport = FailoverTransport::new(
+            vec![
+                ("main".to_string(), main.clone()),
+                ("fallback".to_string(), fallback.clone()),
+            ],
+            retry_config(),
+        );
+
+        assert!(
+            transport
+                .call(request("eth_sendRawTransaction"))
+                .await
+                .is_err()
+        );
+        assert_eq!((main.calls(), fallback.calls()), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_with_retries() {
+        let attempts = AtomicU32::new(0);
+
+        let result = with_retries("test", retry_config(), || async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(eyre!("transient")),
+                attempt => Ok(attempt),
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 2);
+
+        let result: color_eyre::eyre::Result<()> =
+            with_retries("test", retry_config(), || async { Err(eyre!("down")) }).await;
+        assert!(result.is_err());
+    }
+}
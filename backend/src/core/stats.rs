@@ -0,0 +1,174 @@
+/*
+    Vault yield analytics computed from the recorded snapshots. Fees are the growth of the
+    collected fees plus the fees owed by the position, so collecting them does not count twice.
+*/
+use crate::{
+    helpers::math::is_tick_in_range,
+    types::{VaultSnapshot, VaultStats},
+};
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0;
+const COMPOUNDING_PERIODS_PER_YEAR: f64 = 365.0;
+
+impl VaultStats {
+    /// Stats over the snapshots (oldest first) of the period from `from` to `to`
+    pub fn from_snapshots(
+        vault_address: &str,
+        from: i64,
+        to: i64,
+        snapshots: &[VaultSnapshot],
+        performance_fee: f64,
+        rebalance_count: u64,
+    ) -> Self {
+        let mut fees_earned0 = 0.0;
+        let mut fees_earned1 = 0.0;
+        let mut fees_earned_in_token1 = 0.0;
+        let mut tvl_seconds = 0.0;
+        let mut in_range_seconds = 0;
+        let mut covered_seconds = 0;
+
+        for window in snapshots.windows(2) {
+            let (previous, current) = (&window[0], &window[1]);
+
+            let elapsed = current.timestamp - previous.timestamp;
+            covered_seconds += elapsed;
+            tvl_seconds += previous.tvl_in_token1 * elapsed as f64;
+
+            if previous.is_active
+                && is_tick_in_range(
+                    previous.current_tick,
+                    previous.lower_tick,
+                    previous.upper_tick,
+                )
+            {
+                in_range_seconds += elapsed;
+            }
+
+            // Fees total can only drop if the vault state was reset, ignore these windows
+            let earned0 = (total_fees0(current) - total_fees0(previous)).max(0.0);
+            let earned1 = (total_fees1(current) - total_fees1(previous)).max(0.0);
+
+            fees_earned0 += earned0;
+            fees_earned1 += earned1;
+            fees_earned_in_token1 += earned0 * current.price1 + earned1;
+        }
+
+        let average_tvl_in_token1 = if covered_seconds > 0 {
+            tvl_seconds / covered_seconds as f64
+        } else {
+            0.0
+        };
+
+        let fee_apr = if covered_seconds > 0 && average_tvl_in_token1 > 0.0 {
+            fees_earned_in_token1 / average_tvl_in_token1 * SECONDS_PER_YEAR
+                / covered_seconds as f64
+                * 100.0
+        } else {
+            0.0
+        };
+
+        let net_apr = fee_apr * (1.0 - performance_fee / 100.0);
+        let net_apy = ((1.0 + net_apr / 100.0 / COMPOUNDING_PERIODS_PER_YEAR)
+            .powf(COMPOUNDING_PERIODS_PER_YEAR)
+            - 1.0)
+            * 100.0;
+
+        let time_in_range = if covered_seconds > 0 {
+            in_range_seconds as f64 / covered_seconds as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        Self {
+            vault_address: vault_address.to_lowercase(),
+            from,
+            to,
+            covered_seconds,
+            snapshots: snapshots.len() as u32,
+            fees_earned0,
+            fees_earned1,
+            fees_earned_in_token1,
+            average_tvl_in_token1,
+            fee_apr,
+            net_apr,
+            net_apy,
+            performance_fee,
+            time_in_range,
+            rebalance_count,
+        }
+    }
+}
+
+fn total_fees0(snapshot: &VaultSnapshot) -> f64 {
+    snapshot.collected_fees0 + snapshot.fees0
+}
+
+fn total_fees1(snapshot: &VaultSnapshot) -> f64 {
+    snapshot.collected_fees1 + snapshot.fees1
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{VaultSnapshot, VaultStats};
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn snapshot(timestamp: i64, fees1: f64, collected_fees1: f64, in_range: bool) -> VaultSnapshot {
+        VaultSnapshot {
+            timestamp,
+            vault_address: "0xabc".to_string(),
+            tvl0: 0.0,
+            tvl1: 1000.0,
+            tvl_in_token0: 500.0,
+            tvl_in_token1: 1000.0,
+            total_supply: 100.0,
+            share_price0: 5.0,
+            share_price1: 10.0,
+            price1: 2.0,
+            current_tick: if in_range { 0 } else { 120 },
+            lower_tick: -60,
+            upper_tick: 60,
+            is_active: true,
+            fees0: 0.0,
+            fees1,
+            collected_fees0: 0.0,
+            collected_fees1,
+        }
+    }
+
+    #[test]
+    fn test_stats_from_snapshots() {
+        // 1 token1 of fees per day on a 1000 TVL, collected on the second day
+        let snapshots = vec![
+            snapshot(0, 0.0, 0.0, true),
+            snapshot(DAY, 1.0, 0.0, true),
+            snapshot(2 * DAY, 0.0, 2.0, false),
+            snapshot(3 * DAY, 0.0, 3.0, true),
+        ];
+
+        let stats = VaultStats::from_snapshots("0xABC", 0, 3 * DAY, &snapshots, 10.0, 2);
+
+        assert_eq!(stats.vault_address, "0xabc");
+        assert_eq!(stats.covered_seconds, 3 * DAY);
+        assert_eq!(stats.snapshots, 4);
+        assert_eq!(stats.fees_earned1, 3.0);
+        assert_eq!(stats.fees_earned_in_token1, 3.0);
+        assert_eq!(stats.average_tvl_in_token1, 1000.0);
+        assert!((stats.fee_apr - 36.5).abs() < 1e-9);
+        assert!((stats.net_apr - 32.85).abs() < 1e-9);
+        assert!(stats.net_apy > stats.net_apr && stats.net_apy < 39.0);
+        assert!((stats.time_in_range - 200.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.rebalance_count, 2);
+    }
+
+    #[test]
+    fn test_stats_without_enough_snapshots() {
+        let stats =
+            VaultStats::from_snapshots("0xabc", 0, DAY, &[snapshot(0, 0.0, 0.0, true)], 10.0, 0);
+
+        assert_eq!(stats.covered_seconds, 0);
+        assert_eq!(stats.fee_apr, 0.0);
+        assert_eq!(stats.net_apy, 0.0);
+        assert_eq!(stats.time_in_range, 0.0);
+    }
+}
@@ -0,0 +1,185 @@
+/*
+    Unsigned transaction builder: the vault deposit/withdraw and the ERC20 approve calls encoded
+    with the contract bindings, for the wallets to sign and send themselves.
+*/
+use std::str::FromStr;
+
+use alloy::{
+    primitives::{Address, Bytes, U256, utils::parse_units},
+    providers::Provider,
+};
+use color_eyre::eyre::{Result, eyre};
+
+use crate::{
+    core::vault::{ERC20, YielderaVault},
+    helpers::vault::deposit_value,
+    types::{EvmProvider, Token, TokenAmount, UnsignedTransaction, VaultDetails},
+};
+
+impl TokenAmount {
+    /// Raw amount with the token decimals. Empty or negative amounts and amounts with more
+    /// decimals than the token are rejected
+    pub fn to_raw(&self, decimals: u8) -> Result<U256> {
+        let amount = self.0.trim();
+
+        if amount.is_empty() || amount.starts_with('-') {
+            return Err(eyre!("Invalid amount {:?}: not a positive number", amount));
+        }
+        if let Some((_, fraction)) = amount.split_once('.')
+            && fraction.len() > decimals as usize
+        {
+            return Err(eyre!(
+                "Invalid amount {}: more than {} decimals",
+                amount,
+                decimals
+            ));
+        }
+
+        Ok(parse_units(amount, decimals)
+            .map_err(|e| eyre!("Invalid amount {}: {}", amount, e))?
+            .into())
+    }
+}
+
+/// `deposit(amount0, amount1, receiver)` sent by `from`, with the WHBAR side as the value
+pub async fn build_deposit_tx(
+    provider: &EvmProvider,
+    vault: &VaultDetails,
+    from: Address,
+    receiver: Address,
+    amount0: U256,
+    amount1: U256,
+) -> Result<UnsignedTransaction> {
+    let value = deposit_value(vault, amount0, amount1);
+
+    let vault_address = Address::from_str(&vault.address)?;
+    let vault_contract = YielderaVault::new(vault_address, provider);
+    let call = vault_contract
+        .deposit(amount0, amount1, receiver)
+        .from(from)
+        .value(value);
+
+    let gas = call.estimate_gas().await;
+    unsigned_transaction(
+        provider,
+        from,
+        vault_address,
+        call.calldata().clone(),
+        value,
+        gas,
+    )
+    .await
+}
+
+/// `withdraw(shares, receiver)` sent by the shares owner `from`
+pub async fn build_withdraw_tx(
+    provider: &EvmProvider,
+    vault: &VaultDetails,
+    from: Address,
+    receiver: Address,
+    shares: U256,
+) -> Result<UnsignedTransaction> {
+    let vault_address = Address::from_str(&vault.address)?;
+    let vault_contract = YielderaVault::new(vault_address, provider);
+    let call = vault_contract.withdraw(shares, receiver).from(from);
+
+    let gas = call.estimate_gas().await;
+    unsigned_transaction(
+        provider,
+        from,
+        vault_address,
+        call.calldata().clone(),
+        U256::ZERO,
+        gas,
+    )
+    .await
+}
+
+/// `approve(vault, amount)` of a vault token, needed before depositing a non native token
+pub async fn build_approve_tx(
+    provider: &EvmProvider,
+    vault: &VaultDetails,
+    from: Address,
+    token: &Token,
+    amount: U256,
+) -> Result<UnsignedTransaction> {
+    let token_address = Address::from_str(&token.address)?;
+
+    let token_contract = ERC20::new(token_address, provider);
+    let call = token_contract
+        .approve(Address::from_str(&vault.address)?, amount)
+        .from(from);
+
+    let gas = call.estimate_gas().await;
+    unsigned_transaction(
+        provider,
+        from,
+        token_address,
+        call.calldata().clone(),
+        U256::ZERO,
+        gas,
+    )
+    .await
+}
+
+/// A failed gas estimate is returned with the transaction: it reverts until the approvals are
+/// sent, so a deposit can be built before them
+async fn unsigned_transaction(
+    provider: &EvmProvider,
+    from: Address,
+    to: Address,
+    data: Bytes,
+    value: U256,
+    gas: Result<u64, alloy::contract::Error>,
+) -> Result<UnsignedTransaction> {
+    let (gas, gas_error) = match gas {
+        Ok(gas) => (Some(gas), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+
+    Ok(UnsignedTransaction {
+        chain_id: provider.get_chain_id().await?,
+        from: from.to_string(),
+        to: to.to_string(),
+        data,
+        value,
+        gas,
+        gas_error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use crate::types::{DepositTxRequest, TokenAmount};
+
+    #[test]
+    fn test_token_amount_to_raw() {
+        let amount = |amount: &str| TokenAmount(amount.to_string());
+
+        // 0.1 is not rounded through a f64
+        assert_eq!(
+            amount("0.1").to_raw(18).unwrap(),
+            U256::from(100_000_000_000_000_000u64)
+        );
+        assert_eq!(amount("100").to_raw(6).unwrap(), U256::from(100_000_000));
+        assert_eq!(amount(" 1.5 ").to_raw(8).unwrap(), U256::from(150_000_000));
+
+        assert!(amount("1.0000001").to_raw(6).is_err());
+        assert!(amount("-1").to_raw(6).is_err());
+        assert!(amount("1e6").to_raw(6).is_err());
+        assert!(amount("").to_raw(6).is_err());
+    }
+
+    #[test]
+    fn test_token_amount_from_string_or_number() {
+        let request: DepositTxRequest = serde_json::from_str(
+            r#"{"from": "0x0000000000000000000000000000000000000001", "amount0": "0.1", "amount1": 0.1}"#,
+        )
+        .unwrap();
+
+        assert_eq!(request.amount0, TokenAmount("0.1".to_string()));
+        assert_eq!(request.amount1, TokenAmount("0.1".to_string()));
+    }
+}
@@ -0,0 +1,187 @@
+/*
+    Rebalance triggers: wait until the next evaluation of a vault. With the `swap` trigger the
+    pool Swap logs are polled and the wait ends as soon as a swap moves the tick close to a
+    range edge (or out of the range), the monitor interval staying the upper bound.
+*/
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use alloy::{primitives::Address, providers::Provider, rpc::types::Filter, sol_types::SolEvent};
+use color_eyre::eyre::Result;
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::{TRIGGER_MIN_GAP_SECONDS, TRIGGER_POLL_INTERVAL_SECONDS},
+    core::vault::UniswapV3Pool,
+    types::{RebalanceTrigger, VaultConfig, WebAppState},
+};
+
+/// Wait until the vault should be evaluated again
+pub async fn wait_for_next_evaluation(vault_config: &VaultConfig, app_state: &WebAppState) {
+    let interval = Duration::from_secs(vault_config.monitor_interval_seconds);
+
+    match vault_config.trigger {
+        RebalanceTrigger::Interval => {
+            info!(
+                "Sleeping for {} seconds for vault {}",
+                vault_config.monitor_interval_seconds, vault_config.address
+            );
+            tokio::time::sleep(interval).await;
+        }
+        RebalanceTrigger::Swap => wait_for_swap_trigger(vault_config, app_state, interval).await,
+    }
+}
+
+/// Poll the pool swaps until one gets close to a range edge or `timeout` is reached
+async fn wait_for_swap_trigger(
+    vault_config: &VaultConfig,
+    app_state: &WebAppState,
+    timeout: Duration,
+) {
+    let vault_address = vault_config.address.as_str();
+    let started_at = Instant::now();
+
+    // The swaps of the min gap below are polled too, from the block the wait starts at
+    let mut from_block = match app_state.evm_provider.get_block_number().await {
+        Ok(block) => Some(block),
+        Err(e) => {
+            warn!(
+                "Failed to read the block number for the swap trigger of vault {}: {:?}",
+                vault_address, e
+            );
+            None
+        }
+    };
+
+    // Several swaps in a row near an edge must not evaluate the vault on each of them
+    tokio::time::sleep(timeout.min(Duration::from_secs(TRIGGER_MIN_GAP_SECONDS))).await;
+
+    while started_at.elapsed() < timeout {
+        match poll_swap_ticks(vault_address, app_state, from_block).await {
+            Ok((ticks, last_block)) => {
+                from_block = Some(last_block + 1);
+
+                let Some((lower_tick, upper_tick)) = app_state
+                    .all_vaults
+                    .get(vault_address)
+                    .map(|vault| (vault.lower_tick, vault.upper_tick))
+                else {
+                    return;
+                };
+
+                if let Some(tick) = ticks.into_iter().find(|tick| {
+                    is_near_range_edge(
+                        *tick,
+                        lower_tick,
+                        upper_tick,
+                        vault_config.trigger_edge_percent,
+                    )
+                }) {
+                    info!(
+                        "Swap moved the tick of vault {} to {} (range {}..{}), evaluating now",
+                        vault_address, tick, lower_tick, upper_tick
+                    );
+                    return;
+                }
+            }
+            Err(e) => warn!(
+                "Failed to poll the swaps of vault {}: {:?}",
+                vault_address, e
+            ),
+        }
+
+        let remaining = timeout.saturating_sub(started_at.elapsed());
+        tokio::time::sleep(remaining.min(Duration::from_secs(TRIGGER_POLL_INTERVAL_SECONDS))).await;
+    }
+
+    debug!(
+        "No swap trigger for vault {}, evaluating after the monitor interval",
+        vault_address
+    );
+}
+
+/// Ticks of the pool swaps from `from_block` (the latest block on the first poll) with the last
+/// polled block
+async fn poll_swap_ticks(
+    vault_address: &str,
+    app_state: &WebAppState,
+    from_block: Option<u64>,
+) -> Result<(Vec<i32>, u64)> {
+    let provider = &app_state.evm_provider;
+
+    let Some(pool_address) = app_state
+        .all_vaults
+        .get(vault_address)
+        .map(|vault| vault.pool.address.clone())
+    else {
+        return Err(color_eyre::eyre::eyre!(
+            "Vault details not found for vault address: {}",
+            vault_address
+        ));
+    };
+
+    let latest_block = provider.get_block_number().await?;
+    let from_block = from_block.unwrap_or(latest_block);
+
+    if from_block > latest_block {
+        return Ok((Vec::new(), latest_block));
+    }
+
+    let filter = Filter::new()
+        .address(Address::from_str(&pool_address)?)
+        .event_signature(UniswapV3Pool::Swap::SIGNATURE_HASH)
+        .from_block(from_block)
+        .to_block(latest_block);
+
+    let ticks = provider
+        .get_logs(&filter)
+        .await?
+        .iter()
+        .map(|log| {
+            Ok(log
+                .log_decode::<UniswapV3Pool::Swap>()?
+                .inner
+                .data
+                .tick
+                .as_i32())
+        })
+        .collect::<Result<Vec<i32>>>()?;
+
+    Ok((ticks, latest_block))
+}
+
+/// True when `tick` is out of the range or within `edge_percent` of the range width from one
+/// of its edges. An empty range (inactive vault) never triggers
+pub fn is_near_range_edge(tick: i32, lower_tick: i32, upper_tick: i32, edge_percent: f64) -> bool {
+    if upper_tick <= lower_tick {
+        return false;
+    }
+
+    let margin = (upper_tick - lower_tick) as f64 * edge_percent / 100.0;
+
+    (tick as f64) < lower_tick as f64 + margin || (tick as f64) >= upper_tick as f64 - margin
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_near_range_edge;
+
+    #[test]
+    fn test_is_near_range_edge() {
+        // Range of 1000 ticks, 10% margin: 100 ticks from each edge
+        assert!(!is_near_range_edge(500, 0, 1_000, 10.0));
+        assert!(!is_near_range_edge(100, 0, 1_000, 10.0));
+        assert!(is_near_range_edge(99, 0, 1_000, 10.0));
+        assert!(is_near_range_edge(900, 0, 1_000, 10.0));
+
+        // Out of range
+        assert!(is_near_range_edge(-5, 0, 1_000, 10.0));
+        assert!(is_near_range_edge(1_000, 0, 1_000, 0.0));
+        assert!(!is_near_range_edge(999, 0, 1_000, 0.0));
+
+        // No position
+        assert!(!is_near_range_edge(0, 0, 0, 10.0));
+    }
+}
@@ -0,0 +1,232 @@
+/*
+    Transaction manager: every transaction of the vault tasks is submitted here. Each signer has
+    one queue worker sending its transactions one at a time, so the vault loops sharing a signer
+    never race on a nonce. The worker tracks the next nonce, polls the receipt and, when the
+    transaction is not mined in time, replaces it (same nonce) with bumped fees. The receipt (or
+    the error) is sent back to the submitter.
+*/
+use std::time::{Duration, Instant};
+
+use alloy::{
+    primitives::{Address, TxHash},
+    providers::{Provider, WalletProvider},
+    rpc::types::{TransactionReceipt, TransactionRequest},
+};
+use dashmap::DashMap;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{info, warn};
+
+use crate::{
+    config::{
+        TX_GAS_BUMP_PERCENT, TX_MAX_REPLACEMENTS, TX_QUEUE_SIZE, TX_RECEIPT_POLL_INTERVAL_SECONDS,
+        TX_RECEIPT_TIMEOUT_SECONDS,
+    },
+    core::preflight::TransactionError,
+    types::EvmProvider,
+};
+
+type TxReply = oneshot::Sender<Result<TransactionReceipt, TransactionError>>;
+
+struct TxJob {
+    request: TransactionRequest,
+    reply: TxReply,
+}
+
+pub struct TxManager {
+    provider: EvmProvider,
+    /// Queue of each signer, its worker is spawned on the first submission
+    queues: DashMap<Address, mpsc::Sender<TxJob>>,
+}
+
+impl TxManager {
+    pub fn new(provider: EvmProvider) -> Self {
+        Self {
+            provider,
+            queues: DashMap::new(),
+        }
+    }
+
+    /// Queue a transaction (sent by the default signer when `from` is not set) and wait for its
+    /// receipt. The receipt is returned whatever its status
+    pub async fn submit(
+        &self,
+        mut request: TransactionRequest,
+    ) -> Result<TransactionReceipt, TransactionError> {
+        let signer = *request
+            .from
+            .get_or_insert_with(|| self.provider.default_signer_address());
+
+        let (reply, receiver) = oneshot::channel();
+        self.queue(signer)
+            .send(TxJob { request, reply })
+            .await
+            .map_err(|_| {
+                TransactionError::Rpc(format!("Transaction queue of {} closed", signer))
+            })?;
+
+        receiver.await.map_err(|_| {
+            TransactionError::Rpc(format!("Transaction queue of {} dropped the reply", signer))
+        })?
+    }
+
+    fn queue(&self, signer: Address) -> mpsc::Sender<TxJob> {
+        self.queues
+            .entry(signer)
+            .or_insert_with(|| {
+                let (sender, jobs) = mpsc::channel(TX_QUEUE_SIZE);
+                tokio::spawn(run_signer_queue(self.provider.clone(), signer, jobs));
+                sender
+            })
+            .clone()
+    }
+}
+
+/// Send the transactions of a signer in order
+async fn run_signer_queue(provider: EvmProvider, signer: Address, mut jobs: mpsc::Receiver<TxJob>) {
+    // None until fetched from the chain, and again after a failure left the nonce unknown
+    let mut next_nonce = None;
+
+    while let Some(job) = jobs.recv().await {
+        let result = send_transaction(&provider, signer, &mut next_nonce, job.request).await;
+
+        if let Err(e) = &result {
+            warn!("Transaction of {} failed: {}", signer, e);
+        }
+
+        // The submitter may have given up waiting
+        let _ = job.reply.send(result);
+    }
+}
+
+/// Send a transaction with the next nonce of the signer and wait for it to be mined, replacing it
+/// with bumped fees each time the receipt times out
+async fn send_transaction(
+    provider: &EvmProvider,
+    signer: Address,
+    next_nonce: &mut Option<u64>,
+    mut request: TransactionRequest,
+) -> Result<TransactionReceipt, TransactionError> {
+    let rpc_error = |e: alloy::transports::TransportError| TransactionError::Rpc(e.to_string());
+
+    let nonce = match *next_nonce {
+        Some(nonce) => nonce,
+        None => provider
+            .get_transaction_count(signer)
+            .pending()
+            .await
+            .map_err(rpc_error)?,
+    };
+    let fees = provider.estimate_eip1559_fees().await.map_err(rpc_error)?;
+
+    request.nonce = Some(nonce);
+    request.max_fee_per_gas = Some(fees.max_fee_per_gas);
+    request.max_priority_fee_per_gas = Some(fees.max_priority_fee_per_gas);
+
+    let tx_hash = match provider.send_transaction(request.clone()).await {
+        Ok(pending) => *pending.tx_hash(),
+        Err(e) => {
+            *next_nonce = None;
+            return Err(rpc_error(e));
+        }
+    };
+    info!(
+        "Sent transaction {} of {} with nonce {}",
+        tx_hash, signer, nonce
+    );
+    *next_nonce = Some(nonce + 1);
+
+    // Any of the sent transactions can be the one mined
+    let mut tx_hashes = vec![tx_hash];
+
+    for replacement in 1..=TX_MAX_REPLACEMENTS + 1 {
+        if let Some(receipt) = wait_for_receipt(provider, &tx_hashes).await {
+            return Ok(receipt);
+        }
+
+        if replacement > TX_MAX_REPLACEMENTS {
+            break;
+        }
+
+        let fees = provider.estimate_eip1559_fees().await.ok();
+        request.max_fee_per_gas = request
+            .max_fee_per_gas
+            .map(|fee| bump_fee(fee, fees.map(|fees| fees.max_fee_per_gas)));
+        request.max_priority_fee_per_gas = request
+            .max_priority_fee_per_gas
+            .map(|fee| bump_fee(fee, fees.map(|fees| fees.max_priority_fee_per_gas)));
+
+        match provider.send_transaction(request.clone()).await {
+            Ok(pending) => {
+                warn!(
+                    "Transaction {} of {} with nonce {} not mined after {} seconds, replaced by {}",
+                    tx_hashes[tx_hashes.len() - 1],
+                    signer,
+                    nonce,
+                    TX_RECEIPT_TIMEOUT_SECONDS,
+                    pending.tx_hash()
+                );
+                tx_hashes.push(*pending.tx_hash());
+            }
+            // Usually one of the sent transactions got mined meanwhile
+            Err(e) => warn!(
+                "Failed to replace the transaction of {} with nonce {}: {}",
+                signer, nonce, e
+            ),
+        }
+    }
+
+    // The nonce may or may not be used in the end, get it from the chain again
+    *next_nonce = None;
+
+    Err(TransactionError::NotMined {
+        nonce,
+        attempts: tx_hashes.len(),
+    })
+}
+
+/// Poll the receipts of the sent transactions until one is found or the receipt timeout
+async fn wait_for_receipt(
+    provider: &EvmProvider,
+    tx_hashes: &[TxHash],
+) -> Option<TransactionReceipt> {
+    let started_at = Instant::now();
+
+    loop {
+        for tx_hash in tx_hashes {
+            match provider.get_transaction_receipt(*tx_hash).await {
+                Ok(Some(receipt)) => return Some(receipt),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to get the receipt of {}: {}", tx_hash, e),
+            }
+        }
+
+        if started_at.elapsed() >= Duration::from_secs(TX_RECEIPT_TIMEOUT_SECONDS) {
+            return None;
+        }
+
+        tokio::time::sleep(Duration::from_secs(TX_RECEIPT_POLL_INTERVAL_SECONDS)).await;
+    }
+}
+
+/// Fee of a replacement transaction: the previous fee bumped by `TX_GAS_BUMP_PERCENT` (nodes
+/// reject a replacement under 10% more), or the current estimate when higher
+pub fn bump_fee(fee: u128, estimate: Option<u128>) -> u128 {
+    let bumped = fee.saturating_mul(100 + TX_GAS_BUMP_PERCENT) / 100;
+
+    bumped.max(fee + 1).max(estimate.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bump_fee;
+
+    #[test]
+    fn test_bump_fee() {
+        assert_eq!(bump_fee(100, None), 120);
+        assert_eq!(bump_fee(100, Some(150)), 150);
+        assert_eq!(bump_fee(100, Some(110)), 120);
+
+        // Always strictly higher
+        assert_eq!(bump_fee(0, None), 1);
+    }
+}
@@ -11,12 +11,14 @@ use tracing::{info, trace};
 
 use crate::{
     config::{CONFIG, FEE_FACTOR},
+    core::multicall::batch_calls,
     helpers,
-    types::{Pool, Position, Token, VaultDetails, VaultTVL, VaultTokenBalances},
+    types::{Pool, Position, Token, VaultDetails, VaultFees, VaultTVL, VaultTokenBalances},
 };
 
 sol!(
     #[sol(rpc)]
+    #[derive(Debug)]
     YielderaVault,
     "./src/abi/YielderaVault.json",
 );
@@ -53,6 +55,23 @@ sol! {
 
         function slot0() external view returns (uint160 sqrtPriceX96, int24 tick, uint16 observationIndex, uint16 observationCardinality, uint16 observationCardinalityNext, uint8 feeProtocol, bool unlocked);
 
+        function observe(uint32[] calldata secondsAgos) external view returns (int56[] memory tickCumulatives, uint160[] memory secondsPerLiquidityCumulativeX128s);
+
+        event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick);
+
+        function tickBitmap(int16 wordPosition) external view returns (uint256);
+
+        function ticks(int24 tick) external view returns (
+            uint128 liquidityGross,
+            int128 liquidityNet,
+            uint256 feeGrowthOutside0X128,
+            uint256 feeGrowthOutside1X128,
+            int56 tickCumulativeOutside,
+            uint160 secondsPerLiquidityOutsideX128,
+            uint32 secondsOutside,
+            bool initialized
+        );
+
 
         function positions(bytes32 key) external view returns (
             uint128 liquidity,
@@ -68,71 +87,120 @@ pub async fn get_vault_details<P>(provider: &P, vault_address: &str) -> Result<V
 where
     P: Provider + WalletProvider,
 {
-    let vault = YielderaVault::new(Address::from_str(vault_address)?, provider);
+    let vault_evm_address = Address::from_str(vault_address)?;
+    let vault = YielderaVault::new(vault_evm_address, provider);
 
     let hbar_evm_address = &CONFIG.toml_config.hbar_evm_address;
+    let multicall_address = CONFIG.toml_config.multicall_address;
 
-    let name = vault.name().call().await?;
-    let symbol = vault.symbol().call().await?;
-    let decimals = vault.decimals().call().await?;
-    let total_supply = vault.totalSupply().call().await?;
-    let pool_address = vault.pool().call().await?;
-    let token0_address = vault.token0().call().await?;
-    let token1_address = vault.token1().call().await?;
-    let mut fee: f64 = vault.fee().call().await?.into();
+    let (
+        name,
+        symbol,
+        decimals,
+        total_supply,
+        pool_address,
+        token0_address,
+        token1_address,
+        fee,
+        tick_spacing,
+        lower_tick_org,
+        upper_tick_org,
+        is_active,
+        is_vault_tokens_associated,
+        collected0,
+        collected1,
+        performance_fee,
+    ) = batch_calls!(
+        provider,
+        multicall_address,
+        vault.name(),
+        vault.symbol(),
+        vault.decimals(),
+        vault.totalSupply(),
+        vault.pool(),
+        vault.token0(),
+        vault.token1(),
+        vault.fee(),
+        vault.tickSpacing(),
+        vault.lowerTick(),
+        vault.upperTick(),
+        vault.isActive(),
+        vault.isVaultTokensAssociated(),
+        vault.vaultFees0(),
+        vault.vaultFees1(),
+        vault.performanceFee(),
+    )
+    .await?;
+
+    let mut fee: f64 = fee.into();
     fee = fee / FEE_FACTOR;
-    let tick_spacing = vault.tickSpacing().call().await?.as_i32();
-    let lower_tick_org = vault.lowerTick().call().await?;
+    let tick_spacing = tick_spacing.as_i32();
     let lower_tick = lower_tick_org.as_i32();
-    let upper_tick_org = vault.upperTick().call().await?;
     let upper_tick = upper_tick_org.as_i32();
-    let is_active = vault.isActive().call().await?;
-    let is_vault_tokens_associated = vault.isVaultTokensAssociated().call().await?;
 
     let pool_contract = UniswapV3Pool::new(pool_address, provider);
+    let token0 = ERC20::new(token0_address, provider);
+    let token1 = ERC20::new(token1_address, provider);
+
+    // Read in the same batch for an inactive vault, its position is then ignored
+    let position_key =
+        keccak256((vault_evm_address, lower_tick_org, upper_tick_org).abi_encode_packed());
+
+    let (
+        slot0,
+        token0_name,
+        token0_symbol,
+        token0_decimals,
+        balance0,
+        token1_name,
+        token1_symbol,
+        token1_decimals,
+        balance1,
+        position_details,
+    ) = batch_calls!(
+        provider,
+        multicall_address,
+        pool_contract.slot0(),
+        token0.name(),
+        token0.symbol(),
+        token0.decimals(),
+        token0.balanceOf(vault_evm_address),
+        token1.name(),
+        token1.symbol(),
+        token1.decimals(),
+        token1.balanceOf(vault_evm_address),
+        pool_contract.positions(position_key),
+    )
+    .await?;
 
     // Ftehcing sqrt and tick of the pool
-    let slot0 = pool_contract.slot0().call().await?;
     let sqrt_price_x96 = U256::from_str(slot0.sqrtPriceX96.to_string().as_str())?;
     let current_tick = slot0.tick.as_i32();
 
     let total_supply: f64 = format_units(total_supply, decimals)?.parse()?;
 
-    // Ftehc token0 and token1 details
-    let token0 = ERC20::new(token0_address, provider);
-    let token1 = ERC20::new(token1_address, provider);
-
     let token0_address = token0_address.to_string();
-    let token0_name = token0.name().call().await?;
-    let token0_symbol = token0.symbol().call().await?;
-    let token0_decimals = token0.decimals().call().await?;
     let is_token0_native_wrapper = token0_address.to_lowercase() == hbar_evm_address.to_lowercase();
 
     let token1_address = token1_address.to_string();
-    let token1_name = token1.name().call().await?;
-    let token1_symbol = token1.symbol().call().await?;
-    let token1_decimals = token1.decimals().call().await?;
     let is_token1_native_wrapper = token1_address.to_lowercase() == hbar_evm_address.to_lowercase();
 
     // Calculate the pool price1 and price0 by using the current tick
     let price1 = helpers::math::tick_to_price(current_tick, token0_decimals, token1_decimals)?;
     let price0 = 1.0 / price1;
 
+    let fees = vault_fees(
+        collected0,
+        collected1,
+        performance_fee.into(),
+        token0_decimals,
+        token1_decimals,
+    )?;
+
     let position: Position;
 
     // Fetch the vault current position
     if is_active {
-        let value = (
-            Address::from_str(vault_address)?,
-            lower_tick_org,
-            upper_tick_org,
-        );
-        let res_value = value.abi_encode_packed();
-
-        let position_key = keccak256(res_value);
-
-        let position_details = pool_contract.positions(position_key).call().await?;
-
         let liquidity = position_details.liquidity;
         let tokens_owed_0 = position_details.tokensOwed0;
         let tokens_owed_1 = position_details.tokensOwed1;
@@ -168,16 +236,6 @@ where
         position = Position::default();
     }
 
-    let balance0 = token0
-        .balanceOf(Address::from_str(vault_address)?)
-        .call()
-        .await?;
-
-    let balance1 = token1
-        .balanceOf(Address::from_str(vault_address)?)
-        .call()
-        .await?;
-
     let balance0: f64 = format_units(balance0, token0_decimals)?.parse()?;
     let balance1: f64 = format_units(balance1, token1_decimals)?.parse()?;
 
@@ -224,6 +282,22 @@ where
         is_vault_tokens_associated,
         position,
         tvl,
+        fees,
+    })
+}
+
+/// Fees collected by the vault since its creation and its performance fee
+fn vault_fees(
+    collected0: U256,
+    collected1: U256,
+    performance_fee: f64,
+    token0_decimals: u8,
+    token1_decimals: u8,
+) -> Result<VaultFees> {
+    Ok(VaultFees {
+        collected0: format_units(collected0, token0_decimals)?.parse()?,
+        collected1: format_units(collected1, token1_decimals)?.parse()?,
+        performance_fee,
     })
 }
 
@@ -291,15 +365,42 @@ where
     let vault_address = Address::from_str(vault.address.as_str())?;
 
     let vault_contract = YielderaVault::new(vault_address, provider);
-
-    let current_tick = vault_contract.currentTick().call().await?.as_i32();
-    let lower_tick = vault_contract.lowerTick().call().await?;
-    let upper_tick = vault_contract.upperTick().call().await?;
-
     let pool_contract = UniswapV3Pool::new(vault.pool.address.parse()?, provider);
+    let token0 = ERC20::new(vault.pool.token0.address.parse()?, provider);
+    let token1 = ERC20::new(vault.pool.token1.address.parse()?, provider);
 
-    let slot0 = pool_contract.slot0().call().await?;
+    let multicall_address = CONFIG.toml_config.multicall_address;
 
+    let (
+        current_tick,
+        lower_tick,
+        upper_tick,
+        is_active,
+        total_supply,
+        collected0,
+        collected1,
+        performance_fee,
+        slot0,
+        balance0,
+        balance1,
+    ) = batch_calls!(
+        provider,
+        multicall_address,
+        vault_contract.currentTick(),
+        vault_contract.lowerTick(),
+        vault_contract.upperTick(),
+        vault_contract.isActive(),
+        vault_contract.totalSupply(),
+        vault_contract.vaultFees0(),
+        vault_contract.vaultFees1(),
+        vault_contract.performanceFee(),
+        pool_contract.slot0(),
+        token0.balanceOf(vault_address),
+        token1.balanceOf(vault_address),
+    )
+    .await?;
+
+    let current_tick = current_tick.as_i32();
     let sqrt_price_x96 = U256::from_str(slot0.sqrtPriceX96.to_string().as_str())?;
 
     let token0_decimals = vault.pool.token0.decimals;
@@ -313,10 +414,9 @@ where
     )?;
     let price0 = 1.0 / price1;
 
-    // Fetch position details
-
-    let is_active = vault_contract.isActive().call().await?;
+    let total_supply: f64 = format_units(total_supply, vault.decimals)?.parse()?;
 
+    // Fetch position details, its key depends on the ticks read above
     let value = (vault_address, lower_tick, upper_tick);
     let res_value = value.abi_encode_packed();
 
@@ -360,13 +460,6 @@ where
     }
 
     // Calculate the TVL
-    let token0 = ERC20::new(vault.pool.token0.address.parse()?, provider);
-    let token1 = ERC20::new(vault.pool.token1.address.parse()?, provider);
-
-    let balance0 = token0.balanceOf(vault_address).call().await?;
-
-    let balance1 = token1.balanceOf(vault_address).call().await?;
-
     let balance0: f64 = format_units(balance0, token0_decimals)?.parse()?;
     let balance1: f64 = format_units(balance1, token1_decimals)?.parse()?;
 
@@ -378,15 +471,25 @@ where
         tvl1: vault_tvl1,
     };
 
+    let fees = vault_fees(
+        collected0,
+        collected1,
+        performance_fee.into(),
+        token0_decimals,
+        token1_decimals,
+    )?;
+
     vault.pool.current_tick = current_tick;
     vault.pool.sqrt_price_x96 = sqrt_price_x96;
     vault.lower_tick = lower_tick.as_i32();
     vault.upper_tick = upper_tick.as_i32();
     vault.is_active = is_active;
+    vault.total_supply = total_supply;
     vault.pool.price1 = price1;
     vault.pool.price0 = price0;
     vault.position = position;
     vault.tvl = tvl;
+    vault.fees = fees;
 
     Ok(())
 }
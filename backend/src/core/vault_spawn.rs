@@ -1,413 +1,925 @@
 use std::str::FromStr;
 
 use crate::{
-    config::{CONFIG, MONITOR_VAULT_INTERVAL_SECONDS},
-    core::{self, csv_logger::RebalanceLogEntry, vault::YielderaVault},
-    helpers::{self},
-    strategies,
-    types::{PrepareSwapArgs, VaultDetails, VaultTokenBalances, WebAppState},
+    config::{CONFIG, UNSENT_REBALANCE_RETENTION_SECONDS},
+    core::{self, control::VaultCommand, vault::YielderaVault},
+    error::YielderaError,
+    helpers::{
+        self,
+        math::{
+            is_tick_in_range,
+            swap_to_ratio::{SwapQuote, SwapToRatio},
+            uniswap_v3::tick_math::{MAX_TICK, MIN_TICK},
+        },
+    },
+    types::{
+        MarketContext, RebalancePlan, RebalanceRecord, RebalanceStatus, RebalanceSwapPlan,
+        VaultConfig, VaultDetails, VaultSnapshot, VaultTokenBalances, WebAppState,
+    },
 };
-use alloy::primitives::{
-    Address, U256,
-    aliases::I24,
-    utils::{format_units, parse_units},
+use alloy::{
+    primitives::{
+        Address, U256,
+        aliases::I24,
+        utils::{format_units, parse_units},
+    },
+    rpc::types::TransactionReceipt,
 };
-use color_eyre::eyre::{Context, Result};
+use color_eyre::eyre::Result;
+use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 
-pub async fn start_vault_liq_management(vault_address: &str, app_state: WebAppState) -> Result<()> {
+/// Strategy name of the admin forced rebalances
+pub const MANUAL_STRATEGY: &str = "manual";
+
+pub async fn start_vault_liq_management(
+    vault_config: &VaultConfig,
+    app_state: WebAppState,
+    mut commands: mpsc::Receiver<VaultCommand>,
+) -> Result<()> {
+    let vault_address = vault_config.address.as_str();
+
     info!(
         "Vault liquidity management loop started for vault address: {:?}",
         vault_address
     );
 
-    // for each MONITOR_VAULT_INTERVAL_SECONDS, check if we need to rebalance the vault
+    // on each trigger of the vault (monitor interval or swap), check if we need to rebalance the vault
     loop {
-        // Implement the logic to rebalance the vault
-        match start_rebalance_strategy(vault_address, &app_state).await {
-            Ok(_) => {
-                info!(
-                    "Start Rebalance strategy for vault {} completed successfully",
-                    vault_address
-                );
+        if core::control::is_paused(&app_state, vault_address) {
+            info!("Vault {} is paused, skipping its evaluation", vault_address);
+        } else {
+            evaluate_vault(vault_config, &app_state).await?;
+        }
+
+        // Wait for the next trigger, the admin commands run as they arrive
+        let next_evaluation = core::trigger::wait_for_next_evaluation(vault_config, &app_state);
+        tokio::pin!(next_evaluation);
+
+        loop {
+            tokio::select! {
+                _ = &mut next_evaluation => break,
+                Some(command) = commands.recv() => {
+                    if run_command(vault_config, &app_state, command).await {
+                        break;
+                    }
+                }
             }
-            Err(e) => {
-                error!(
-                    "Start Rebalance strategy for vault {} failed with error: {:?}",
-                    vault_address, e
-                );
-
-                // Init the email
-                let mailer = core::email::init_mailer()
-                    .await
-                    .context("Failed to initialize mailer")?;
-
-                // send an alert email
-                core::email::send_email_notification(
-                    "Yieldera Vault Rebalance Alert",
-                    format!(
-                        "Vault {} Rebalance failed to rebalance with error: \n{:?}",
-                        vault_address, e
-                    ),
-                    &mailer,
-                )
-                .await?;
+        }
+    }
+}
+
+/// Run the rebalance strategy of the vault, alerting the vault recipients on failure
+async fn evaluate_vault(vault_config: &VaultConfig, app_state: &WebAppState) -> Result<()> {
+    let vault_address = vault_config.address.as_str();
+
+    // Implement the logic to rebalance the vault
+    match start_rebalance_strategy(vault_config, app_state).await {
+        Ok(_) => {
+            info!(
+                "Start Rebalance strategy for vault {} completed successfully",
+                vault_address
+            );
+        }
+        Err(e) => {
+            let e = YielderaError::from(e);
+            let severity = e.severity();
+
+            error!(
+                "Start Rebalance strategy for vault {} failed with {} error: {:?}",
+                vault_address, severity, e
+            );
+
+            if severity < vault_config.alert_min_severity {
+                return Ok(());
             }
-        };
 
-        info!(
-            "Sleeping for {} seconds for vault {}",
-            MONITOR_VAULT_INTERVAL_SECONDS, vault_address
-        );
+            // Init the email
+            let mailer = core::email::init_mailer().await?;
 
-        tokio::time::sleep(std::time::Duration::from_secs(
-            MONITOR_VAULT_INTERVAL_SECONDS,
-        ))
-        .await;
+            // send an alert email
+            core::email::send_email_notification(
+                &format!("Yieldera Vault Rebalance Alert ({})", severity),
+                format!(
+                    "Vault {} Rebalance failed to rebalance with error: \n{:?}",
+                    vault_address, e
+                ),
+                &vault_config.alert_recipients,
+                &mailer,
+            )
+            .await?;
+        }
+    };
+
+    Ok(())
+}
+
+/// Run an admin command on the vault loop, true when the vault must be evaluated now
+async fn run_command(
+    vault_config: &VaultConfig,
+    app_state: &WebAppState,
+    command: VaultCommand,
+) -> bool {
+    let vault_address = vault_config.address.as_str();
+
+    match command {
+        VaultCommand::EvaluateNow => {
+            info!("Admin evaluation requested for vault {}", vault_address);
+            true
+        }
+        VaultCommand::ForceRebalance {
+            lower_tick,
+            upper_tick,
+            reply,
+        } => {
+            info!(
+                "Admin rebalance of vault {} to [{}, {}]",
+                vault_address, lower_tick, upper_tick
+            );
+            let result = force_rebalance(vault_config, app_state, lower_tick, upper_tick).await;
+            let _ = reply.send(result);
+            false
+        }
+        VaultCommand::BurnAllLiquidity { reply } => {
+            info!("Admin burn of all the liquidity of vault {}", vault_address);
+            let result = burn_all_liquidity(vault_config, app_state).await;
+            let _ = reply.send(result);
+            false
+        }
     }
 }
 
-async fn start_rebalance_strategy(vault_address: &str, app_state: &WebAppState) -> Result<()> {
-    // 1. Check if the vault already has a position or not by checkinfg the isActive flag
-    let vault_details = app_state.all_vaults.get_mut(vault_address);
+/// Rebalance the vault to an explicit tick range, recorded in the history like the strategy
+/// rebalances. Returns the transaction hash
+async fn force_rebalance(
+    vault_config: &VaultConfig,
+    app_state: &WebAppState,
+    lower_tick: i32,
+    upper_tick: i32,
+) -> Result<String> {
+    let vault_address = vault_config.address.as_str();
 
-    if vault_details.is_none() {
+    let mut vault_details = vault_details_of(app_state, vault_address)?;
+
+    core::vault::update_vault_live(&app_state.evm_provider, &mut vault_details).await?;
+    store_vault_details(app_state, vault_address, &vault_details);
+
+    let plan = compute_forced_rebalance_plan(
+        &vault_details,
+        vault_config,
+        app_state,
+        lower_tick,
+        upper_tick,
+    )
+    .await?;
+
+    if let Some(reason) =
+        core::oracle::check_twap(&app_state.evm_provider, &vault_details, vault_config).await?
+    {
+        return Err(YielderaError::Blocked(reason).into());
+    }
+
+    execute_rebalance(vault_config, app_state, &mut vault_details, &plan).await
+}
+
+/// Remove all the vault liquidity, the tokens stay in the vault. Returns the transaction hash
+async fn burn_all_liquidity(vault_config: &VaultConfig, app_state: &WebAppState) -> Result<String> {
+    let vault_address = vault_config.address.as_str();
+
+    let mut vault_details = vault_details_of(app_state, vault_address)?;
+
+    if !vault_details.is_active {
         return Err(color_eyre::eyre::eyre!(
-            "Vault details not found for vault address: {}",
+            "Vault {} has no liquidity to burn",
             vault_address
         ));
     }
 
-    let mut vault_details = vault_details.unwrap();
+    let vault_contract =
+        YielderaVault::new(Address::from_str(vault_address)?, &app_state.evm_provider);
+    let burn_request = core::preflight::prepare(vault_contract.burnAllLiquidity()).await?;
+    let burn_receipt = app_state.tx_manager.submit(burn_request).await?;
+
+    core::preflight::check_receipt(&burn_receipt).map_err(|e| {
+        color_eyre::Report::from(e).wrap_err(format!(
+            "Burn all liquidity transaction failed for vault {}",
+            vault_address
+        ))
+    })?;
+
+    core::vault::update_vault_live(&app_state.evm_provider, &mut vault_details).await?;
+    store_vault_details(app_state, vault_address, &vault_details);
 
-    let has_a_position = vault_details.is_active;
+    Ok(burn_receipt.transaction_hash.to_string())
+}
 
-    let vault_token_balances =
-        core::vault::get_vault_tokens_balances(&app_state.evm_provider, &vault_details).await?;
+/// Copy of the vault details. The map entry is not held across the awaits of an evaluation (RPC
+/// calls, strategy, transaction receipt), the API reads it meanwhile
+fn vault_details_of(app_state: &WebAppState, vault_address: &str) -> Result<VaultDetails> {
+    app_state
+        .all_vaults
+        .get(vault_address)
+        .map(|vault_details| vault_details.clone())
+        .ok_or_else(|| {
+            color_eyre::eyre::eyre!(
+                "Vault details not found for vault address: {}",
+                vault_address
+            )
+        })
+}
+
+/// Write back the refreshed details of a vault
+fn store_vault_details(app_state: &WebAppState, vault_address: &str, vault_details: &VaultDetails) {
+    app_state
+        .all_vaults
+        .insert(vault_address.to_string(), vault_details.clone());
+}
+
+async fn start_rebalance_strategy(
+    vault_config: &VaultConfig,
+    app_state: &WebAppState,
+) -> Result<()> {
+    let vault_address = vault_config.address.as_str();
+
+    let mut vault_details = vault_details_of(app_state, vault_address)?;
 
     // Update the vault live data from the blockchain (tick, prices)
     core::vault::update_vault_live(&app_state.evm_provider, &mut vault_details).await?;
+    store_vault_details(app_state, vault_address, &vault_details);
+
+    // Record the vault state, a storage failure must not block the rebalance
+    let snapshot = VaultSnapshot::from_vault(&vault_details, chrono::Utc::now().timestamp());
+    if let Err(e) = app_state.db.insert_snapshot(&snapshot) {
+        error!(
+            "Failed to store the snapshot of vault {}: {:?}",
+            vault_address, e
+        );
+    }
 
-    if has_a_position {
-        debug!(
-            "Vault {} has already a position. Checking if need to rebalance...",
+    let position_before = vault_details.position.clone();
+
+    // A tripped circuit breaker waits for an admin reset, the strategy (an AI call for some) is
+    // not asked until then
+    if app_state.db.circuit_breaker(vault_address)?.is_tripped() {
+        warn!(
+            "Circuit breaker tripped for vault {}, waiting for an admin reset",
             vault_address
         );
+        return Ok(());
+    }
 
-        // Check if teh vault is out of range by checking the current tick
-        let current_tick = vault_details.pool.current_tick;
-        let lower_tick = vault_details.lower_tick;
-        let upper_tick = vault_details.upper_tick;
+    let mut plan = compute_rebalance_plan(&vault_details, vault_config, app_state).await?;
 
-        let is_out_of_range = current_tick < lower_tick || current_tick > upper_tick;
+    if !plan.rebalance_required {
+        warn!(
+            "Skipping rebalance for vault {}: {}",
+            vault_address,
+            plan.skip_reason.as_deref().unwrap_or_default()
+        );
+        record_unsent_rebalance(
+            app_state,
+            &RebalanceRecord::from_plan(&plan, RebalanceStatus::Skipped, &position_before),
+        );
+        return Ok(());
+    }
 
-        // If the vault is not out of range, we skip the rebalance if teh fees are very low
-        if !is_out_of_range {
-            let fees0 = vault_details.position.fees0;
-            let fees1 = vault_details.position.fees1;
-
-            if fees0 < 0.01 || fees1 < 0.01 {
-                warn!(
-                    "Vault {} is still in range and generated fees are very low. Skipping AI strategy and rebalance.",
-                    vault_address
-                );
-                return Ok(());
-            }
-        }
+    // The limits and the TWAP are only checked when a rebalance is required, an evaluation that
+    // would not rebalance records no blocked attempt
+    if let Some(reason) = blocked_reason(app_state, &vault_details, vault_config).await? {
+        warn!("Rebalance blocked for vault {}: {}", vault_address, reason);
+        plan.blocked_reason = Some(reason);
+        record_unsent_rebalance(
+            app_state,
+            &RebalanceRecord::from_plan(&plan, RebalanceStatus::Blocked, &position_before),
+        );
+        return Ok(());
+    }
 
-        // TEST ERROR
-        // return Err(color_eyre::eyre::eyre!(
-        //     "Vault {} already has a position. Send TEST ERROR",
-        //     vault_address
-        // ));
-
-        // Estimate balances after removing the existant liquidity bygetting the vault tvl, then call the rebalance function
-        let estim_balance0 = vault_details.tvl.tvl0;
-        let estim_balance1 = vault_details.tvl.tvl1;
-        let estim_balance0_u256 = parse_units(
-            estim_balance0.to_string().as_str(),
-            vault_details.pool.token0.decimals,
-        )?
-        .into();
-
-        let estim_balance1_u256 = parse_units(
-            estim_balance1.to_string().as_str(),
-            vault_details.pool.token1.decimals,
-        )?
-        .into();
-
-        let vault_token_balances = VaultTokenBalances {
-            token0_balance: estim_balance0,
-            token1_balance: estim_balance1,
-            token0_balance_u256: estim_balance0_u256,
-            token1_balance_u256: estim_balance1_u256,
-        };
+    let is_execute = vault_config.execute.unwrap_or(CONFIG.is_execute);
 
-        debug!(
-            "Esimated balances after removing all the liqudiity: {:?}",
-            vault_token_balances
+    if !is_execute {
+        warn!(
+            "Execution is disabled. Skipping rebalance for vault {}",
+            vault_address
         );
+        record_unsent_rebalance(
+            app_state,
+            &RebalanceRecord::from_plan(&plan, RebalanceStatus::DryRun, &position_before),
+        );
+        return Ok(());
+    }
 
-        // Call the rebalance function
-        rebalance_vault(&mut vault_details, &app_state, &vault_token_balances).await?;
-    } else {
+    execute_rebalance(vault_config, app_state, &mut vault_details, &plan).await?;
+
+    Ok(())
+}
+
+/// Reason to postpone a required rebalance: the circuit breaker and rebalance limits, then the
+/// pool TWAP. None when it can be sent
+pub async fn blocked_reason(
+    app_state: &WebAppState,
+    vault_details: &VaultDetails,
+    vault_config: &VaultConfig,
+) -> Result<Option<String>> {
+    let now = chrono::Utc::now().timestamp();
+    if let Some(reason) = core::guardrails::check_guardrails(&app_state.db, vault_config, now)? {
+        return Ok(Some(reason));
+    }
+
+    // The spot price can be manipulated within a block, the rebalance waits until it is back
+    // close to the TWAP
+    core::oracle::check_twap(&app_state.evm_provider, vault_details, vault_config).await
+}
+
+/// Record an attempt that sent no transaction (skipped, dry run, blocked) and prune the old ones.
+/// A storage failure is only logged, it must not fail the evaluation
+fn record_unsent_rebalance(app_state: &WebAppState, record: &RebalanceRecord) {
+    let before = chrono::Utc::now().timestamp() - UNSENT_REBALANCE_RETENTION_SECONDS;
+
+    let result = app_state.db.insert_rebalance(record).and_then(|_| {
+        app_state
+            .db
+            .prune_unsent_rebalances(&record.vault_address, before)
+    });
+    if let Err(e) = result {
+        error!(
+            "Failed to store the rebalance attempt of vault {}: {:?}",
+            record.vault_address, e
+        );
+    }
+}
+
+/// Record a sent rebalance. A storage failure is only logged, the transaction outcome is what
+/// the caller gets
+fn record_rebalance(app_state: &WebAppState, record: &RebalanceRecord) {
+    if let Err(e) = app_state.db.insert_rebalance(record) {
+        error!(
+            "Failed to store the rebalance of vault {}: {:?}",
+            record.vault_address, e
+        );
+    }
+}
+
+/// Send the rebalance of a plan, record it in the history and refresh the vault live data.
+/// Returns the transaction hash
+async fn execute_rebalance(
+    vault_config: &VaultConfig,
+    app_state: &WebAppState,
+    vault_details: &mut VaultDetails,
+    plan: &RebalancePlan,
+) -> Result<String> {
+    let vault_address = vault_config.address.as_str();
+    let position_before = vault_details.position.clone();
+
+    let mut record = RebalanceRecord::from_plan(plan, RebalanceStatus::Success, &position_before);
+
+    // Call the rebalance function
+    let rebalance_receipt =
+        match rebalance_vault(vault_details, vault_config, app_state, plan).await {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                record.status = RebalanceStatus::Error;
+                record.error = Some(e.to_string());
+                record_rebalance(app_state, &record);
+                record_rebalance_outcome(vault_config, app_state, record.error.as_deref());
+                return Err(e);
+            }
+        };
+
+    let rebalance_tx_hash = rebalance_receipt.transaction_hash;
+    record.tx_hash = Some(rebalance_tx_hash.to_string());
+    record.hbar_spent =
+        core::guardrails::hbar_spent(&rebalance_receipt, vault_config.rebalance_value_hbar)
+            .unwrap_or(vault_config.rebalance_value_hbar);
+
+    let receipt_error = core::preflight::check_receipt(&rebalance_receipt).err();
+    if let Some(e) = &receipt_error {
+        record.status = RebalanceStatus::Failed;
+        record.error = Some(e.to_string());
+    }
+    record_rebalance_outcome(vault_config, app_state, record.error.as_deref());
+
+    // Update the vault details in the app state after rebalance
+    let update_result =
+        core::vault::update_vault_live(&app_state.evm_provider, vault_details).await;
+    if update_result.is_ok() {
+        record.position_after = Some(vault_details.position.clone());
+        store_vault_details(app_state, vault_address, vault_details);
+    }
+
+    record_rebalance(app_state, &record);
+    update_result?;
+
+    if let Some(e) = receipt_error {
+        return Err(color_eyre::Report::from(e).wrap_err(format!(
+            "Rebalance transaction failed for vault {}. TX Hash: {}",
+            vault_address, rebalance_tx_hash
+        )));
+    }
+
+    info!(
+        "Rebalance transaction succeeded for vault {}. TX Hash: {}",
+        vault_address, rebalance_tx_hash
+    );
+
+    Ok(rebalance_tx_hash.to_string())
+}
+
+/// Update the circuit breaker of the vault with the outcome of a rebalance transaction (`error`
+/// set when it failed). A storage failure is only logged
+fn record_rebalance_outcome(
+    vault_config: &VaultConfig,
+    app_state: &WebAppState,
+    error: Option<&str>,
+) {
+    let vault_address = vault_config.address.as_str();
+
+    match app_state.db.record_rebalance_outcome(
+        vault_address,
+        error,
+        vault_config.max_consecutive_failures,
+        chrono::Utc::now().timestamp(),
+    ) {
+        Ok(breaker) if breaker.is_tripped() => error!(
+            "Circuit breaker tripped for vault {} after {} consecutive failed rebalances, rebalances are paused until an admin reset",
+            vault_address, breaker.consecutive_failures
+        ),
+        Ok(_) => {}
+        Err(e) => error!(
+            "Failed to update the circuit breaker of vault {}: {:?}",
+            vault_address, e
+        ),
+    }
+}
+
+/// Run the vault checks, the strategy and the swap/liquidity computation without sending any transaction
+pub async fn compute_rebalance_plan(
+    vault_details: &VaultDetails,
+    vault_config: &VaultConfig,
+    app_state: &WebAppState,
+) -> Result<RebalancePlan> {
+    let vault_address = vault_details.address.as_str();
+
+    let strategy_name = CONFIG.toml_config.strategy_for(&vault_config.address);
+
+    // 1. Get the balances the position will be minted with
+    if vault_details.is_active {
         debug!(
-            "Vault {} does not have a position. Checking if it is possible to mint a new position...",
+            "Vault {} has already a position. Checking if need to rebalance...",
             vault_address
         );
 
-        // 2. Check if the vault is eligible to mint a new position by checking if it has some token balances
-        let balance0 = vault_token_balances.token0_balance;
-        let balance1 = vault_token_balances.token1_balance;
+        // Check if teh vault is out of range by checking the current tick
+        let current_tick = vault_details.pool.current_tick;
+        let is_out_of_range = !is_tick_in_range(
+            current_tick,
+            vault_details.lower_tick,
+            vault_details.upper_tick,
+        );
 
-        if balance0 <= f64::EPSILON && balance1 <= f64::EPSILON {
-            warn!(
-                "Vault {} does not have any token balances.Its balances are: {} , {} . Skipping minting liquidity.",
-                vault_address, balance0, balance1
+        // If the vault is not out of range, we skip the rebalance if teh fees are very low
+        if !is_out_of_range
+            && (vault_details.position.fees0 < vault_config.min_fees0
+                || vault_details.position.fees1 < vault_config.min_fees1)
+        {
+            return skipped_plan(
+                vault_details,
+                strategy_name,
+                "Vault is still in range and generated fees are very low".to_string(),
+                None,
             );
-            return Ok(());
         }
-
-        // 3. Call the rebalance function
-        rebalance_vault(&mut vault_details, &app_state, &vault_token_balances).await?;
     }
 
-    // 4. Update the vault details in the app state after rebalance
-    core::vault::update_vault_live(&app_state.evm_provider, &mut vault_details).await?;
+    let vault_token_balances = rebalance_balances(vault_details, app_state).await?;
 
-    Ok(())
-}
-
-pub async fn rebalance_vault(
-    vault_details: &mut VaultDetails,
-    _app_state: &WebAppState,
-    vault_token_balances: &VaultTokenBalances,
-) -> Result<()> {
     let balance0 = vault_token_balances.token0_balance;
     let balance1 = vault_token_balances.token1_balance;
 
-    // 3.2 Start strategy thta will get me the best tick range to put liq on
-    let _basic_tick_range = strategies::basic::get_best_range(&vault_details).await?;
+    if !vault_details.is_active && balance0 <= f64::EPSILON && balance1 <= f64::EPSILON {
+        return skipped_plan(
+            vault_details,
+            strategy_name,
+            format!(
+                "Vault does not have any token balances. Its balances are: {} , {}",
+                balance0, balance1
+            ),
+            None,
+        );
+    }
 
-    // 3.3 Start ai strategy that will get me the best tick range to put liq on
-    let ai_strategy_result = strategies::ai::start(&vault_details).await?;
+    // 2. Run the strategy configured for this vault to get the best tick range to put liq on
+    let strategy = app_state
+        .strategies
+        .build(strategy_name, &vault_config.strategy_params)?;
+
+    let decision = strategy
+        .decide(vault_details, &MarketContext::live())
+        .await
+        .map_err(|e| YielderaError::Strategy {
+            strategy: strategy.name().to_string(),
+            message: format!("{:#}", e),
+        })?;
 
-    if !ai_strategy_result.rebalance_required {
-        warn!(
-            "AI strategy does not recommend rebalance for vault {}. Skipping rebalance.",
-            vault_details.address
+    if !decision.rebalance_required {
+        return skipped_plan(
+            vault_details,
+            strategy_name,
+            format!("Strategy {} does not recommend rebalance", strategy.name()),
+            decision.analysis,
         );
-        return Ok(());
     }
 
-    let ai_tick_range =
-        strategies::ai::get_tick_range_from_ai_response(ai_strategy_result, &vault_details).await?;
+    info!(
+        "Strategy {} Tick range: {:?}",
+        strategy.name(),
+        decision.tick_range
+    );
 
-    info!("AI strategy Tick range: {:?}", ai_tick_range);
+    let lower_tick = decision.tick_range.lower_tick;
+    let upper_tick = decision.tick_range.upper_tick;
 
-    let tick_range = ai_tick_range;
+    if vault_details.lower_tick == lower_tick && vault_details.upper_tick == upper_tick {
+        return skipped_plan(
+            vault_details,
+            strategy_name,
+            "Vault already has the best tick range".to_string(),
+            decision.analysis,
+        );
+    }
 
-    let lower_tick = tick_range.lower_tick;
-    let upper_tick = tick_range.upper_tick;
-    let current_tick = tick_range.curent_tick;
+    plan_for_range(
+        vault_details,
+        vault_config,
+        app_state,
+        &vault_token_balances,
+        strategy_name,
+        (lower_tick, upper_tick),
+        decision.analysis,
+    )
+    .await
+}
 
-    if vault_details.lower_tick == lower_tick && vault_details.upper_tick == upper_tick {
-        warn!(
-            "Vault {} already has the best tick range. Skipping rebalance.",
+/// Plan of an admin rebalance to an explicit tick range, the strategy and the fees checks are
+/// bypassed
+pub async fn compute_forced_rebalance_plan(
+    vault_details: &VaultDetails,
+    vault_config: &VaultConfig,
+    app_state: &WebAppState,
+    lower_tick: i32,
+    upper_tick: i32,
+) -> Result<RebalancePlan> {
+    check_tick_range(lower_tick, upper_tick, vault_details.pool.tick_spacing)?;
+
+    let vault_token_balances = rebalance_balances(vault_details, app_state).await?;
+
+    if vault_token_balances.token0_balance <= f64::EPSILON
+        && vault_token_balances.token1_balance <= f64::EPSILON
+    {
+        return Err(color_eyre::eyre::eyre!(
+            "Vault {} does not have any token balances",
             vault_details.address
-        );
-        return Ok(());
+        ));
+    }
+
+    plan_for_range(
+        vault_details,
+        vault_config,
+        app_state,
+        &vault_token_balances,
+        MANUAL_STRATEGY,
+        (lower_tick, upper_tick),
+        None,
+    )
+    .await
+}
+
+/// A valid range has `lower < upper`, both within the tick bounds and multiples of the pool
+/// tick spacing
+pub fn check_tick_range(lower_tick: i32, upper_tick: i32, tick_spacing: i32) -> Result<()> {
+    if lower_tick >= upper_tick {
+        return Err(color_eyre::eyre::eyre!(
+            "Lower tick {} must be below upper tick {}",
+            lower_tick,
+            upper_tick
+        ));
     }
 
-    // DEBUG: STop here for debugging purposes
-    // return Ok(());
+    if lower_tick < MIN_TICK || upper_tick > MAX_TICK {
+        return Err(color_eyre::eyre::eyre!(
+            "Tick range must be within [{}, {}]",
+            MIN_TICK,
+            MAX_TICK
+        ));
+    }
+
+    if tick_spacing <= 0 || lower_tick % tick_spacing != 0 || upper_tick % tick_spacing != 0 {
+        return Err(color_eyre::eyre::eyre!(
+            "Ticks must be multiples of the pool tick spacing {}",
+            tick_spacing
+        ));
+    }
+
+    Ok(())
+}
+
+/// Balances the new position will be minted with: the vault TVL when a position is active (all
+/// its liquidity is removed first), the vault token balances otherwise
+async fn rebalance_balances(
+    vault_details: &VaultDetails,
+    app_state: &WebAppState,
+) -> Result<VaultTokenBalances> {
+    if !vault_details.is_active {
+        return core::vault::get_vault_tokens_balances(&app_state.evm_provider, vault_details)
+            .await;
+    }
+
+    // Estimate balances after removing the existant liquidity by getting the vault tvl
+    let estim_balance0 = vault_details.tvl.tvl0;
+    let estim_balance1 = vault_details.tvl.tvl1;
+    let estim_balance0_u256 = parse_units(
+        estim_balance0.to_string().as_str(),
+        vault_details.pool.token0.decimals,
+    )?
+    .into();
+
+    let estim_balance1_u256 = parse_units(
+        estim_balance1.to_string().as_str(),
+        vault_details.pool.token1.decimals,
+    )?
+    .into();
+
+    let vault_token_balances = VaultTokenBalances {
+        token0_balance: estim_balance0,
+        token1_balance: estim_balance1,
+        token0_balance_u256: estim_balance0_u256,
+        token1_balance_u256: estim_balance1_u256,
+    };
+
+    debug!(
+        "Esimated balances after removing all the liqudiity: {:?}",
+        vault_token_balances
+    );
+
+    Ok(vault_token_balances)
+}
 
-    // 3.3 Get the appropriate amount of token0 and token1 to add liquidity
+/// Swap and position minted for a rebalance of the vault to `(lower_tick, upper_tick)`
+async fn plan_for_range(
+    vault_details: &VaultDetails,
+    vault_config: &VaultConfig,
+    app_state: &WebAppState,
+    vault_token_balances: &VaultTokenBalances,
+    strategy_name: &str,
+    (lower_tick, upper_tick): (i32, i32),
+    analysis: Option<String>,
+) -> Result<RebalancePlan> {
+    // 3. Find the swap leaving the balances in the ratio of the new range, with the pool fee and price impact
     let lower_tick_sqrt_price =
         helpers::math::uniswap_v3::tick_math::get_sqrt_ratio_at_tick(lower_tick)?;
     let upper_tick_sqrt_price =
         helpers::math::uniswap_v3::tick_math::get_sqrt_ratio_at_tick(upper_tick)?;
-    let current_tick_sqrt_price =
-        helpers::math::uniswap_v3::tick_math::get_sqrt_ratio_at_tick(current_tick)?;
 
-    let balance1_token0_equivalent = balance1 * vault_details.pool.price0;
-    let is_balance0_larger = balance0 > balance1_token0_equivalent;
+    let mut pool_state =
+        core::pool_state::get_pool_state(&app_state.evm_provider, &vault_details.pool.address)
+            .await?;
 
-    let liquidity = if is_balance0_larger {
-        helpers::math::uniswap_v3::liquidity_math::get_liquidity_for_amount0(
-            lower_tick_sqrt_price,
-            upper_tick_sqrt_price,
-            vault_token_balances.token0_balance_u256,
-        )?
-    } else {
-        helpers::math::uniswap_v3::liquidity_math::get_liquidity_for_amount1(
-            lower_tick_sqrt_price,
-            upper_tick_sqrt_price,
-            vault_token_balances.token1_balance_u256,
-        )?
-    };
+    // `rebalance` burns the vault position before the swap, quote it without that liquidity
+    if vault_details.is_active {
+        pool_state.remove_position(
+            vault_details.lower_tick,
+            vault_details.upper_tick,
+            vault_details.position.liquidity,
+        )?;
+    }
 
-    let (amount0, amount1) = helpers::math::uniswap_v3::liquidity_math::get_amounts_for_liquidity(
-        current_tick_sqrt_price,
+    let swap_solution = helpers::math::swap_to_ratio::solve_swap_to_ratio(
+        vault_token_balances.token0_balance_u256,
+        vault_token_balances.token1_balance_u256,
+        pool_state.sqrt_price_x96,
         lower_tick_sqrt_price,
         upper_tick_sqrt_price,
-        liquidity,
+        |amount_out, zero_for_one| {
+            let simulation = pool_state.simulate_exact_output(amount_out, zero_for_one)?;
+            Ok(SwapQuote {
+                amount_in: simulation.amount_in,
+                sqrt_price_x96_after: simulation.sqrt_price_x96_after,
+            })
+        },
     )?;
 
-    let desired_amount0: f64 =
-        format_units(amount0, vault_details.pool.token0.decimals)?.parse()?;
-    let desired_amount1: f64 =
-        format_units(amount1, vault_details.pool.token1.decimals)?.parse()?;
-
-    // Prepare if need to swap token0 for token1 or teh reverse and how much to swap
-    let exess0 = balance0 - desired_amount0;
-    let exess1 = balance1 - desired_amount1;
-
-    // Prepare the swap args for the negative value betwen the exess0 and exess1
-    let swap_arg: PrepareSwapArgs;
-
-    if exess0 < 0.0 {
-        let exact_amount_out = exess0.abs();
-        let parsed_exact_amount_out: U256 = parse_units(
-            exact_amount_out.to_string().as_str(),
-            vault_details.pool.token0.decimals,
-        )?
-        .into();
-        let max_amount_in: U256 = parse_units(
-            exess1.abs().to_string().as_str(),
-            vault_details.pool.token1.decimals,
-        )?
-        .into();
-
-        swap_arg = PrepareSwapArgs {
-            exact_amount_out,
-            parsed_exact_amount_out,
-            token_in: vault_details.pool.token1.clone(),
-            token_out: vault_details.pool.token0.clone(),
-            is_swap_0_to_1: false,
-            max_amount_in,
-            formatted_max_amount_in: exess1.abs(),
-        };
-    } else if exess1 < 0.0 {
-        let exact_amount_out = exess1.abs();
-        let parsed_exact_amount_out: U256 = parse_units(
-            exact_amount_out.to_string().as_str(),
-            vault_details.pool.token1.decimals,
-        )?
-        .into();
-        let max_amount_in: U256 = parse_units(
-            exess0.abs().to_string().as_str(),
-            vault_details.pool.token0.decimals,
-        )?
-        .into();
-
-        swap_arg = PrepareSwapArgs {
-            exact_amount_out,
-            parsed_exact_amount_out,
-            token_in: vault_details.pool.token0.clone(),
-            token_out: vault_details.pool.token1.clone(),
-            is_swap_0_to_1: true,
-            max_amount_in,
-            formatted_max_amount_in: exess0.abs(),
-        };
-    } else {
-        // No need to swap
-        swap_arg = PrepareSwapArgs {
-            exact_amount_out: 0.0,
-            parsed_exact_amount_out: U256::ZERO,
-            token_in: vault_details.pool.token0.clone(),
-            token_out: vault_details.pool.token1.clone(),
-            is_swap_0_to_1: true,
-            max_amount_in: U256::ZERO,
-            formatted_max_amount_in: 0.0,
-        };
-    }
+    let swap = plan_swap(
+        vault_details,
+        vault_config,
+        vault_token_balances,
+        &swap_solution,
+    )?;
+
+    // 4. Position minted with the balances left after the swap
+    let (expected_amount0, expected_amount1) =
+        helpers::math::uniswap_v3::liquidity_math::get_amounts_for_liquidity(
+            swap_solution.sqrt_price_x96_after,
+            lower_tick_sqrt_price,
+            upper_tick_sqrt_price,
+            swap_solution.liquidity,
+        )?;
+
+    let token0_decimals = vault_details.pool.token0.decimals;
+    let token1_decimals = vault_details.pool.token1.decimals;
+
+    Ok(RebalancePlan {
+        vault_address: vault_details.address.clone(),
+        strategy: strategy_name.to_string(),
+        rebalance_required: true,
+        skip_reason: None,
+        blocked_reason: None,
+        current_tick: vault_details.pool.current_tick,
+        current_lower_tick: vault_details.lower_tick,
+        current_upper_tick: vault_details.upper_tick,
+        lower_tick,
+        upper_tick,
+        price1: vault_details.pool.price1,
+        lower_price1: helpers::math::tick_to_price(lower_tick, token0_decimals, token1_decimals)?,
+        upper_price1: helpers::math::tick_to_price(upper_tick, token0_decimals, token1_decimals)?,
+        swap,
+        expected_liquidity: swap_solution.liquidity,
+        expected_amount0: format_units(expected_amount0, token0_decimals)?.parse()?,
+        expected_amount1: format_units(expected_amount1, token1_decimals)?.parse()?,
+        analysis,
+    })
+}
 
+/// Plan keeping the vault position as it is
+fn skipped_plan(
+    vault_details: &VaultDetails,
+    strategy_name: &str,
+    skip_reason: String,
+    analysis: Option<String>,
+) -> Result<RebalancePlan> {
+    let token0_decimals = vault_details.pool.token0.decimals;
+    let token1_decimals = vault_details.pool.token1.decimals;
+
+    Ok(RebalancePlan {
+        vault_address: vault_details.address.clone(),
+        strategy: strategy_name.to_string(),
+        rebalance_required: false,
+        skip_reason: Some(skip_reason),
+        blocked_reason: None,
+        current_tick: vault_details.pool.current_tick,
+        current_lower_tick: vault_details.lower_tick,
+        current_upper_tick: vault_details.upper_tick,
+        lower_tick: vault_details.lower_tick,
+        upper_tick: vault_details.upper_tick,
+        price1: vault_details.pool.price1,
+        lower_price1: helpers::math::tick_to_price(
+            vault_details.lower_tick,
+            token0_decimals,
+            token1_decimals,
+        )?,
+        upper_price1: helpers::math::tick_to_price(
+            vault_details.upper_tick,
+            token0_decimals,
+            token1_decimals,
+        )?,
+        swap: None,
+        expected_liquidity: vault_details.position.liquidity,
+        expected_amount0: vault_details.position.amount0,
+        expected_amount1: vault_details.position.amount1,
+        analysis,
+    })
+}
+
+/// Submit the rebalance transaction of a plan to the transaction manager and wait for its receipt
+pub async fn rebalance_vault(
+    vault_details: &VaultDetails,
+    vault_config: &VaultConfig,
+    app_state: &WebAppState,
+    plan: &RebalancePlan,
+) -> Result<TransactionReceipt> {
     let vault_address = vault_details.address.as_str();
 
-    let is_execute = CONFIG.is_execute;
+    info!(
+        "Rebalance plan for vault {}: range [{}, {}], swap {:?}, expected liquidity {}",
+        vault_address, plan.lower_tick, plan.upper_tick, plan.swap, plan.expected_liquidity
+    );
 
-    if is_execute {
-        // Reint evm provider to ensure it has teh latest nonce
-        let evm_provider = core::init::init_evm_provider().await?;
+    let (parsed_exact_amount_out, max_amount_in, is_swap_0_to_1) = match &plan.swap {
+        Some(swap) => (swap.amount_out, swap.max_amount_in, swap.is_swap_0_to_1),
+        None => (U256::ZERO, U256::ZERO, true),
+    };
 
-        // call rebelance on the vault with new tick range and swap direction and amount
-        let vault_contract = YielderaVault::new(Address::from_str(vault_address)?, &evm_provider);
+    // call rebelance on the vault with new tick range and swap direction and amount
+    let vault_contract =
+        YielderaVault::new(Address::from_str(vault_address)?, &app_state.evm_provider);
 
-        let upper_tick = I24::from_str(upper_tick.to_string().as_str())?;
-        let lower_tick = I24::from_str(lower_tick.to_string().as_str())?;
+    let upper_tick = I24::from_str(plan.upper_tick.to_string().as_str())?;
+    let lower_tick = I24::from_str(plan.lower_tick.to_string().as_str())?;
 
-        let value_to_send: U256 = parse_units("0.2", 18)?.into();
+    let value_to_send: U256 = parse_units(
+        format!("{:.18}", vault_config.rebalance_value_hbar).as_str(),
+        18,
+    )?
+    .into();
 
-        let rebalnce_reciept = vault_contract
+    let rebalance_request = core::preflight::prepare(
+        vault_contract
             .rebalance(
                 lower_tick,
                 upper_tick,
-                swap_arg.parsed_exact_amount_out,
-                swap_arg.max_amount_in,
-                swap_arg.is_swap_0_to_1,
+                parsed_exact_amount_out,
+                max_amount_in,
+                is_swap_0_to_1,
             )
-            .value(value_to_send)
-            .gas(15_000_000)
-            .send()
-            .await?
-            .get_receipt()
-            .await?;
+            .value(value_to_send),
+    )
+    .await?;
+    let rebalnce_reciept = app_state.tx_manager.submit(rebalance_request).await?;
 
-        let rebalnce_tx_hash = rebalnce_reciept.transaction_hash;
+    info!(
+        "Rebalance TX Hash for vault {} is: {}",
+        vault_address, rebalnce_reciept.transaction_hash
+    );
 
-        info!(
-            "Rebalance TX Hash for vault {} is: {}",
-            vault_address, rebalnce_tx_hash
-        );
+    Ok(rebalnce_reciept)
+}
 
-        let rebalnce_tx_status = rebalnce_reciept.status();
-
-        core::csv_logger::log_rebalance_result_to_csv(RebalanceLogEntry {
-            timestamp: chrono::Utc::now().to_string(),
-            vault_address: vault_address.to_string(),
-            transaction_hash: rebalnce_tx_hash.to_string(),
-            transaction_status: if rebalnce_tx_status {
-                "Success".to_string()
-            } else {
-                "Failed".to_string()
-            },
-            tvl0: vault_details.tvl.tvl0,
-            tvl1: vault_details.tvl.tvl1,
-            fees0_bef: vault_details.position.fees0,
-            fees1_bef: vault_details.position.fees1,
-            current_tick: vault_details.pool.current_tick,
-            lower_tick_bef: vault_details.lower_tick,
-            upper_tick_bef: vault_details.upper_tick,
-            lower_tick_aft: lower_tick.as_i32(),
-            upper_tick_aft: upper_tick.as_i32(),
-            liquidity_bef: vault_details.position.liquidity,
-            amount0_bef: vault_details.position.amount0,
-            amount1_bef: vault_details.position.amount1,
-            swap_amount_out: swap_arg.exact_amount_out,
-            swap_max_amount_in: swap_arg.formatted_max_amount_in,
-            is_swap_0_to_1: swap_arg.is_swap_0_to_1,
-        })?;
+/// Swap part of the plan from the solver output, None when no swap is needed. The max amount in is
+/// the simulated amount in plus the allowed slippage, capped at the vault balance of the token in
+fn plan_swap(
+    vault_details: &VaultDetails,
+    vault_config: &VaultConfig,
+    vault_token_balances: &VaultTokenBalances,
+    swap_solution: &SwapToRatio,
+) -> Result<Option<RebalanceSwapPlan>> {
+    if swap_solution.amount_out.is_zero() {
+        return Ok(None);
+    }
 
-        if !rebalnce_tx_status {
-            return Err(color_eyre::eyre::eyre!(
-                "Rebalance transaction failed for vault {}. TX Hash: {},  Error: {:?}",
-                vault_address,
-                rebalnce_tx_hash,
-                rebalnce_reciept
-            ));
-        } else {
-            info!(
-                "Rebalance transaction succeeded for vault {}. TX Hash: {}",
-                vault_address, rebalnce_tx_hash
-            );
-        }
+    let is_swap_0_to_1 = swap_solution.zero_for_one;
+
+    let (token_in, token_out, balance_in) = if is_swap_0_to_1 {
+        (
+            &vault_details.pool.token0,
+            &vault_details.pool.token1,
+            vault_token_balances.token0_balance_u256,
+        )
     } else {
+        (
+            &vault_details.pool.token1,
+            &vault_details.pool.token0,
+            vault_token_balances.token1_balance_u256,
+        )
+    };
+
+    let price1_after = helpers::math::tick_to_price(
+        helpers::math::uniswap_v3::tick_math::get_tick_at_sqrt_ratio(
+            swap_solution.sqrt_price_x96_after,
+        )?,
+        vault_details.pool.token0.decimals,
+        vault_details.pool.token1.decimals,
+    )?;
+
+    // Slippage in basis points on top of the simulated amount in
+    let slippage_bps = U256::from((vault_config.max_slippage_percent * 100.0).round() as u64);
+    let max_amount_in =
+        swap_solution.amount_in * (U256::from(10_000) + slippage_bps) / U256::from(10_000);
+
+    let max_amount_in = if max_amount_in > balance_in {
         warn!(
-            "Execution is disabled. Skipping rebalance for vault {}",
-            vault_address
+            "Vault {} swap max amount in {} is capped by the available balance {}",
+            vault_details.address, max_amount_in, balance_in
         );
-    }
+        balance_in
+    } else {
+        max_amount_in
+    };
 
-    Ok(())
+    Ok(Some(RebalanceSwapPlan {
+        is_swap_0_to_1,
+        token_in: token_in.symbol.clone(),
+        token_out: token_out.symbol.clone(),
+        amount_in: swap_solution.amount_in,
+        formatted_amount_in: format_units(swap_solution.amount_in, token_in.decimals)?.parse()?,
+        amount_out: swap_solution.amount_out,
+        formatted_amount_out: format_units(swap_solution.amount_out, token_out.decimals)?
+            .parse()?,
+        max_amount_in,
+        formatted_max_amount_in: format_units(max_amount_in, token_in.decimals)?.parse()?,
+        price1_after,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_tick_range;
+
+    #[test]
+    fn test_check_tick_range() {
+        assert!(check_tick_range(-120, 120, 60).is_ok());
+
+        assert!(check_tick_range(120, 120, 60).is_err());
+        assert!(check_tick_range(120, -120, 60).is_err());
+        assert!(check_tick_range(-100, 120, 60).is_err());
+        assert!(check_tick_range(-887_280, 120, 60).is_err());
+    }
 }
@@ -0,0 +1,102 @@
+use color_eyre::eyre::Result;
+use rusqlite::params;
+
+use crate::{
+    db::{Db, rebalances::MAX_PAGE_SIZE},
+    types::{AdminAuditEntry, AdminAuditPage},
+};
+
+impl Db {
+    pub fn insert_audit_entry(&self, entry: &AdminAuditEntry) -> Result<()> {
+        let conn = self.conn()?;
+
+        conn.execute(
+            "INSERT INTO admin_audit_log (timestamp, principal, method, path, status, error)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                entry.timestamp,
+                entry.principal,
+                entry.method,
+                entry.path,
+                entry.status,
+                entry.error
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Admin requests, most recent first. Pages start at 1
+    pub fn list_audit_entries(&self, page: u32, page_size: u32) -> Result<AdminAuditPage> {
+        let page = page.max(1);
+        let page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+
+        let conn = self.conn()?;
+
+        let total: i64 =
+            conn.query_row("SELECT COUNT(*) FROM admin_audit_log", [], |row| row.get(0))?;
+
+        let mut statement = conn.prepare(
+            "SELECT timestamp, principal, method, path, status, error FROM admin_audit_log
+            ORDER BY timestamp DESC, id DESC LIMIT ?1 OFFSET ?2",
+        )?;
+
+        let items = statement
+            .query_map(
+                params![page_size, (page as i64 - 1) * page_size as i64],
+                |row| {
+                    Ok(AdminAuditEntry {
+                        timestamp: row.get(0)?,
+                        principal: row.get(1)?,
+                        method: row.get(2)?,
+                        path: row.get(3)?,
+                        status: row.get(4)?,
+                        error: row.get(5)?,
+                    })
+                },
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(AdminAuditPage {
+            items,
+            page,
+            page_size,
+            total: total as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{db::Db, types::AdminAuditEntry};
+
+    #[test]
+    fn test_insert_and_list_audit_entries() {
+        let db = Db::open(":memory:").unwrap();
+
+        let denied = AdminAuditEntry {
+            timestamp: 100,
+            principal: None,
+            method: "POST".to_string(),
+            path: "/api/v1/admin/vaults/0xabc/pause".to_string(),
+            status: 401,
+            error: Some("Invalid API key".to_string()),
+        };
+        let accepted = AdminAuditEntry {
+            timestamp: 200,
+            principal: Some("api_key:ops".to_string()),
+            status: 200,
+            error: None,
+            ..denied.clone()
+        };
+        db.insert_audit_entry(&denied).unwrap();
+        db.insert_audit_entry(&accepted).unwrap();
+
+        let page = db.list_audit_entries(1, 10).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.items, vec![accepted, denied.clone()]);
+
+        let page = db.list_audit_entries(2, 1).unwrap();
+        assert_eq!(page.items, vec![denied]);
+    }
+}
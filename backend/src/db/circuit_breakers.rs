@@ -0,0 +1,141 @@
+use color_eyre::eyre::Result;
+use rusqlite::{OptionalExtension, params};
+
+use crate::{db::Db, types::CircuitBreaker};
+
+impl CircuitBreaker {
+    pub fn is_tripped(&self) -> bool {
+        self.tripped_at.is_some()
+    }
+}
+
+impl Db {
+    /// Circuit breaker of a vault, closed when the vault never failed
+    pub fn circuit_breaker(&self, vault_address: &str) -> Result<CircuitBreaker> {
+        let vault_address = vault_address.to_lowercase();
+        let conn = self.conn()?;
+
+        let breaker = conn
+            .query_row(
+                "SELECT consecutive_failures, tripped_at, reason FROM circuit_breakers
+                WHERE vault_address = ?1",
+                params![vault_address],
+                |row| {
+                    Ok(CircuitBreaker {
+                        vault_address: vault_address.clone(),
+                        consecutive_failures: row.get(0)?,
+                        tripped_at: row.get(1)?,
+                        reason: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(breaker.unwrap_or(CircuitBreaker {
+            vault_address,
+            ..CircuitBreaker::default()
+        }))
+    }
+
+    /// Record the outcome of a rebalance transaction: a success closes the breaker, a failure
+    /// (`error` set) trips it at `max_failures` consecutive failures. A tripped breaker stays
+    /// tripped until `reset_circuit_breaker`
+    pub fn record_rebalance_outcome(
+        &self,
+        vault_address: &str,
+        error: Option<&str>,
+        max_failures: u32,
+        now: i64,
+    ) -> Result<CircuitBreaker> {
+        let mut breaker = self.circuit_breaker(vault_address)?;
+
+        match error {
+            None if !breaker.is_tripped() => {
+                breaker.consecutive_failures = 0;
+                breaker.reason = None;
+            }
+            None => {}
+            Some(error) => {
+                breaker.consecutive_failures += 1;
+                breaker.reason = Some(error.to_string());
+                if breaker.consecutive_failures >= max_failures && !breaker.is_tripped() {
+                    breaker.tripped_at = Some(now);
+                }
+            }
+        }
+
+        self.save_circuit_breaker(&breaker)?;
+
+        Ok(breaker)
+    }
+
+    /// Close the breaker of a vault, its rebalances resume
+    pub fn reset_circuit_breaker(&self, vault_address: &str) -> Result<CircuitBreaker> {
+        let breaker = CircuitBreaker {
+            vault_address: vault_address.to_lowercase(),
+            ..CircuitBreaker::default()
+        };
+
+        self.save_circuit_breaker(&breaker)?;
+
+        Ok(breaker)
+    }
+
+    fn save_circuit_breaker(&self, breaker: &CircuitBreaker) -> Result<()> {
+        let conn = self.conn()?;
+
+        conn.execute(
+            "INSERT INTO circuit_breakers (vault_address, consecutive_failures, tripped_at, reason)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(vault_address) DO UPDATE SET
+                consecutive_failures = excluded.consecutive_failures,
+                tripped_at = excluded.tripped_at,
+                reason = excluded.reason",
+            params![
+                breaker.vault_address,
+                breaker.consecutive_failures,
+                breaker.tripped_at,
+                breaker.reason
+            ],
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Db;
+
+    #[test]
+    fn test_circuit_breaker_trips_and_resets() {
+        let db = Db::open(":memory:").unwrap();
+
+        assert!(!db.circuit_breaker("0xabc").unwrap().is_tripped());
+
+        // A success in between restarts the count
+        db.record_rebalance_outcome("0xABC", Some("reverted"), 2, 100)
+            .unwrap();
+        db.record_rebalance_outcome("0xabc", None, 2, 110).unwrap();
+        let breaker = db
+            .record_rebalance_outcome("0xabc", Some("reverted"), 2, 120)
+            .unwrap();
+        assert_eq!(breaker.consecutive_failures, 1);
+        assert!(!breaker.is_tripped());
+
+        let breaker = db
+            .record_rebalance_outcome("0xabc", Some("out of gas"), 2, 130)
+            .unwrap();
+        assert_eq!(breaker.tripped_at, Some(130));
+        assert_eq!(breaker.reason.as_deref(), Some("out of gas"));
+
+        // Only an admin reset closes a tripped breaker
+        db.record_rebalance_outcome("0xabc", None, 2, 140).unwrap();
+        assert_eq!(db.circuit_breaker("0xAbC").unwrap().tripped_at, Some(130));
+
+        db.reset_circuit_breaker("0xABC").unwrap();
+        let breaker = db.circuit_breaker("0xabc").unwrap();
+        assert!(!breaker.is_tripped());
+        assert_eq!(breaker.consecutive_failures, 0);
+    }
+}
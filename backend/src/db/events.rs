@@ -0,0 +1,305 @@
+use color_eyre::eyre::Result;
+use rusqlite::{OptionalExtension, Row, params};
+
+use crate::{
+    db::{Db, rebalances::MAX_PAGE_SIZE},
+    types::{VaultEvent, VaultEventData, VaultEventsPage},
+};
+
+impl VaultEventData {
+    pub fn name(&self) -> &'static str {
+        match self {
+            VaultEventData::Deposit { .. } => "deposit",
+            VaultEventData::Withdraw { .. } => "withdraw",
+            VaultEventData::Rebalance { .. } => "rebalance",
+            VaultEventData::MintLiquidity { .. } => "mint_liquidity",
+            VaultEventData::BurnAllLiquidity { .. } => "burn_all_liquidity",
+            VaultEventData::CollectFees { .. } => "collect_fees",
+            VaultEventData::AssociateToken { .. } => "associate_token",
+            VaultEventData::Transfer { .. } => "transfer",
+        }
+    }
+
+    /// Owner of the shares moved by the event: receiver of a deposit, burner of a withdraw,
+    /// sender of a transfer
+    pub fn account(&self) -> Option<&str> {
+        match self {
+            VaultEventData::Deposit { to, .. } => Some(to),
+            VaultEventData::Withdraw { sender, .. } => Some(sender),
+            VaultEventData::Transfer { from, .. } => Some(from),
+            _ => None,
+        }
+    }
+
+    /// Receiver of the shares of a transfer
+    pub fn receiver(&self) -> Option<&str> {
+        match self {
+            VaultEventData::Transfer { to, .. } => Some(to),
+            _ => None,
+        }
+    }
+}
+
+impl VaultEvent {
+    fn from_row(row: &Row) -> Result<Self> {
+        let block_number: i64 = row.get("block_number")?;
+        let log_index: i64 = row.get("log_index")?;
+        let data: String = row.get("data")?;
+
+        Ok(Self {
+            vault_address: row.get("vault_address")?,
+            block_number: block_number as u64,
+            block_timestamp: row.get("block_timestamp")?,
+            tx_hash: row.get("tx_hash")?,
+            log_index: log_index as u64,
+            event: serde_json::from_str(&data)?,
+        })
+    }
+}
+
+impl Db {
+    /// Last indexed block of a vault
+    pub fn indexer_cursor(&self, vault_address: &str) -> Result<Option<u64>> {
+        let conn = self.conn()?;
+
+        let last_block: Option<i64> = conn
+            .query_row(
+                "SELECT last_block FROM indexer_cursors WHERE vault_address = ?1",
+                params![vault_address.to_lowercase()],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(last_block.map(|block| block as u64))
+    }
+
+    /// Record the block the indexer of a vault starts from, on its first run
+    pub fn set_indexer_start(
+        &self,
+        vault_address: &str,
+        start_block: u64,
+        is_deployment_block: bool,
+    ) -> Result<()> {
+        let conn = self.conn()?;
+
+        conn.execute(
+            "INSERT INTO indexer_starts (vault_address, start_block, is_deployment_block)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT (vault_address) DO UPDATE SET
+                start_block = excluded.start_block,
+                is_deployment_block = excluded.is_deployment_block",
+            params![
+                vault_address.to_lowercase(),
+                start_block as i64,
+                is_deployment_block
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// The events of the vault are indexed from its deployment block, none is missing
+    pub fn is_indexed_from_deployment(&self, vault_address: &str) -> Result<bool> {
+        let conn = self.conn()?;
+
+        let is_deployment_block: Option<bool> = conn
+            .query_row(
+                "SELECT is_deployment_block FROM indexer_starts WHERE vault_address = ?1",
+                params![vault_address.to_lowercase()],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(is_deployment_block.unwrap_or(false))
+    }
+
+    /// Store the events of a block range and move the cursor to its last block, atomically.
+    /// Events already stored are ignored so a range can be indexed again safely
+    pub fn insert_events(
+        &self,
+        vault_address: &str,
+        events: &[VaultEvent],
+        last_block: u64,
+    ) -> Result<()> {
+        let vault_address = vault_address.to_lowercase();
+
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+
+        for event in events {
+            tx.execute(
+                "INSERT OR IGNORE INTO vault_events (
+                    vault_address, block_number, block_timestamp, tx_hash, log_index, name,
+                    account, receiver, data
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    vault_address,
+                    event.block_number as i64,
+                    event.block_timestamp,
+                    event.tx_hash,
+                    event.log_index as i64,
+                    event.event.name(),
+                    event.event.account().map(|account| account.to_lowercase()),
+                    event
+                        .event
+                        .receiver()
+                        .map(|receiver| receiver.to_lowercase()),
+                    serde_json::to_string(&event.event)?,
+                ],
+            )?;
+        }
+
+        tx.execute(
+            "INSERT INTO indexer_cursors (vault_address, last_block) VALUES (?1, ?2)
+            ON CONFLICT (vault_address) DO UPDATE SET last_block = excluded.last_block",
+            params![vault_address, last_block as i64],
+        )?;
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Deposits, withdrawals and share transfers of an account in every vault, oldest first
+    pub fn account_events(&self, account: &str) -> Result<Vec<VaultEvent>> {
+        let conn = self.conn()?;
+
+        let mut statement = conn.prepare(
+            "SELECT * FROM vault_events WHERE account = ?1 OR receiver = ?1
+            ORDER BY vault_address, block_number ASC, log_index ASC",
+        )?;
+
+        let mut rows = statement.query(params![account.to_lowercase()])?;
+
+        let mut items = Vec::new();
+        while let Some(row) = rows.next()? {
+            items.push(VaultEvent::from_row(row)?);
+        }
+
+        Ok(items)
+    }
+
+    /// Indexed events of a vault, most recent first. Pages start at 1
+    pub fn list_events(
+        &self,
+        vault_address: &str,
+        page: u32,
+        page_size: u32,
+    ) -> Result<VaultEventsPage> {
+        let page = page.max(1);
+        let page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        let vault_address = vault_address.to_lowercase();
+
+        let conn = self.conn()?;
+
+        let total: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM vault_events WHERE vault_address = ?1",
+            params![vault_address],
+            |row| row.get(0),
+        )?;
+
+        let mut statement = conn.prepare(
+            "SELECT * FROM vault_events WHERE vault_address = ?1
+            ORDER BY block_number DESC, log_index DESC LIMIT ?2 OFFSET ?3",
+        )?;
+
+        let mut rows = statement.query(params![
+            vault_address,
+            page_size,
+            (page as i64 - 1) * page_size as i64
+        ])?;
+
+        let mut items = Vec::new();
+        while let Some(row) = rows.next()? {
+            items.push(VaultEvent::from_row(row)?);
+        }
+
+        Ok(VaultEventsPage {
+            items,
+            page,
+            page_size,
+            total: total as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use crate::{
+        db::Db,
+        types::{VaultEvent, VaultEventData},
+    };
+
+    fn deposit(block_number: u64, log_index: u64) -> VaultEvent {
+        VaultEvent {
+            vault_address: "0xabc".to_string(),
+            block_number,
+            block_timestamp: Some(1_700_000_000),
+            tx_hash: format!("0x{}", block_number),
+            log_index,
+            event: VaultEventData::Deposit {
+                sender: "0xSender".to_string(),
+                to: "0xReceiver".to_string(),
+                shares: U256::from(10u64).pow(U256::from(30u64)),
+                amount0: U256::from(1),
+                amount1: U256::ZERO,
+            },
+        }
+    }
+
+    #[test]
+    fn test_insert_events_and_cursor() {
+        let db = Db::open(":memory:").unwrap();
+
+        assert_eq!(db.indexer_cursor("0xABC").unwrap(), None);
+
+        db.insert_events("0xABC", &[deposit(10, 0), deposit(10, 1)], 100)
+            .unwrap();
+        // Indexing the same range again keeps one copy of each event
+        db.insert_events("0xABC", &[deposit(10, 1), deposit(150, 0)], 200)
+            .unwrap();
+
+        assert_eq!(db.indexer_cursor("0xabc").unwrap(), Some(200));
+
+        let page = db.list_events("0xabc", 1, 20).unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.items[0], deposit(150, 0));
+        assert_eq!(page.items[1].log_index, 1);
+        assert_eq!(page.items[2].log_index, 0);
+
+        let events = db.account_events("0xRECEIVER").unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].block_number, 10);
+        assert_eq!(events[2], deposit(150, 0));
+        assert!(db.account_events("0xsender").unwrap().is_empty());
+
+        // A transfer is an event of both accounts
+        let transfer = VaultEvent {
+            event: VaultEventData::Transfer {
+                from: "0xReceiver".to_string(),
+                to: "0xOther".to_string(),
+                value: U256::from(1),
+            },
+            ..deposit(300, 0)
+        };
+        db.insert_events("0xABC", std::slice::from_ref(&transfer), 300)
+            .unwrap();
+        assert_eq!(db.account_events("0xreceiver").unwrap().len(), 4);
+        assert_eq!(db.account_events("0xOTHER").unwrap(), vec![transfer]);
+    }
+
+    #[test]
+    fn test_indexer_start() {
+        let db = Db::open(":memory:").unwrap();
+
+        assert!(!db.is_indexed_from_deployment("0xABC").unwrap());
+
+        db.set_indexer_start("0xABC", 100, false).unwrap();
+        assert!(!db.is_indexed_from_deployment("0xabc").unwrap());
+
+        db.set_indexer_start("0xABC", 90, true).unwrap();
+        assert!(db.is_indexed_from_deployment("0xabc").unwrap());
+    }
+}
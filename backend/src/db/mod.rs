@@ -0,0 +1,179 @@
+/*
+    Embedded SQLite store. Migrations are applied in order on open and tracked with the
+    `user_version` pragma, append new ones at the end of MIGRATIONS and never edit applied ones.
+*/
+pub mod audit;
+pub mod circuit_breakers;
+pub mod events;
+pub mod rebalances;
+pub mod snapshots;
+
+use std::{
+    path::Path,
+    sync::{Mutex, MutexGuard},
+};
+
+use color_eyre::eyre::{Result, eyre};
+use rusqlite::Connection;
+use tracing::info;
+
+const MIGRATIONS: &[&str] = &[
+    // 1. Rebalance attempts history
+    "CREATE TABLE rebalances (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        timestamp INTEGER NOT NULL,
+        vault_address TEXT NOT NULL,
+        strategy TEXT NOT NULL,
+        status TEXT NOT NULL,
+        skip_reason TEXT,
+        current_tick INTEGER NOT NULL,
+        price1 REAL NOT NULL,
+        lower_tick INTEGER NOT NULL,
+        upper_tick INTEGER NOT NULL,
+        analysis TEXT,
+        plan TEXT NOT NULL,
+        tx_hash TEXT,
+        error TEXT,
+        position_before TEXT NOT NULL,
+        position_after TEXT
+    );
+    CREATE INDEX idx_rebalances_vault_timestamp ON rebalances (vault_address, timestamp);",
+    // 2. Vault state time series
+    "CREATE TABLE vault_snapshots (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        timestamp INTEGER NOT NULL,
+        vault_address TEXT NOT NULL,
+        tvl0 REAL NOT NULL,
+        tvl1 REAL NOT NULL,
+        tvl_in_token0 REAL NOT NULL,
+        tvl_in_token1 REAL NOT NULL,
+        total_supply REAL NOT NULL,
+        share_price0 REAL NOT NULL,
+        share_price1 REAL NOT NULL,
+        price1 REAL NOT NULL,
+        current_tick INTEGER NOT NULL,
+        lower_tick INTEGER NOT NULL,
+        upper_tick INTEGER NOT NULL,
+        is_active INTEGER NOT NULL,
+        fees0 REAL NOT NULL,
+        fees1 REAL NOT NULL
+    );
+    CREATE INDEX idx_vault_snapshots_vault_timestamp ON vault_snapshots (vault_address, timestamp);",
+    // 3. Cumulative collected fees, for the yield stats
+    "ALTER TABLE vault_snapshots ADD COLUMN collected_fees0 REAL NOT NULL DEFAULT 0;
+    ALTER TABLE vault_snapshots ADD COLUMN collected_fees1 REAL NOT NULL DEFAULT 0;",
+    // 4. Indexed vault events, the last indexed block of each vault and the block its indexing
+    // started from
+    "CREATE TABLE vault_events (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        vault_address TEXT NOT NULL,
+        block_number INTEGER NOT NULL,
+        block_timestamp INTEGER,
+        tx_hash TEXT NOT NULL,
+        log_index INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        account TEXT,
+        receiver TEXT,
+        data TEXT NOT NULL,
+        UNIQUE (vault_address, tx_hash, log_index)
+    );
+    CREATE INDEX idx_vault_events_vault_block ON vault_events (vault_address, block_number);
+    CREATE INDEX idx_vault_events_account ON vault_events (account, vault_address);
+    CREATE INDEX idx_vault_events_receiver ON vault_events (receiver, vault_address);
+    CREATE TABLE indexer_cursors (
+        vault_address TEXT PRIMARY KEY,
+        last_block INTEGER NOT NULL
+    );
+    CREATE TABLE indexer_starts (
+        vault_address TEXT PRIMARY KEY,
+        start_block INTEGER NOT NULL,
+        is_deployment_block INTEGER NOT NULL
+    );",
+    // 5. Rebalance costs and the circuit breaker of each vault
+    "ALTER TABLE rebalances ADD COLUMN hbar_spent REAL NOT NULL DEFAULT 0;
+    CREATE TABLE circuit_breakers (
+        vault_address TEXT PRIMARY KEY,
+        consecutive_failures INTEGER NOT NULL,
+        tripped_at INTEGER,
+        reason TEXT
+    );",
+    // 6. Audit log of the admin requests
+    "CREATE TABLE admin_audit_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        timestamp INTEGER NOT NULL,
+        principal TEXT,
+        method TEXT NOT NULL,
+        path TEXT NOT NULL,
+        status INTEGER NOT NULL,
+        error TEXT
+    );
+    CREATE INDEX idx_admin_audit_log_timestamp ON admin_audit_log (timestamp);",
+];
+
+pub struct Db {
+    conn: Mutex<Connection>,
+}
+
+impl Db {
+    /// Open (or create) the database file and apply the pending migrations
+    pub fn open(path: &str) -> Result<Self> {
+        if let Some(parent) = Path::new(path).parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+
+        let db = Self {
+            conn: Mutex::new(conn),
+        };
+        db.migrate()?;
+
+        Ok(db)
+    }
+
+    fn migrate(&self) -> Result<()> {
+        let mut conn = self.conn()?;
+
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", index as i64 + 1)?;
+            tx.commit()?;
+
+            info!("Applied database migration {}", index + 1);
+        }
+
+        Ok(())
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|e| eyre!("Database connection lock poisoned: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Db, MIGRATIONS};
+
+    #[test]
+    fn test_migrations_are_applied_once() {
+        let db = Db::open(":memory:").unwrap();
+
+        let version: i64 = db
+            .conn()
+            .unwrap()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version as usize, MIGRATIONS.len());
+
+        // Re-running does nothing
+        db.migrate().unwrap();
+    }
+}
@@ -0,0 +1,393 @@
+use color_eyre::eyre::{Result, eyre};
+use rusqlite::{Row, params};
+
+use crate::{
+    db::Db,
+    types::{Position, RebalanceHistoryPage, RebalancePlan, RebalanceRecord, RebalanceStatus},
+};
+
+pub const DEFAULT_PAGE_SIZE: u32 = 20;
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+/// Statuses of the attempts that sent (or tried to send) a rebalance transaction
+const EXECUTED_STATUSES: &str = "'success', 'failed', 'error'";
+
+impl RebalanceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RebalanceStatus::Skipped => "skipped",
+            RebalanceStatus::DryRun => "dry_run",
+            RebalanceStatus::Success => "success",
+            RebalanceStatus::Failed => "failed",
+            RebalanceStatus::Error => "error",
+            RebalanceStatus::Blocked => "blocked",
+        }
+    }
+
+    fn parse(status: &str) -> Result<Self> {
+        match status {
+            "skipped" => Ok(RebalanceStatus::Skipped),
+            "dry_run" => Ok(RebalanceStatus::DryRun),
+            "success" => Ok(RebalanceStatus::Success),
+            "failed" => Ok(RebalanceStatus::Failed),
+            "error" => Ok(RebalanceStatus::Error),
+            "blocked" => Ok(RebalanceStatus::Blocked),
+            _ => Err(eyre!("Unknown rebalance status {}", status)),
+        }
+    }
+}
+
+impl RebalanceRecord {
+    /// Record of a plan, the tx hash, error and position after are filled by the caller
+    pub fn from_plan(
+        plan: &RebalancePlan,
+        status: RebalanceStatus,
+        position_before: &Position,
+    ) -> Self {
+        Self {
+            id: 0,
+            timestamp: chrono::Utc::now().timestamp(),
+            vault_address: plan.vault_address.clone(),
+            strategy: plan.strategy.clone(),
+            status,
+            skip_reason: plan
+                .skip_reason
+                .clone()
+                .or_else(|| plan.blocked_reason.clone()),
+            current_tick: plan.current_tick,
+            price1: plan.price1,
+            lower_tick: plan.lower_tick,
+            upper_tick: plan.upper_tick,
+            analysis: plan.analysis.clone(),
+            plan: plan.clone(),
+            tx_hash: None,
+            error: None,
+            position_before: position_before.clone(),
+            position_after: None,
+            hbar_spent: 0.0,
+        }
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        let status: String = row.get("status")?;
+        let plan: String = row.get("plan")?;
+        let position_before: String = row.get("position_before")?;
+        let position_after: Option<String> = row.get("position_after")?;
+
+        Ok(Self {
+            id: row.get("id")?,
+            timestamp: row.get("timestamp")?,
+            vault_address: row.get("vault_address")?,
+            strategy: row.get("strategy")?,
+            status: RebalanceStatus::parse(&status)?,
+            skip_reason: row.get("skip_reason")?,
+            current_tick: row.get("current_tick")?,
+            price1: row.get("price1")?,
+            lower_tick: row.get("lower_tick")?,
+            upper_tick: row.get("upper_tick")?,
+            analysis: row.get("analysis")?,
+            plan: serde_json::from_str(&plan)?,
+            tx_hash: row.get("tx_hash")?,
+            error: row.get("error")?,
+            position_before: serde_json::from_str(&position_before)?,
+            position_after: position_after
+                .map(|position| serde_json::from_str(&position))
+                .transpose()?,
+            hbar_spent: row.get("hbar_spent")?,
+        })
+    }
+}
+
+impl Db {
+    /// Store a rebalance attempt, returns its id
+    pub fn insert_rebalance(&self, record: &RebalanceRecord) -> Result<i64> {
+        let conn = self.conn()?;
+
+        conn.execute(
+            "INSERT INTO rebalances (
+                timestamp, vault_address, strategy, status, skip_reason, current_tick, price1,
+                lower_tick, upper_tick, analysis, plan, tx_hash, error, position_before, position_after,
+                hbar_spent
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            params![
+                record.timestamp,
+                record.vault_address.to_lowercase(),
+                record.strategy,
+                record.status.as_str(),
+                record.skip_reason,
+                record.current_tick,
+                record.price1,
+                record.lower_tick,
+                record.upper_tick,
+                record.analysis,
+                serde_json::to_string(&record.plan)?,
+                record.tx_hash,
+                record.error,
+                serde_json::to_string(&record.position_before)?,
+                record
+                    .position_after
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?,
+                record.hbar_spent,
+            ],
+        )?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Delete the attempts of a vault that sent no transaction (skipped, dry run, blocked) older
+    /// than `before`, returns how many were deleted
+    pub fn prune_unsent_rebalances(&self, vault_address: &str, before: i64) -> Result<usize> {
+        let conn = self.conn()?;
+
+        let deleted = conn.execute(
+            &format!(
+                "DELETE FROM rebalances
+                WHERE vault_address = ?1 AND timestamp < ?2 AND status NOT IN ({})",
+                EXECUTED_STATUSES
+            ),
+            params![vault_address.to_lowercase(), before],
+        )?;
+
+        Ok(deleted)
+    }
+
+    /// Rebalance transactions of a vault (sent or attempted) since `from`: their count and the
+    /// HBAR they spent
+    pub fn rebalance_spending(&self, vault_address: &str, from: i64) -> Result<(u64, f64)> {
+        let conn = self.conn()?;
+
+        let (count, hbar_spent): (i64, f64) = conn.query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(hbar_spent), 0) FROM rebalances
+                WHERE vault_address = ?1 AND timestamp >= ?2 AND status IN ({})",
+                EXECUTED_STATUSES
+            ),
+            params![vault_address.to_lowercase(), from],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        Ok((count as u64, hbar_spent))
+    }
+
+    /// Timestamp of the last rebalance transaction of a vault (sent or attempted)
+    pub fn last_rebalance_timestamp(&self, vault_address: &str) -> Result<Option<i64>> {
+        let conn = self.conn()?;
+
+        let timestamp: Option<i64> = conn.query_row(
+            &format!(
+                "SELECT MAX(timestamp) FROM rebalances
+                WHERE vault_address = ?1 AND status IN ({})",
+                EXECUTED_STATUSES
+            ),
+            params![vault_address.to_lowercase()],
+            |row| row.get(0),
+        )?;
+
+        Ok(timestamp)
+    }
+
+    /// Number of rebalance attempts of a vault with the given status between `from` and `to`
+    pub fn count_rebalances(
+        &self,
+        vault_address: &str,
+        status: RebalanceStatus,
+        from: i64,
+        to: i64,
+    ) -> Result<u64> {
+        let conn = self.conn()?;
+
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM rebalances
+            WHERE vault_address = ?1 AND status = ?2 AND timestamp BETWEEN ?3 AND ?4",
+            params![vault_address.to_lowercase(), status.as_str(), from, to],
+            |row| row.get(0),
+        )?;
+
+        Ok(count as u64)
+    }
+
+    /// Rebalance attempts of a vault, most recent first. Pages start at 1
+    pub fn list_rebalances(
+        &self,
+        vault_address: &str,
+        page: u32,
+        page_size: u32,
+    ) -> Result<RebalanceHistoryPage> {
+        let page = page.max(1);
+        let page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        let vault_address = vault_address.to_lowercase();
+
+        let conn = self.conn()?;
+
+        let total: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM rebalances WHERE vault_address = ?1",
+            params![vault_address],
+            |row| row.get(0),
+        )?;
+
+        let mut statement = conn.prepare(
+            "SELECT * FROM rebalances WHERE vault_address = ?1
+            ORDER BY timestamp DESC, id DESC LIMIT ?2 OFFSET ?3",
+        )?;
+
+        let mut rows = statement.query(params![
+            vault_address,
+            page_size,
+            (page as i64 - 1) * page_size as i64
+        ])?;
+
+        let mut items = Vec::new();
+        while let Some(row) = rows.next()? {
+            items.push(RebalanceRecord::from_row(row)?);
+        }
+
+        Ok(RebalanceHistoryPage {
+            items,
+            page,
+            page_size,
+            total: total as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::Db,
+        types::{Position, RebalancePlan, RebalanceRecord, RebalanceStatus},
+    };
+
+    fn plan(vault_address: &str, lower_tick: i32) -> RebalancePlan {
+        RebalancePlan {
+            vault_address: vault_address.to_string(),
+            strategy: "basic".to_string(),
+            rebalance_required: true,
+            skip_reason: None,
+            blocked_reason: None,
+            current_tick: 10,
+            current_lower_tick: -60,
+            current_upper_tick: 60,
+            lower_tick,
+            upper_tick: lower_tick + 120,
+            price1: 1.001,
+            lower_price1: 0.99,
+            upper_price1: 1.01,
+            swap: None,
+            expected_liquidity: u128::MAX,
+            expected_amount0: 1.5,
+            expected_amount1: 2.5,
+            analysis: Some("Price is trending up".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_insert_and_list_rebalances() {
+        let db = Db::open(":memory:").unwrap();
+
+        for lower_tick in 0..5 {
+            let mut record = RebalanceRecord::from_plan(
+                &plan("0xABC", lower_tick),
+                RebalanceStatus::Success,
+                &Position::default(),
+            );
+            record.tx_hash = Some(format!("0x{}", lower_tick));
+            record.position_after = Some(Position {
+                liquidity: 42,
+                ..Position::default()
+            });
+            db.insert_rebalance(&record).unwrap();
+        }
+        db.insert_rebalance(&RebalanceRecord::from_plan(
+            &plan("0xdef", 0),
+            RebalanceStatus::Skipped,
+            &Position::default(),
+        ))
+        .unwrap();
+
+        // Address case is ignored, most recent first
+        let page = db.list_rebalances("0xabc", 1, 2).unwrap();
+        assert_eq!(page.total, 5);
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0].lower_tick, 4);
+        assert_eq!(page.items[1].lower_tick, 3);
+        assert_eq!(page.items[0].status, RebalanceStatus::Success);
+        assert_eq!(page.items[0].tx_hash.as_deref(), Some("0x4"));
+        assert_eq!(page.items[0].plan.expected_liquidity, u128::MAX);
+        assert_eq!(page.items[0].position_after.as_ref().unwrap().liquidity, 42);
+
+        let page = db.list_rebalances("0xABC", 3, 2).unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].lower_tick, 0);
+
+        let now = chrono::Utc::now().timestamp();
+        assert_eq!(
+            db.count_rebalances("0xAbC", RebalanceStatus::Success, now - 60, now + 60)
+                .unwrap(),
+            5
+        );
+        assert_eq!(
+            db.count_rebalances("0xabc", RebalanceStatus::Success, now + 60, now + 120)
+                .unwrap(),
+            0
+        );
+
+        let page = db.list_rebalances("0xdef", 1, 20).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items[0].status, RebalanceStatus::Skipped);
+        assert!(page.items[0].position_after.is_none());
+    }
+
+    #[test]
+    fn test_rebalance_spending() {
+        let db = Db::open(":memory:").unwrap();
+        let now = chrono::Utc::now().timestamp();
+
+        assert_eq!(db.rebalance_spending("0xabc", now - 60).unwrap(), (0, 0.0));
+        assert_eq!(db.last_rebalance_timestamp("0xabc").unwrap(), None);
+
+        for (status, hbar_spent) in [
+            (RebalanceStatus::Success, 1.5),
+            (RebalanceStatus::Failed, 0.5),
+            (RebalanceStatus::Skipped, 0.0),
+            (RebalanceStatus::Blocked, 0.0),
+        ] {
+            let mut record =
+                RebalanceRecord::from_plan(&plan("0xABC", 0), status, &Position::default());
+            record.hbar_spent = hbar_spent;
+            db.insert_rebalance(&record).unwrap();
+        }
+
+        // Only the attempts sending a transaction count
+        assert_eq!(db.rebalance_spending("0xAbc", now - 60).unwrap(), (2, 2.0));
+        assert_eq!(db.rebalance_spending("0xabc", now + 60).unwrap(), (0, 0.0));
+        assert!(db.last_rebalance_timestamp("0xabc").unwrap().unwrap() >= now);
+    }
+
+    #[test]
+    fn test_prune_unsent_rebalances() {
+        let db = Db::open(":memory:").unwrap();
+        let now = chrono::Utc::now().timestamp();
+
+        for (status, timestamp) in [
+            (RebalanceStatus::Skipped, now - 1_000),
+            (RebalanceStatus::Blocked, now - 1_000),
+            (RebalanceStatus::Success, now - 1_000),
+            (RebalanceStatus::DryRun, now),
+        ] {
+            let mut record =
+                RebalanceRecord::from_plan(&plan("0xABC", 0), status, &Position::default());
+            record.timestamp = timestamp;
+            db.insert_rebalance(&record).unwrap();
+        }
+
+        // The old attempts without transaction only
+        assert_eq!(db.prune_unsent_rebalances("0xAbc", now - 10).unwrap(), 2);
+
+        let page = db.list_rebalances("0xabc", 1, 20).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.items[0].status, RebalanceStatus::DryRun);
+        assert_eq!(page.items[1].status, RebalanceStatus::Success);
+    }
+}
@@ -0,0 +1,268 @@
+use color_eyre::eyre::Result;
+use rusqlite::{Row, params};
+
+use crate::{
+    db::Db,
+    types::{SnapshotInterval, VaultDetails, VaultHistory, VaultSnapshot},
+};
+
+pub const DEFAULT_HISTORY_SECONDS: i64 = 7 * 24 * 60 * 60;
+/// Upper bound of the points returned by one history query
+pub const MAX_HISTORY_POINTS: u32 = 5_000;
+
+impl SnapshotInterval {
+    /// Bucket size in seconds, None for raw snapshots
+    pub fn seconds(&self) -> Option<i64> {
+        match self {
+            SnapshotInterval::Raw => None,
+            SnapshotInterval::FiveMinutes => Some(5 * 60),
+            SnapshotInterval::OneHour => Some(60 * 60),
+            SnapshotInterval::OneDay => Some(24 * 60 * 60),
+        }
+    }
+}
+
+impl VaultSnapshot {
+    /// Snapshot of the vault live data, the vault must be updated by the caller
+    pub fn from_vault(vault: &VaultDetails, timestamp: i64) -> Self {
+        let price1 = vault.pool.price1;
+        let tvl_in_token1 = vault.tvl.tvl0 * price1 + vault.tvl.tvl1;
+        let tvl_in_token0 = if price1 > 0.0 {
+            tvl_in_token1 / price1
+        } else {
+            vault.tvl.tvl0
+        };
+
+        let (share_price0, share_price1) = if vault.total_supply > 0.0 {
+            (
+                tvl_in_token0 / vault.total_supply,
+                tvl_in_token1 / vault.total_supply,
+            )
+        } else {
+            (0.0, 0.0)
+        };
+
+        Self {
+            timestamp,
+            vault_address: vault.address.to_lowercase(),
+            tvl0: vault.tvl.tvl0,
+            tvl1: vault.tvl.tvl1,
+            tvl_in_token0,
+            tvl_in_token1,
+            total_supply: vault.total_supply,
+            share_price0,
+            share_price1,
+            price1,
+            current_tick: vault.pool.current_tick,
+            lower_tick: vault.lower_tick,
+            upper_tick: vault.upper_tick,
+            is_active: vault.is_active,
+            fees0: vault.position.fees0,
+            fees1: vault.position.fees1,
+            collected_fees0: vault.fees.collected0,
+            collected_fees1: vault.fees.collected1,
+        }
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            timestamp: row.get("timestamp")?,
+            vault_address: row.get("vault_address")?,
+            tvl0: row.get("tvl0")?,
+            tvl1: row.get("tvl1")?,
+            tvl_in_token0: row.get("tvl_in_token0")?,
+            tvl_in_token1: row.get("tvl_in_token1")?,
+            total_supply: row.get("total_supply")?,
+            share_price0: row.get("share_price0")?,
+            share_price1: row.get("share_price1")?,
+            price1: row.get("price1")?,
+            current_tick: row.get("current_tick")?,
+            lower_tick: row.get("lower_tick")?,
+            upper_tick: row.get("upper_tick")?,
+            is_active: row.get("is_active")?,
+            fees0: row.get("fees0")?,
+            fees1: row.get("fees1")?,
+            collected_fees0: row.get("collected_fees0")?,
+            collected_fees1: row.get("collected_fees1")?,
+        })
+    }
+}
+
+impl Db {
+    pub fn insert_snapshot(&self, snapshot: &VaultSnapshot) -> Result<()> {
+        let conn = self.conn()?;
+
+        conn.execute(
+            "INSERT INTO vault_snapshots (
+                timestamp, vault_address, tvl0, tvl1, tvl_in_token0, tvl_in_token1, total_supply,
+                share_price0, share_price1, price1, current_tick, lower_tick, upper_tick, is_active,
+                fees0, fees1, collected_fees0, collected_fees1
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18
+            )",
+            params![
+                snapshot.timestamp,
+                snapshot.vault_address.to_lowercase(),
+                snapshot.tvl0,
+                snapshot.tvl1,
+                snapshot.tvl_in_token0,
+                snapshot.tvl_in_token1,
+                snapshot.total_supply,
+                snapshot.share_price0,
+                snapshot.share_price1,
+                snapshot.price1,
+                snapshot.current_tick,
+                snapshot.lower_tick,
+                snapshot.upper_tick,
+                snapshot.is_active,
+                snapshot.fees0,
+                snapshot.fees1,
+                snapshot.collected_fees0,
+                snapshot.collected_fees1,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Snapshots of a vault between `from` and `to` (inclusive), oldest first. Above
+    /// MAX_HISTORY_POINTS the most recent ones are returned.
+    /// With a bucketed interval only the last snapshot of each bucket is kept
+    pub fn vault_history(
+        &self,
+        vault_address: &str,
+        from: i64,
+        to: i64,
+        interval: SnapshotInterval,
+    ) -> Result<VaultHistory> {
+        let items = self.query_snapshots(
+            vault_address,
+            from,
+            to,
+            interval.seconds(),
+            MAX_HISTORY_POINTS,
+        )?;
+
+        Ok(VaultHistory {
+            vault_address: vault_address.to_lowercase(),
+            from,
+            to,
+            interval,
+            items,
+        })
+    }
+
+    /// Every snapshot of a vault between `from` and `to` (inclusive), oldest first
+    pub fn vault_snapshots(
+        &self,
+        vault_address: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<VaultSnapshot>> {
+        self.query_snapshots(vault_address, from, to, None, u32::MAX)
+    }
+
+    fn query_snapshots(
+        &self,
+        vault_address: &str,
+        from: i64,
+        to: i64,
+        bucket_seconds: Option<i64>,
+        limit: u32,
+    ) -> Result<Vec<VaultSnapshot>> {
+        let conn = self.conn()?;
+
+        let mut statement = conn.prepare(
+            "SELECT * FROM vault_snapshots WHERE id IN (
+                SELECT MAX(id) FROM vault_snapshots
+                WHERE vault_address = ?1 AND timestamp BETWEEN ?2 AND ?3
+                GROUP BY CASE WHEN ?4 IS NULL THEN id ELSE timestamp / ?4 END
+            )
+            ORDER BY timestamp DESC, id DESC LIMIT ?5",
+        )?;
+
+        let mut rows = statement.query(params![
+            vault_address.to_lowercase(),
+            from,
+            to,
+            bucket_seconds,
+            limit
+        ])?;
+
+        let mut items = Vec::new();
+        while let Some(row) = rows.next()? {
+            items.push(VaultSnapshot::from_row(row)?);
+        }
+        // The limit keeps the most recent snapshots, returned oldest first
+        items.reverse();
+
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::Db,
+        types::{SnapshotInterval, VaultSnapshot},
+    };
+
+    fn snapshot(vault_address: &str, timestamp: i64) -> VaultSnapshot {
+        VaultSnapshot {
+            timestamp,
+            vault_address: vault_address.to_string(),
+            tvl0: 10.0,
+            tvl1: 20.0,
+            tvl_in_token0: 20.0,
+            tvl_in_token1: 40.0,
+            total_supply: 4.0,
+            share_price0: 5.0,
+            share_price1: 10.0,
+            price1: 2.0,
+            current_tick: timestamp as i32,
+            lower_tick: -60,
+            upper_tick: 60,
+            is_active: true,
+            fees0: 0.1,
+            fees1: 0.2,
+            collected_fees0: 1.0,
+            collected_fees1: 2.0,
+        }
+    }
+
+    #[test]
+    fn test_vault_history_buckets() {
+        let db = Db::open(":memory:").unwrap();
+
+        // One snapshot every 20 minutes over 3 hours
+        for timestamp in (0..3 * 3600).step_by(1200) {
+            db.insert_snapshot(&snapshot("0xABC", timestamp)).unwrap();
+        }
+        db.insert_snapshot(&snapshot("0xdef", 0)).unwrap();
+
+        let history = db
+            .vault_history("0xabc", 0, 3 * 3600, SnapshotInterval::Raw)
+            .unwrap();
+        assert_eq!(history.items.len(), 9);
+        assert_eq!(history.items[0], snapshot("0xabc", 0));
+
+        // Last snapshot of each hour
+        let history = db
+            .vault_history("0xabc", 0, 3 * 3600, SnapshotInterval::OneHour)
+            .unwrap();
+        let timestamps: Vec<i64> = history.items.iter().map(|s| s.timestamp).collect();
+        assert_eq!(timestamps, vec![2400, 6000, 9600]);
+
+        let history = db
+            .vault_history("0xABC", 3600, 7200, SnapshotInterval::Raw)
+            .unwrap();
+        assert_eq!(history.items.len(), 4);
+        assert_eq!(history.items[0].timestamp, 3600);
+        assert_eq!(history.items[3].timestamp, 7200);
+
+        // Above the limit the most recent snapshots are kept
+        let items = db.query_snapshots("0xabc", 0, 3 * 3600, None, 2).unwrap();
+        let timestamps: Vec<i64> = items.iter().map(|s| s.timestamp).collect();
+        assert_eq!(timestamps, vec![8400, 9600]);
+    }
+}
@@ -0,0 +1,170 @@
+/*
+    Typed errors of the backend. Most functions still return an `eyre::Report`, the typed error
+    is recovered from it (`From<Report>`) where the kind of failure matters: the HTTP status code
+    of the API errors and the severity filtering the alert emails.
+*/
+use std::fmt;
+
+use alloy::transports::{RpcError, TransportErrorKind};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    core::preflight::TransactionError, helpers::math::uniswap_v3::error::UniswapV3MathError,
+};
+
+#[derive(Error, Debug)]
+pub enum YielderaError {
+    #[error("Math error: {0}")]
+    Math(#[from] UniswapV3MathError),
+    #[error("RPC error: {0}")]
+    Rpc(String),
+    #[error(transparent)]
+    Transaction(#[from] TransactionError),
+    #[error("Strategy {strategy} failed: {message}")]
+    Strategy { strategy: String, message: String },
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+    #[error("Notification failed: {0}")]
+    Notification(String),
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("No managed vault with address {0}")]
+    VaultNotFound(String),
+    /// An action refused by a safety check (TWAP deviation, guardrails)
+    #[error("Blocked: {0}")]
+    Blocked(String),
+    #[error("{0:#}")]
+    Other(color_eyre::Report),
+}
+
+/// How bad an error is, the alert emails are only sent from the vault `alert_min_severity`
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Expected outcome, nothing to do
+    Info,
+    /// Transient or local failure, the next evaluation may succeed
+    #[default]
+    Warning,
+    /// Funds spent for nothing, a broken configuration or an unknown failure
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+impl YielderaError {
+    pub fn severity(&self) -> Severity {
+        match self {
+            YielderaError::VaultNotFound(_) | YielderaError::Blocked(_) => Severity::Info,
+            YielderaError::Math(_)
+            | YielderaError::Rpc(_)
+            | YielderaError::Strategy { .. }
+            | YielderaError::Notification(_) => Severity::Warning,
+            YielderaError::Transaction(
+                TransactionError::Reverted { .. } | TransactionError::NotMined { .. },
+            ) => Severity::Critical,
+            YielderaError::Transaction(_) => Severity::Warning,
+            YielderaError::Config(_) | YielderaError::Database(_) | YielderaError::Other(_) => {
+                Severity::Critical
+            }
+        }
+    }
+}
+
+impl From<RpcError<TransportErrorKind>> for YielderaError {
+    fn from(e: RpcError<TransportErrorKind>) -> Self {
+        YielderaError::Rpc(e.to_string())
+    }
+}
+
+impl From<alloy::contract::Error> for YielderaError {
+    fn from(e: alloy::contract::Error) -> Self {
+        match TransactionError::from(e) {
+            TransactionError::Rpc(e) => YielderaError::Rpc(e),
+            e => YielderaError::Transaction(e),
+        }
+    }
+}
+
+/// Recover the typed error of a report, `Other` when it only holds a message
+impl From<color_eyre::Report> for YielderaError {
+    fn from(report: color_eyre::Report) -> Self {
+        let report = match report.downcast::<YielderaError>() {
+            Ok(e) => return e,
+            Err(report) => report,
+        };
+        let report = match report.downcast::<TransactionError>() {
+            Ok(e) => return e.into(),
+            Err(report) => report,
+        };
+        let report = match report.downcast::<UniswapV3MathError>() {
+            Ok(e) => return e.into(),
+            Err(report) => report,
+        };
+        let report = match report.downcast::<alloy::contract::Error>() {
+            Ok(e) => return e.into(),
+            Err(report) => report,
+        };
+        let report = match report.downcast::<RpcError<TransportErrorKind>>() {
+            Ok(e) => return e.into(),
+            Err(report) => report,
+        };
+        match report.downcast::<rusqlite::Error>() {
+            Ok(e) => e.into(),
+            Err(report) => YielderaError::Other(report),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::{WrapErr, eyre};
+
+    use crate::{
+        core::preflight::TransactionError, helpers::math::uniswap_v3::error::UniswapV3MathError,
+    };
+
+    use super::{Severity, YielderaError};
+
+    #[test]
+    fn test_error_from_report() {
+        let report = color_eyre::Report::new(UniswapV3MathError::LiquidityIsZero);
+        assert!(matches!(
+            YielderaError::from(report),
+            YielderaError::Math(UniswapV3MathError::LiquidityIsZero)
+        ));
+
+        // Typed errors are found behind a context
+        let report = Err::<(), _>(TransactionError::Reverted {
+            tx_hash: "0x1".to_string(),
+            gas_used: 21_000,
+        })
+        .wrap_err("Rebalance failed")
+        .unwrap_err();
+        let error = YielderaError::from(report);
+        assert!(matches!(
+            error,
+            YielderaError::Transaction(TransactionError::Reverted { .. })
+        ));
+        assert_eq!(error.severity(), Severity::Critical);
+
+        let report = color_eyre::Report::new(YielderaError::Blocked("TWAP".to_string()));
+        assert_eq!(YielderaError::from(report).severity(), Severity::Info);
+
+        let error = YielderaError::from(eyre!("Something failed"));
+        assert!(matches!(error, YielderaError::Other(_)));
+        assert_eq!(error.to_string(), "Something failed");
+        assert_eq!(error.severity(), Severity::Critical);
+
+        assert!(Severity::Info < Severity::Warning && Severity::Warning < Severity::Critical);
+    }
+}
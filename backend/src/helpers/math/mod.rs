@@ -1,4 +1,6 @@
+pub mod swap_to_ratio;
 pub mod uniswap_v3;
+pub mod vault_shares;
 
 use alloy::primitives::U256;
 use color_eyre::eyre::Result;
@@ -20,6 +22,12 @@ pub fn tick_to_price(tick: i32, token0_decimals: u8, token1_decimals: u8) -> Res
     Ok(price)
 }
 
+/// A position earns fees while the pool tick is in `[lower_tick, upper_tick)`, as its liquidity
+/// is active in the pool (`_updatePosition`)
+pub fn is_tick_in_range(tick: i32, lower_tick: i32, upper_tick: i32) -> bool {
+    lower_tick <= tick && tick < upper_tick
+}
+
 pub fn align_to_pool_tick_spacing(tick: i32, spacing: i32) -> i32 {
     tick - (tick % spacing)
 }
@@ -63,3 +71,18 @@ pub fn estimate_amount1_given_amount0(
 
     Ok(amount1)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::is_tick_in_range;
+
+    #[test]
+    fn test_is_tick_in_range() {
+        assert!(is_tick_in_range(-60, -60, 60));
+        assert!(is_tick_in_range(59, -60, 60));
+
+        // The upper tick is out of range, the position is all token1
+        assert!(!is_tick_in_range(60, -60, 60));
+        assert!(!is_tick_in_range(-61, -60, 60));
+    }
+}
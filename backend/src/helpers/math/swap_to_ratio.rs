@@ -0,0 +1,360 @@
+/*
+    Solver for the swap to do before minting a position: finds the exact output amount that leaves
+    the balances in the ratio of the target range, at the price the pool ends at after the swap.
+    Pool fees and price impact come from the quote function (usually PoolState::simulate_exact_output).
+*/
+use alloy::primitives::U256;
+use color_eyre::eyre::Result;
+
+use crate::helpers::math::uniswap_v3::{
+    U256_1,
+    liquidity_math::{
+        get_liquidity_for_amount0, get_liquidity_for_amount1, get_liquidity_for_amounts,
+    },
+};
+
+/// Result of an exact output swap quote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapQuote {
+    /// Amount of token in paid to the pool, fee included
+    pub amount_in: U256,
+    pub sqrt_price_x96_after: U256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapToRatio {
+    pub zero_for_one: bool,
+    /// Zero when the balances are already in the range ratio
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub sqrt_price_x96_after: U256,
+    pub balance0_after: U256,
+    pub balance1_after: U256,
+    /// Liquidity that can be minted with the balances after the swap
+    pub liquidity: u128,
+}
+
+/// Find the exact output swap maximizing the liquidity minted in [sqrt_ratio_lower_x96, sqrt_ratio_upper_x96].
+/// `quote_exact_output(amount_out, zero_for_one)` returns the amount in and the price after the swap,
+/// an error from it is treated as an amount the pool can't fill.
+pub fn solve_swap_to_ratio<F>(
+    balance0: U256,
+    balance1: U256,
+    sqrt_price_x96: U256,
+    sqrt_ratio_lower_x96: U256,
+    sqrt_ratio_upper_x96: U256,
+    quote_exact_output: F,
+) -> Result<SwapToRatio>
+where
+    F: Fn(U256, bool) -> Result<SwapQuote>,
+{
+    // Direction: swap the token that is in excess for the range at the current price
+    let zero_for_one = if sqrt_price_x96 <= sqrt_ratio_lower_x96 {
+        false
+    } else if sqrt_price_x96 >= sqrt_ratio_upper_x96 {
+        true
+    } else {
+        let liquidity0 = get_liquidity_for_amount0(sqrt_price_x96, sqrt_ratio_upper_x96, balance0)?;
+        let liquidity1 = get_liquidity_for_amount1(sqrt_ratio_lower_x96, sqrt_price_x96, balance1)?;
+        liquidity0 > liquidity1
+    };
+
+    let no_swap = SwapToRatio {
+        zero_for_one,
+        amount_in: U256::ZERO,
+        amount_out: U256::ZERO,
+        sqrt_price_x96_after: sqrt_price_x96,
+        balance0_after: balance0,
+        balance1_after: balance1,
+        liquidity: get_liquidity_for_amounts(
+            sqrt_price_x96,
+            sqrt_ratio_lower_x96,
+            sqrt_ratio_upper_x96,
+            balance0,
+            balance1,
+        )?,
+    };
+
+    let evaluate = |amount_out: U256| -> Result<Option<(SwapToRatio, bool)>> {
+        let Ok(quote) = quote_exact_output(amount_out, zero_for_one) else {
+            return Ok(None);
+        };
+
+        let (balance0_after, balance1_after) = if zero_for_one {
+            if quote.amount_in > balance0 {
+                return Ok(None);
+            }
+            (balance0 - quote.amount_in, balance1 + amount_out)
+        } else {
+            if quote.amount_in > balance1 {
+                return Ok(None);
+            }
+            (balance0 + amount_out, balance1 - quote.amount_in)
+        };
+
+        let sqrt_price_x96_after = quote.sqrt_price_x96_after;
+
+        // Is the token we sell still in excess at the new price
+        let needs_more = if sqrt_price_x96_after <= sqrt_ratio_lower_x96 {
+            !zero_for_one
+        } else if sqrt_price_x96_after >= sqrt_ratio_upper_x96 {
+            zero_for_one
+        } else {
+            let liquidity0 = get_liquidity_for_amount0(
+                sqrt_price_x96_after,
+                sqrt_ratio_upper_x96,
+                balance0_after,
+            )?;
+            let liquidity1 = get_liquidity_for_amount1(
+                sqrt_ratio_lower_x96,
+                sqrt_price_x96_after,
+                balance1_after,
+            )?;
+            if zero_for_one {
+                liquidity0 >= liquidity1
+            } else {
+                liquidity1 >= liquidity0
+            }
+        };
+
+        let liquidity = get_liquidity_for_amounts(
+            sqrt_price_x96_after,
+            sqrt_ratio_lower_x96,
+            sqrt_ratio_upper_x96,
+            balance0_after,
+            balance1_after,
+        )?;
+
+        Ok(Some((
+            SwapToRatio {
+                zero_for_one,
+                amount_in: quote.amount_in,
+                amount_out,
+                sqrt_price_x96_after,
+                balance0_after,
+                balance1_after,
+                liquidity,
+            },
+            needs_more,
+        )))
+    };
+
+    // The excess shrinks as the output grows, so search the largest output still leaving an excess:
+    // grow an upper bound first then bisect
+    let needs_more =
+        |amount_out: U256| -> Result<bool> { Ok(matches!(evaluate(amount_out)?, Some((_, true)))) };
+
+    let mut low = U256::ZERO;
+    let mut high = U256_1;
+
+    while needs_more(high)? {
+        low = high;
+        if high.bit(255) {
+            break;
+        }
+        high <<= 1;
+    }
+
+    while high - low > U256_1 {
+        let mid = low + ((high - low) >> 1);
+        if needs_more(mid)? {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    // The optimum is the last output leaving an excess or one unit more, unless fees make any swap worse
+    let mut best = no_swap;
+    for amount_out in [low, low + U256_1] {
+        if amount_out.is_zero() {
+            continue;
+        }
+        if let Some((solution, _)) = evaluate(amount_out)?
+            && solution.liquidity > best.liquidity
+        {
+            best = solution;
+        }
+    }
+
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{I256, U256};
+    use color_eyre::eyre::{Result, eyre};
+
+    use super::{SwapQuote, solve_swap_to_ratio};
+    use crate::helpers::math::uniswap_v3::{
+        U256_1,
+        liquidity_math::get_liquidity_for_amounts,
+        swap_math::compute_swap_step,
+        tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO, get_sqrt_ratio_at_tick},
+    };
+
+    const POOL_LIQUIDITY: u128 = 200_000;
+    const FEE: u32 = 3000;
+
+    // Pool with the same liquidity at every price
+    fn quote(sqrt_price_x96: U256, amount_out: U256, zero_for_one: bool) -> Result<SwapQuote> {
+        let sqrt_price_limit_x96 = if zero_for_one {
+            MIN_SQRT_RATIO + U256_1
+        } else {
+            MAX_SQRT_RATIO - U256_1
+        };
+        let (sqrt_price_x96_after, amount_in, step_amount_out, fee_amount) = compute_swap_step(
+            sqrt_price_x96,
+            sqrt_price_limit_x96,
+            POOL_LIQUIDITY,
+            -I256::from_raw(amount_out),
+            FEE,
+        )?;
+        if step_amount_out < amount_out {
+            return Err(eyre!("Not enough liquidity"));
+        }
+        Ok(SwapQuote {
+            amount_in: amount_in + fee_amount,
+            sqrt_price_x96_after,
+        })
+    }
+
+    // Max liquidity over every possible output amount
+    fn brute_force(
+        balance0: U256,
+        balance1: U256,
+        sqrt_price_x96: U256,
+        sqrt_ratio_lower_x96: U256,
+        sqrt_ratio_upper_x96: U256,
+        zero_for_one: bool,
+    ) -> u128 {
+        let mut best = get_liquidity_for_amounts(
+            sqrt_price_x96,
+            sqrt_ratio_lower_x96,
+            sqrt_ratio_upper_x96,
+            balance0,
+            balance1,
+        )
+        .unwrap();
+
+        let mut amount_out = U256_1;
+        while let Ok(swap) = quote(sqrt_price_x96, amount_out, zero_for_one) {
+            let (balance0_after, balance1_after) = if zero_for_one {
+                if swap.amount_in > balance0 {
+                    break;
+                }
+                (balance0 - swap.amount_in, balance1 + amount_out)
+            } else {
+                if swap.amount_in > balance1 {
+                    break;
+                }
+                (balance0 + amount_out, balance1 - swap.amount_in)
+            };
+            let liquidity = get_liquidity_for_amounts(
+                swap.sqrt_price_x96_after,
+                sqrt_ratio_lower_x96,
+                sqrt_ratio_upper_x96,
+                balance0_after,
+                balance1_after,
+            )
+            .unwrap();
+            best = best.max(liquidity);
+            amount_out += U256_1;
+        }
+
+        best
+    }
+
+    fn assert_matches_brute_force(
+        balance0: u64,
+        balance1: u64,
+        tick: i32,
+        tick_lower: i32,
+        tick_upper: i32,
+        zero_for_one: bool,
+    ) {
+        let balance0 = U256::from(balance0);
+        let balance1 = U256::from(balance1);
+        let sqrt_price_x96 = get_sqrt_ratio_at_tick(tick).unwrap();
+        let sqrt_ratio_lower_x96 = get_sqrt_ratio_at_tick(tick_lower).unwrap();
+        let sqrt_ratio_upper_x96 = get_sqrt_ratio_at_tick(tick_upper).unwrap();
+
+        let solution = solve_swap_to_ratio(
+            balance0,
+            balance1,
+            sqrt_price_x96,
+            sqrt_ratio_lower_x96,
+            sqrt_ratio_upper_x96,
+            |amount_out, zero_for_one| quote(sqrt_price_x96, amount_out, zero_for_one),
+        )
+        .unwrap();
+
+        let expected = brute_force(
+            balance0,
+            balance1,
+            sqrt_price_x96,
+            sqrt_ratio_lower_x96,
+            sqrt_ratio_upper_x96,
+            zero_for_one,
+        );
+
+        assert_eq!(solution.zero_for_one, zero_for_one);
+        assert!(solution.amount_out > U256::ZERO);
+        assert_eq!(solution.liquidity, expected);
+
+        // The solution balances must be the ones given by the quote
+        let swap = quote(sqrt_price_x96, solution.amount_out, zero_for_one).unwrap();
+        assert_eq!(solution.amount_in, swap.amount_in);
+        assert_eq!(solution.sqrt_price_x96_after, swap.sqrt_price_x96_after);
+    }
+
+    #[test]
+    fn test_solve_only_token0_in_range() {
+        assert_matches_brute_force(10_000, 0, 0, -600, 600, true);
+    }
+
+    #[test]
+    fn test_solve_only_token1_in_range() {
+        assert_matches_brute_force(0, 10_000, 0, -600, 600, false);
+    }
+
+    #[test]
+    fn test_solve_both_tokens_asymmetric_range() {
+        assert_matches_brute_force(7_000, 2_000, 100, -1200, 300, true);
+        assert_matches_brute_force(1_000, 6_000, -50, -300, 1200, false);
+    }
+
+    #[test]
+    fn test_solve_range_above_price() {
+        // Only token0 is needed, all token1 is swapped
+        assert_matches_brute_force(1_000, 5_000, 0, 600, 1200, false);
+    }
+
+    #[test]
+    fn test_solve_range_below_price() {
+        // Only token1 is needed, all token0 is swapped
+        assert_matches_brute_force(5_000, 1_000, 0, -1200, -600, true);
+    }
+
+    #[test]
+    fn test_solve_balanced_no_swap() {
+        let sqrt_price_x96 = get_sqrt_ratio_at_tick(0).unwrap();
+        let sqrt_ratio_lower_x96 = get_sqrt_ratio_at_tick(-600).unwrap();
+        let sqrt_ratio_upper_x96 = get_sqrt_ratio_at_tick(600).unwrap();
+        let balance = U256::from(5_000);
+
+        let solution = solve_swap_to_ratio(
+            balance,
+            balance,
+            sqrt_price_x96,
+            sqrt_ratio_lower_x96,
+            sqrt_ratio_upper_x96,
+            |amount_out, zero_for_one| quote(sqrt_price_x96, amount_out, zero_for_one),
+        )
+        .unwrap();
+
+        // A symmetric range around the price is already balanced, any swap loses fees
+        assert_eq!(solution.amount_out, U256::ZERO);
+        assert_eq!(solution.amount_in, U256::ZERO);
+    }
+}
@@ -33,10 +33,10 @@ pub enum UniswapV3MathError {
     R,
     #[error("Overflow when casting to U160")]
     SafeCastToU160Overflow,
+    #[error("Liquidity gross exceeds the max liquidity per tick")]
+    MaxLiquidityPerTick,
     #[error("Tick spacing error")]
     TickSpacingError,
-    #[error("Middleware error when getting next_initialized_tick_within_one_word")]
-    MiddlewareError(String),
     #[error("Parse error")]
     ParseError(#[from] ParseError),
 }
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use alloy::primitives::U256;
+
+use crate::helpers::math::uniswap_v3::{
+    error::UniswapV3MathError,
+    liquidity_math::add_delta,
+    tick_math::{MAX_TICK, MIN_TICK},
+};
+
+// Tick.Info without the oracle accumulators, those are not needed to simulate swaps off-chain
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tick {
+    pub liquidity_gross: u128,
+    pub liquidity_net: i128,
+    pub fee_growth_outside_0_x_128: U256,
+    pub fee_growth_outside_1_x_128: U256,
+    pub initialized: bool,
+}
+
+// Derives max liquidity per tick from given tick spacing
+pub fn tick_spacing_to_max_liquidity_per_tick(tick_spacing: i32) -> u128 {
+    let min_tick = (MIN_TICK / tick_spacing) * tick_spacing;
+    let max_tick = (MAX_TICK / tick_spacing) * tick_spacing;
+    let num_ticks = ((max_tick - min_tick) / tick_spacing) as u128 + 1;
+    u128::MAX / num_ticks
+}
+
+// Retrieves fee growth data, returns (feeGrowthInside0X128, feeGrowthInside1X128)
+pub fn get_fee_growth_inside(
+    ticks: &HashMap<i32, Tick>,
+    tick_lower: i32,
+    tick_upper: i32,
+    tick_current: i32,
+    fee_growth_global_0_x_128: U256,
+    fee_growth_global_1_x_128: U256,
+) -> (U256, U256) {
+    let lower = ticks.get(&tick_lower).copied().unwrap_or_default();
+    let upper = ticks.get(&tick_upper).copied().unwrap_or_default();
+
+    // calculate fee growth below
+    let (fee_growth_below_0_x_128, fee_growth_below_1_x_128) = if tick_current >= tick_lower {
+        (
+            lower.fee_growth_outside_0_x_128,
+            lower.fee_growth_outside_1_x_128,
+        )
+    } else {
+        (
+            fee_growth_global_0_x_128.wrapping_sub(lower.fee_growth_outside_0_x_128),
+            fee_growth_global_1_x_128.wrapping_sub(lower.fee_growth_outside_1_x_128),
+        )
+    };
+
+    // calculate fee growth above
+    let (fee_growth_above_0_x_128, fee_growth_above_1_x_128) = if tick_current < tick_upper {
+        (
+            upper.fee_growth_outside_0_x_128,
+            upper.fee_growth_outside_1_x_128,
+        )
+    } else {
+        (
+            fee_growth_global_0_x_128.wrapping_sub(upper.fee_growth_outside_0_x_128),
+            fee_growth_global_1_x_128.wrapping_sub(upper.fee_growth_outside_1_x_128),
+        )
+    };
+
+    (
+        fee_growth_global_0_x_128
+            .wrapping_sub(fee_growth_below_0_x_128)
+            .wrapping_sub(fee_growth_above_0_x_128),
+        fee_growth_global_1_x_128
+            .wrapping_sub(fee_growth_below_1_x_128)
+            .wrapping_sub(fee_growth_above_1_x_128),
+    )
+}
+
+// Updates a tick and returns true if the tick was flipped from initialized to uninitialized, or vice versa
+#[allow(clippy::too_many_arguments)]
+pub fn update(
+    ticks: &mut HashMap<i32, Tick>,
+    tick: i32,
+    tick_current: i32,
+    liquidity_delta: i128,
+    fee_growth_global_0_x_128: U256,
+    fee_growth_global_1_x_128: U256,
+    upper: bool,
+    max_liquidity: u128,
+) -> Result<bool, UniswapV3MathError> {
+    let info = ticks.entry(tick).or_default();
+
+    let liquidity_gross_before = info.liquidity_gross;
+    let liquidity_gross_after = add_delta(liquidity_gross_before, liquidity_delta)?;
+
+    if liquidity_gross_after > max_liquidity {
+        return Err(UniswapV3MathError::MaxLiquidityPerTick);
+    }
+
+    let flipped = (liquidity_gross_after == 0) != (liquidity_gross_before == 0);
+
+    if liquidity_gross_before == 0 {
+        // by convention, we assume that all growth before a tick was initialized happened _below_ the tick
+        if tick <= tick_current {
+            info.fee_growth_outside_0_x_128 = fee_growth_global_0_x_128;
+            info.fee_growth_outside_1_x_128 = fee_growth_global_1_x_128;
+        }
+        info.initialized = true;
+    }
+
+    info.liquidity_gross = liquidity_gross_after;
+
+    // when the lower (upper) tick is crossed left to right (right to left), liquidity must be added (removed)
+    info.liquidity_net = if upper {
+        info.liquidity_net
+            .checked_sub(liquidity_delta)
+            .ok_or(UniswapV3MathError::LiquiditySub)?
+    } else {
+        info.liquidity_net
+            .checked_add(liquidity_delta)
+            .ok_or(UniswapV3MathError::LiquidityAdd)?
+    };
+
+    Ok(flipped)
+}
+
+// Transitions to next tick as needed by price movement, returns the amount of liquidity
+// added (subtracted) when the tick is crossed from left to right (right to left)
+pub fn cross(
+    ticks: &mut HashMap<i32, Tick>,
+    tick: i32,
+    fee_growth_global_0_x_128: U256,
+    fee_growth_global_1_x_128: U256,
+) -> i128 {
+    let info = ticks.entry(tick).or_default();
+    info.fee_growth_outside_0_x_128 =
+        fee_growth_global_0_x_128.wrapping_sub(info.fee_growth_outside_0_x_128);
+    info.fee_growth_outside_1_x_128 =
+        fee_growth_global_1_x_128.wrapping_sub(info.fee_growth_outside_1_x_128);
+    info.liquidity_net
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use alloy::primitives::U256;
+
+    use super::{
+        Tick, cross, get_fee_growth_inside, tick_spacing_to_max_liquidity_per_tick, update,
+    };
+
+    #[test]
+    fn test_tick_spacing_to_max_liquidity_per_tick() {
+        // low fee
+        assert_eq!(
+            tick_spacing_to_max_liquidity_per_tick(10),
+            1917569901783203986719870431555990
+        );
+        // medium fee
+        assert_eq!(
+            tick_spacing_to_max_liquidity_per_tick(60),
+            11505743598341114571880798222544994
+        );
+        // high fee
+        assert_eq!(
+            tick_spacing_to_max_liquidity_per_tick(200),
+            38350317471085141830651933667504588
+        );
+        // entire range
+        assert_eq!(
+            tick_spacing_to_max_liquidity_per_tick(887272),
+            u128::MAX / 3
+        );
+        assert_eq!(
+            tick_spacing_to_max_liquidity_per_tick(2302),
+            441351967472034323558203122479595605
+        );
+    }
+
+    #[test]
+    fn test_get_fee_growth_inside() {
+        let mut ticks = HashMap::new();
+
+        // uninitialized ticks, tick is inside
+        let inside = get_fee_growth_inside(&ticks, -2, 2, 0, U256::from(15), U256::from(15));
+        assert_eq!(inside, (U256::from(15), U256::from(15)));
+
+        // uninitialized ticks, tick is above
+        let inside = get_fee_growth_inside(&ticks, -2, 2, 4, U256::from(15), U256::from(15));
+        assert_eq!(inside, (U256::ZERO, U256::ZERO));
+
+        // uninitialized ticks, tick is below
+        let inside = get_fee_growth_inside(&ticks, -2, 2, -4, U256::from(15), U256::from(15));
+        assert_eq!(inside, (U256::ZERO, U256::ZERO));
+
+        // subtracts upper tick if below
+        ticks.insert(
+            2,
+            Tick {
+                fee_growth_outside_0_x_128: U256::from(2),
+                fee_growth_outside_1_x_128: U256::from(3),
+                ..Default::default()
+            },
+        );
+        let inside = get_fee_growth_inside(&ticks, -2, 2, 0, U256::from(15), U256::from(15));
+        assert_eq!(inside, (U256::from(13), U256::from(12)));
+
+        // subtracts lower tick if above
+        ticks.insert(
+            -2,
+            Tick {
+                fee_growth_outside_0_x_128: U256::from(2),
+                fee_growth_outside_1_x_128: U256::from(3),
+                ..Default::default()
+            },
+        );
+        ticks.remove(&2);
+        let inside = get_fee_growth_inside(&ticks, -2, 2, 0, U256::from(15), U256::from(15));
+        assert_eq!(inside, (U256::from(13), U256::from(12)));
+
+        // works correctly with overflow on inside tick
+        ticks.insert(
+            -2,
+            Tick {
+                fee_growth_outside_0_x_128: U256::MAX - U256::from(3),
+                fee_growth_outside_1_x_128: U256::MAX - U256::from(2),
+                ..Default::default()
+            },
+        );
+        ticks.insert(
+            2,
+            Tick {
+                fee_growth_outside_0_x_128: U256::from(3),
+                fee_growth_outside_1_x_128: U256::from(5),
+                ..Default::default()
+            },
+        );
+        let inside = get_fee_growth_inside(&ticks, -2, 2, 0, U256::from(15), U256::from(15));
+        assert_eq!(inside, (U256::from(16), U256::from(13)));
+    }
+
+    #[test]
+    fn test_update() {
+        let mut ticks = HashMap::new();
+
+        // flips from zero to nonzero
+        assert!(update(&mut ticks, 0, 0, 1, U256::ZERO, U256::ZERO, false, 3).unwrap());
+
+        // does not flip from nonzero to greater nonzero
+        assert!(!update(&mut ticks, 0, 0, 1, U256::ZERO, U256::ZERO, false, 3).unwrap());
+
+        // flips from nonzero to zero
+        assert!(update(&mut ticks, 0, 0, -2, U256::ZERO, U256::ZERO, false, 3).unwrap());
+
+        // reverts if total liquidity gross is greater than max
+        update(&mut ticks, 0, 0, 2, U256::ZERO, U256::ZERO, false, 3).unwrap();
+        update(&mut ticks, 0, 0, 1, U256::ZERO, U256::ZERO, true, 3).unwrap();
+        assert!(update(&mut ticks, 0, 0, 1, U256::ZERO, U256::ZERO, false, 3).is_err());
+
+        // nets the liquidity based on upper flag
+        let mut ticks = HashMap::new();
+        update(&mut ticks, 0, 0, 2, U256::ZERO, U256::ZERO, false, 10).unwrap();
+        update(&mut ticks, 0, 0, 1, U256::ZERO, U256::ZERO, true, 10).unwrap();
+        update(&mut ticks, 0, 0, 3, U256::ZERO, U256::ZERO, true, 10).unwrap();
+        update(&mut ticks, 0, 0, 1, U256::ZERO, U256::ZERO, false, 10).unwrap();
+        let info = ticks[&0];
+        assert_eq!(info.liquidity_gross, 2 + 1 + 3 + 1);
+        assert_eq!(info.liquidity_net, 2 - 1 - 3 + 1);
+
+        // assumes all growth happens below ticks lte current tick
+        let mut ticks = HashMap::new();
+        update(
+            &mut ticks,
+            1,
+            1,
+            1,
+            U256::from(1),
+            U256::from(2),
+            false,
+            u128::MAX,
+        )
+        .unwrap();
+        let info = ticks[&1];
+        assert_eq!(info.fee_growth_outside_0_x_128, U256::from(1));
+        assert_eq!(info.fee_growth_outside_1_x_128, U256::from(2));
+        assert!(info.initialized);
+
+        // does not set any growth fields if tick is above current tick
+        update(
+            &mut ticks,
+            2,
+            1,
+            1,
+            U256::from(1),
+            U256::from(2),
+            false,
+            u128::MAX,
+        )
+        .unwrap();
+        let info = ticks[&2];
+        assert_eq!(info.fee_growth_outside_0_x_128, U256::ZERO);
+        assert_eq!(info.fee_growth_outside_1_x_128, U256::ZERO);
+    }
+
+    #[test]
+    fn test_cross() {
+        let mut ticks = HashMap::new();
+        ticks.insert(
+            2,
+            Tick {
+                liquidity_gross: 3,
+                liquidity_net: 4,
+                fee_growth_outside_0_x_128: U256::from(1),
+                fee_growth_outside_1_x_128: U256::from(2),
+                initialized: true,
+            },
+        );
+
+        // flips the growth variables
+        let liquidity_net = cross(&mut ticks, 2, U256::from(7), U256::from(9));
+        assert_eq!(liquidity_net, 4);
+        let info = ticks[&2];
+        assert_eq!(info.fee_growth_outside_0_x_128, U256::from(6));
+        assert_eq!(info.fee_growth_outside_1_x_128, U256::from(7));
+
+        // two flips are no op
+        cross(&mut ticks, 2, U256::from(7), U256::from(9));
+        let info = ticks[&2];
+        assert_eq!(info.fee_growth_outside_0_x_128, U256::from(1));
+        assert_eq!(info.fee_growth_outside_1_x_128, U256::from(2));
+    }
+}
@@ -0,0 +1,239 @@
+/*
+    Off-chain mirror of the YielderaVault share math (deposit, withdraw, _liquidityForShares and
+    the OracleLibrary spot price), with the same integer rounding as the contract.
+*/
+use alloy::primitives::U256;
+use color_eyre::eyre::{Result, eyre};
+
+use crate::helpers::math::uniswap_v3::{
+    full_math::mul_div, liquidity_math::get_amounts_for_liquidity,
+    tick_math::get_sqrt_ratio_at_tick,
+};
+
+/// YielderaVault.PRECISION, base amount of the spot price quote
+pub const PRECISION: U256 = U256::from_limbs([1_000_000_000_000_000_000, 0, 0, 0]);
+/// The JSON-RPC relay takes native HBAR values with 18 decimals (weibars), the EVM uses 8
+pub const NATIVE_VALUE_DECIMALS: u8 = 18;
+
+/// OracleLibrary.getQuoteAtTick: amount of quote token worth `base_amount` of base token
+pub fn get_quote_at_tick(tick: i32, base_amount: u128, base_is_lower: bool) -> Result<U256> {
+    let sqrt_ratio_x96 = get_sqrt_ratio_at_tick(tick)?;
+    let base_amount = U256::from(base_amount);
+
+    let quote_amount = if sqrt_ratio_x96 <= U256::from(u128::MAX) {
+        let ratio_x192 = sqrt_ratio_x96 * sqrt_ratio_x96;
+        if base_is_lower {
+            mul_div(ratio_x192, base_amount, U256::from(1) << 192)?
+        } else {
+            mul_div(U256::from(1) << 192, base_amount, ratio_x192)?
+        }
+    } else {
+        let ratio_x128 = mul_div(sqrt_ratio_x96, sqrt_ratio_x96, U256::from(1) << 64)?;
+        if base_is_lower {
+            mul_div(ratio_x128, base_amount, U256::from(1) << 128)?
+        } else {
+            mul_div(U256::from(1) << 128, base_amount, ratio_x128)?
+        }
+    };
+
+    Ok(quote_amount)
+}
+
+/// Shares minted by `deposit(deposit0, deposit1)`. `spot_price` is the value of PRECISION token0
+/// in token1, `pool0`/`pool1` the vault total amounts before the deposit
+pub fn shares_for_deposit(
+    deposit0: U256,
+    deposit1: U256,
+    pool0: U256,
+    pool1: U256,
+    total_supply: U256,
+    spot_price: U256,
+) -> Result<U256> {
+    if deposit0.is_zero() && deposit1.is_zero() {
+        return Err(eyre!("ZERO_DEPOSIT"));
+    }
+
+    let deposit0_priced_in_token1 = deposit0 * spot_price / PRECISION;
+    let shares = deposit1 + deposit0_priced_in_token1;
+
+    if total_supply.is_zero() {
+        return Ok(shares);
+    }
+
+    let pool0_priced_in_token1 = pool0 * spot_price / PRECISION;
+    let pool_value = pool0_priced_in_token1 + pool1;
+
+    if pool_value.is_zero() {
+        return Err(eyre!("The vault has shares but no assets"));
+    }
+
+    Ok(shares * total_supply / pool_value)
+}
+
+/// Vault state read by `withdraw`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WithdrawState {
+    pub total_supply: U256,
+    /// Token balances held by the vault outside the position
+    pub balance0: U256,
+    pub balance1: U256,
+    pub position_liquidity: u128,
+    pub sqrt_price_x96: U256,
+    pub lower_tick: i32,
+    pub upper_tick: i32,
+}
+
+/// Amounts returned by `withdraw(shares)`: the pro rata of the idle balances plus the amounts of
+/// the pro rata of the position liquidity
+pub fn amounts_for_withdraw(shares: U256, state: &WithdrawState) -> Result<(U256, U256)> {
+    if shares.is_zero() {
+        return Err(eyre!("ZERO_SHARES"));
+    }
+    if shares > state.total_supply {
+        return Err(eyre!("INSUFF_SHARES_BAL"));
+    }
+
+    let unused0 = state.balance0 * shares / state.total_supply;
+    let unused1 = state.balance1 * shares / state.total_supply;
+
+    let liquidity = liquidity_for_shares(state.position_liquidity, shares, state.total_supply)?;
+
+    let (position0, position1) = if liquidity > 0 {
+        get_amounts_for_liquidity(
+            state.sqrt_price_x96,
+            get_sqrt_ratio_at_tick(state.lower_tick)?,
+            get_sqrt_ratio_at_tick(state.upper_tick)?,
+            liquidity,
+        )?
+    } else {
+        (U256::ZERO, U256::ZERO)
+    };
+
+    Ok((unused0 + position0, unused1 + position1))
+}
+
+/// `_liquidityForShares`: share of the position liquidity owned by `shares`
+pub fn liquidity_for_shares(
+    position_liquidity: u128,
+    shares: U256,
+    total_supply: U256,
+) -> Result<u128> {
+    let liquidity = U256::from(position_liquidity) * shares / total_supply;
+
+    u128::try_from(liquidity).map_err(|_| eyre!("Liquidity for shares overflows uint128"))
+}
+
+/// Transaction value to send for a native HBAR amount of a token with `token_decimals`
+pub fn native_value(amount: U256, token_decimals: u8) -> U256 {
+    amount * U256::from(10).pow(U256::from(NATIVE_VALUE_DECIMALS - token_decimals))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use super::{
+        PRECISION, WithdrawState, amounts_for_withdraw, get_quote_at_tick, native_value,
+        shares_for_deposit,
+    };
+    use crate::helpers::math::uniswap_v3::tick_math::get_sqrt_ratio_at_tick;
+
+    #[test]
+    fn test_get_quote_at_tick() {
+        // Tick 0 is a price of 1
+        assert_eq!(
+            get_quote_at_tick(0, 1_000_000, true).unwrap(),
+            U256::from(1_000_000)
+        );
+
+        // 1.0001^6932 ~= 2
+        let quote = get_quote_at_tick(6932, 1_000_000, true).unwrap();
+        assert!(quote >= U256::from(1_999_900) && quote <= U256::from(2_000_100));
+        let quote = get_quote_at_tick(6932, 1_000_000, false).unwrap();
+        assert!(quote >= U256::from(499_970) && quote <= U256::from(500_030));
+
+        // Large ticks use the 128 bits path
+        assert!(get_quote_at_tick(500_000, 1, true).unwrap() > U256::from(u128::MAX) >> 64);
+    }
+
+    #[test]
+    fn test_shares_for_deposit() {
+        let spot_price = PRECISION * U256::from(2);
+
+        // First deposit: the deposit value in token1
+        let shares = shares_for_deposit(
+            U256::from(10),
+            U256::from(5),
+            U256::ZERO,
+            U256::ZERO,
+            U256::ZERO,
+            spot_price,
+        )
+        .unwrap();
+        assert_eq!(shares, U256::from(25));
+
+        // Pool worth 100 token1 for 50 shares: a 20 token1 deposit mints 10 shares
+        let shares = shares_for_deposit(
+            U256::ZERO,
+            U256::from(20),
+            U256::from(30),
+            U256::from(40),
+            U256::from(50),
+            spot_price,
+        )
+        .unwrap();
+        assert_eq!(shares, U256::from(10));
+
+        assert!(
+            shares_for_deposit(
+                U256::ZERO,
+                U256::ZERO,
+                U256::ZERO,
+                U256::ZERO,
+                U256::ZERO,
+                spot_price
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_amounts_for_withdraw() {
+        let state = WithdrawState {
+            total_supply: U256::from(1_000),
+            balance0: U256::from(500),
+            balance1: U256::from(999),
+            position_liquidity: 0,
+            sqrt_price_x96: get_sqrt_ratio_at_tick(0).unwrap(),
+            lower_tick: -60,
+            upper_tick: 60,
+        };
+
+        // Idle balances only, rounded down
+        assert_eq!(
+            amounts_for_withdraw(U256::from(100), &state).unwrap(),
+            (U256::from(50), U256::from(99))
+        );
+
+        // In range position at price 1: both tokens come back
+        let state = WithdrawState {
+            position_liquidity: 1_000_000_000,
+            ..state
+        };
+        let (amount0, amount1) = amounts_for_withdraw(U256::from(1_000), &state).unwrap();
+        assert!(amount0 > U256::from(500) && amount1 > U256::from(999));
+        assert_eq!(amount0 - U256::from(500), amount1 - U256::from(999));
+
+        assert!(amounts_for_withdraw(U256::from(1_001), &state).is_err());
+        assert!(amounts_for_withdraw(U256::ZERO, &state).is_err());
+    }
+
+    #[test]
+    fn test_native_value() {
+        // 1.5 WHBAR (8 decimals) is sent as 1.5e18 weibars
+        assert_eq!(
+            native_value(U256::from(150_000_000u64), 8),
+            U256::from(1_500_000_000_000_000_000u128)
+        );
+    }
+}
@@ -14,8 +14,9 @@ use color_eyre::eyre::Result;
 
 use crate::{
     config::{FEE_FACTOR, HBAR_EVM_ADDRESS},
-    helpers,
-    types::{Pool, Position, Token, VaultDetails, VaultTVL, VaultTokenBalances},
+    core::preflight,
+    helpers::{self, math::vault_shares::native_value},
+    types::{Pool, Position, Token, VaultDetails, VaultFees, VaultTVL, VaultTokenBalances},
 };
 
 sol!(
@@ -147,6 +148,7 @@ where
             tvl0: 0.0,
             tvl1: 0.0,
         },
+        fees: VaultFees::default(),
     })
 }
 
@@ -183,15 +185,15 @@ where
 
     let vault_contract = YielderaVault::new(vault_address, provider);
 
-    // We use 18 decimals cause evm relay handles nativve hbar as 18 decimals
-    let custom_deposit_0: U256 = parse_units(deposit0.to_string().as_str(), 18)?.into();
-    let custom_deposit_1: U256 = parse_units(deposit1.to_string().as_str(), 18)?.into();
-
     let deposit0: U256 =
         parse_units(deposit0.to_string().as_str(), vault.pool.token0.decimals)?.into();
     let deposit1: U256 =
         parse_units(deposit1.to_string().as_str(), vault.pool.token1.decimals)?.into();
 
+    // The evm relay handles native hbar values with 18 decimals
+    let custom_deposit_0 = native_value(deposit0, vault.pool.token0.decimals);
+    let custom_deposit_1 = native_value(deposit1, vault.pool.token1.decimals);
+
     let token0_contract = ERC20::new(
         Address::from_str(vault.pool.token0.address.as_str())?,
         provider,
@@ -201,7 +203,7 @@ where
         provider,
     );
 
-    let mut value_to_send = U256::ZERO;
+    let value_to_send = deposit_value(vault, deposit0, deposit1);
 
     // Check the balance of the user of token0
     if vault.pool.token0.is_native_wrapper {
@@ -217,8 +219,6 @@ where
                 custom_deposit_0
             ));
         }
-
-        value_to_send = custom_deposit_0;
     } else {
         let balance = token0_contract
             .balanceOf(provider.default_signer_address())
@@ -274,8 +274,6 @@ where
                 custom_deposit_1
             ));
         }
-
-        value_to_send = custom_deposit_1;
     } else {
         let balance = token1_contract
             .balanceOf(provider.default_signer_address())
@@ -317,26 +315,38 @@ where
         }
     }
 
-    let deposit_tx = vault_contract
-        .deposit(deposit0, deposit1, provider.default_signer_address())
-        .value(value_to_send)
-        .gas(15_000_000)
-        .send()
+    let deposit_request = preflight::prepare(
+        vault_contract
+            .deposit(deposit0, deposit1, provider.default_signer_address())
+            .value(value_to_send),
+    )
+    .await?;
+    let deposit_receipt = provider
+        .send_transaction(deposit_request)
+        .await?
+        .get_receipt()
         .await?;
 
-    let deposit_receipt = deposit_tx.get_receipt().await?;
+    println!(
+        "Deposit transaction hash: {}",
+        deposit_receipt.transaction_hash
+    );
+    println!("Deposit transaction status: {}", deposit_receipt.status());
 
-    let deposit_tx_hash = deposit_receipt.transaction_hash;
-    let deposit_status = deposit_receipt.status();
+    preflight::check_receipt(&deposit_receipt)?;
 
-    println!("Deposit transaction hash: {}", deposit_tx_hash);
-    println!("Deposit transaction status: {}", deposit_status);
+    Ok(deposit_receipt)
+}
 
-    if !deposit_status {
-        return Err(color_eyre::eyre::eyre!("Deposit transaction failed"));
+/// Transaction value of a deposit: the WHBAR side of the vault is paid in native HBAR
+pub fn deposit_value(vault: &VaultDetails, deposit0: U256, deposit1: U256) -> U256 {
+    if vault.pool.token0.is_native_wrapper {
+        native_value(deposit0, vault.pool.token0.decimals)
+    } else if vault.pool.token1.is_native_wrapper {
+        native_value(deposit1, vault.pool.token1.decimals)
+    } else {
+        U256::ZERO
     }
-
-    Ok(deposit_receipt)
 }
 
 pub async fn mint_liquidity_from_amount0<P>(
@@ -375,27 +385,22 @@ where
     // hbar conversation rate
     let value_to_send: U256 = parse_units("0.5", 18)?.into();
 
-    let mint_tx = vault_contract
-        .mintLiquidity(amount0_desired, amount1_desired, lower_tick, upper_tick)
-        .gas(15_000_000)
-        .value(value_to_send)
-        .send()
+    let mint_request = preflight::prepare(
+        vault_contract
+            .mintLiquidity(amount0_desired, amount1_desired, lower_tick, upper_tick)
+            .value(value_to_send),
+    )
+    .await?;
+    let mint_receipt = provider
+        .send_transaction(mint_request)
+        .await?
+        .get_receipt()
         .await?;
 
-    let mint_receipt = mint_tx.get_receipt().await?;
-
-    let mint_tx_hash = mint_receipt.transaction_hash;
-    let mint_status = mint_receipt.status();
+    println!("Mint transaction hash: {}", mint_receipt.transaction_hash);
+    println!("Mint transaction status: {}", mint_receipt.status());
 
-    println!("Mint transaction hash: {}", mint_tx_hash);
-    println!("Mint transaction status: {}", mint_status);
-
-    if !mint_status {
-        return Err(color_eyre::eyre::eyre!(
-            "Mint transaction failed. Error: {:?}",
-            mint_receipt
-        ));
-    }
+    preflight::check_receipt(&mint_receipt)?;
 
     Ok(())
 }
@@ -413,19 +418,17 @@ where
 
     let vault_contract = YielderaVault::new(vault_address, provider);
 
-    let burn_tx = vault_contract.burnAllLiquidity().send().await?;
-
-    let burn_receipt = burn_tx.get_receipt().await?;
-
-    let burn_tx_hash = burn_receipt.transaction_hash;
-    let burn_status = burn_receipt.status();
+    let burn_request = preflight::prepare(vault_contract.burnAllLiquidity()).await?;
+    let burn_receipt = provider
+        .send_transaction(burn_request)
+        .await?
+        .get_receipt()
+        .await?;
 
-    println!("Burn transaction hash: {}", burn_tx_hash);
-    println!("Burn transaction status: {}", burn_status);
+    println!("Burn transaction hash: {}", burn_receipt.transaction_hash);
+    println!("Burn transaction status: {}", burn_receipt.status());
 
-    if !burn_status {
-        return Err(color_eyre::eyre::eyre!("Burn transaction failed"));
-    }
+    preflight::check_receipt(&burn_receipt)?;
 
     Ok(())
 }
@@ -1,13 +1,16 @@
 mod api;
+mod backtest;
 mod config;
 mod core;
+mod db;
+mod error;
 mod helpers;
 mod state;
 mod strategies;
 mod types;
 
 use actix_cors::Cors;
-use actix_web::{App, HttpServer, web};
+use actix_web::{App, HttpServer, middleware, web};
 
 use tracing::{error, info};
 use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
@@ -50,18 +53,25 @@ async fn main() -> std::io::Result<()> {
     // Init all vaults and store them in the app state
     init_all_vaults(&app_state).await.unwrap();
 
-    let all_vaults_addresses = &CONFIG.toml_config.vaults;
+    let all_vaults_configs = &CONFIG.toml_config.vaults;
 
     //  Open a tokio thread for each vault stored in the app state, and start the liquidity management loop
-    for address in all_vaults_addresses {
+    for vault_config in all_vaults_configs {
         let cloned_app_state = app_state.clone();
+        let commands = core::control::register_vault(&app_state, &vault_config.address);
         tokio::spawn(async move {
-            match core::vault_spawn::start_vault_liq_management(address, cloned_app_state).await {
+            match core::vault_spawn::start_vault_liq_management(
+                vault_config,
+                cloned_app_state,
+                commands,
+            )
+            .await
+            {
                 Ok(_) => {}
                 Err(e) => {
                     error!(
                         "Failed on start vault liq management for address: {:?}",
-                        address
+                        vault_config.address
                     );
                     error!("Error: {:?}", e);
                 }
@@ -69,6 +79,21 @@ async fn main() -> std::io::Result<()> {
         });
     }
 
+    // Follow the events of each vault and store them
+    for vault_config in all_vaults_configs {
+        let cloned_app_state = app_state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = core::indexer::start_vault_indexer(vault_config, cloned_app_state).await
+            {
+                error!(
+                    "Failed on start event indexer for address: {:?}",
+                    vault_config.address
+                );
+                error!("Error: {:?}", e);
+            }
+        });
+    }
+
     // Start the http server
     info!("Starting Http Server at http://127.0.0.1:8090");
     info!("Starting SWAGGER Server at http://127.0.0.1:8090/swagger-ui/");
@@ -80,13 +105,33 @@ async fn main() -> std::io::Result<()> {
             .allow_any_header();
 
         let (app, app_api) = App::new()
+            .wrap(middleware::from_fn(api::auth::admin_auth))
             .wrap(cors)
             .into_utoipa_app()
             .app_data(web::Data::clone(&app_state))
             .service(api::get_index_service)
             .service(api::get_health_service)
             .service(api::handle_get_all_vaults)
+            .service(api::handle_backtest_vault)
+            .service(api::handle_get_vault_rebalances)
+            .service(api::handle_get_vault_history)
+            .service(api::handle_get_vault_stats)
+            .service(api::handle_get_vault_events)
+            .service(api::handle_get_user_positions)
+            .service(api::handle_quote_deposit)
+            .service(api::handle_quote_withdraw)
+            .service(api::handle_build_deposit_tx)
+            .service(api::handle_build_withdraw_tx)
+            .service(api::handle_build_approve_tx)
             .service(api::handle_admin_associate_vault_tokens)
+            .service(api::handle_admin_get_audit_log)
+            .service(api::handle_admin_reset_circuit_breaker)
+            .service(api::handle_admin_pause_vault)
+            .service(api::handle_admin_resume_vault)
+            .service(api::handle_admin_evaluate_vault)
+            .service(api::handle_admin_force_rebalance)
+            .service(api::handle_admin_burn_all_liquidity)
+            .service(api::handle_admin_get_rebalance_plan)
             .service(api::handle_chat)
             .split_for_parts();
 
@@ -113,7 +158,6 @@ mod test {
     use crate::{
         config::{CHAIN_ID, IS_NEW_CONTRACT, RPC_URL},
         helpers::vault::YielderaVault,
-        types::PrepareSwapArgs,
     };
 
     use super::*;
@@ -137,7 +181,7 @@ mod test {
             .connect(&CONFIG.toml_config.rpc_url)
             .await?;
 
-        let contract_address = CONFIG.toml_config.vaults[0].as_str();
+        let contract_address = CONFIG.toml_config.vaults[0].address.as_str();
         // let contract_address = "0xA5B1102CF31e71b59544BD648EE1fC293B043bE0";
 
         let vault_details = core::vault::get_vault_details(&evm_provider, contract_address).await?;
@@ -181,7 +225,7 @@ mod test {
         }
 
         // Start strategy thta will get me the best tick range to put liq on
-        let tick_range = strategies::basic::get_best_range(&vault_details).await?;
+        let tick_range = strategies::basic::get_range_around_price(&vault_details, 1.0)?;
 
         println!("Tick range: {:#?}", tick_range);
 
@@ -249,7 +293,7 @@ mod test {
         }
 
         // Start strategy thta will get me the best tick range to put liq on
-        let tick_range = strategies::basic::get_best_range(&vault_details).await?;
+        let tick_range = strategies::basic::get_range_around_price(&vault_details, 1.0)?;
 
         println!("Tick range: {:#?}", tick_range);
 
@@ -339,66 +383,43 @@ mod test {
         println!("Exess1: {:#?}", exess1);
 
         // Prepare the swap args for the negative value betwen the exess0 and exess1
-        let swap_arg: PrepareSwapArgs;
-
-        if exess0 < 0.0 {
-            let exact_amount_out = exess0.abs();
-            let parsed_exact_amount_out: U256 = parse_units(
-                exact_amount_out.to_string().as_str(),
-                vault_details.pool.token0.decimals,
-            )?
-            .into();
-            let max_amount_in: U256 = parse_units(
-                exess1.abs().to_string().as_str(),
-                vault_details.pool.token1.decimals,
-            )?
-            .into();
-
-            swap_arg = PrepareSwapArgs {
-                exact_amount_out,
-                parsed_exact_amount_out,
-                token_in: vault_details.pool.token1,
-                token_out: vault_details.pool.token0,
-                is_swap_0_to_1: false,
-                max_amount_in,
-                formatted_max_amount_in: exess1.abs(),
-            };
+        let (amount_out, max_amount_in, is_swap_0_to_1): (U256, U256, bool) = if exess0 < 0.0 {
+            (
+                parse_units(
+                    exess0.abs().to_string().as_str(),
+                    vault_details.pool.token0.decimals,
+                )?
+                .into(),
+                parse_units(
+                    exess1.abs().to_string().as_str(),
+                    vault_details.pool.token1.decimals,
+                )?
+                .into(),
+                false,
+            )
         } else if exess1 < 0.0 {
-            let exact_amount_out = exess1.abs();
-            let parsed_exact_amount_out: U256 = parse_units(
-                exact_amount_out.to_string().as_str(),
-                vault_details.pool.token1.decimals,
-            )?
-            .into();
-            let max_amount_in: U256 = parse_units(
-                exess0.abs().to_string().as_str(),
-                vault_details.pool.token0.decimals,
-            )?
-            .into();
-
-            swap_arg = PrepareSwapArgs {
-                exact_amount_out,
-                parsed_exact_amount_out,
-                token_in: vault_details.pool.token0,
-                token_out: vault_details.pool.token1,
-                is_swap_0_to_1: true,
-                max_amount_in,
-                formatted_max_amount_in: exess0.abs(),
-            };
+            (
+                parse_units(
+                    exess1.abs().to_string().as_str(),
+                    vault_details.pool.token1.decimals,
+                )?
+                .into(),
+                parse_units(
+                    exess0.abs().to_string().as_str(),
+                    vault_details.pool.token0.decimals,
+                )?
+                .into(),
+                true,
+            )
         } else {
             // No need to swap
-            swap_arg = PrepareSwapArgs {
-                exact_amount_out: 0.0,
-                parsed_exact_amount_out: U256::ZERO,
-                token_in: vault_details.pool.token0,
-                token_out: vault_details.pool.token1,
-                is_swap_0_to_1: true,
-                max_amount_in: U256::ZERO,
-                formatted_max_amount_in: 0.0,
-            };
-        }
+            (U256::ZERO, U256::ZERO, true)
+        };
 
-        println!("Swap arg: {:#?}", swap_arg);
+        println!(
+            "Swap amount out: {}, max amount in: {}, 0 to 1: {}",
+            amount_out, max_amount_in, is_swap_0_to_1
+        );
 
         // call rebelance on the vault with new tick range and swap direction and amount
         let vault_contract = YielderaVault::new(Address::from_str(contract_address)?, evm_provider);
@@ -412,9 +433,9 @@ mod test {
             .rebalance(
                 lower_tick,
                 upper_tick,
-                swap_arg.parsed_exact_amount_out,
-                swap_arg.max_amount_in,
-                swap_arg.is_swap_0_to_1,
+                amount_out,
+                max_amount_in,
+                is_swap_0_to_1,
             )
             .value(value_to_send)
             .send()
@@ -461,15 +482,11 @@ mod test {
             helpers::vault::deposit_tokens_to_vault(&evm_provider, &vault_details, 1.0, 0.0)
                 .await?;
 
-        // let deposit_logs = deposit_reciept.logs();
-
-        // for log in deposit_logs {
-        //     let log_data = log.data();
-        //     let data = log_data.data.clone();
-
-        //     // decode the log data
-        //     let decoded_log = log.log_decode()?;
-        // }
+        for log in deposit_reciept.logs() {
+            if let Some(event) = core::indexer::decode_vault_event(log)? {
+                println!("Deposit event: {:#?}", event);
+            }
+        }
 
         // Get my vault shares
         let (vault_shares_u256, vault_shares) = helpers::vault::get_vault_shares_by_address(
@@ -1,16 +1,30 @@
-use chrono::{DateTime, Utc};
-use once_cell::sync::Lazy;
 use rig::{agent::Agent, providers::gemini::completion::CompletionModel};
 
 use crate::{
-    core::init::{init_ai_agent, init_evm_provider},
+    api::auth::UsedSignatures,
+    config::CONFIG,
+    core::{
+        control::VaultControl,
+        init::{init_ai_agent, init_evm_provider},
+        tx_manager::TxManager,
+    },
+    db::Db,
+    strategies::StrategyRegistry,
     types::{EvmProvider, VaultDetails},
 };
 
 pub struct AppState {
     pub evm_provider: EvmProvider,
+    /// Sends the transactions of the vault tasks, one queue per signer
+    pub tx_manager: TxManager,
     pub all_vaults: dashmap::DashMap<String, VaultDetails>,
     pub ai_agent: Agent<CompletionModel>,
+    pub strategies: StrategyRegistry,
+    pub db: Db,
+    /// Admin control of the vault loops, by lowercase vault address
+    pub vault_controls: dashmap::DashMap<String, VaultControl>,
+    /// Owner signatures of admin requests already accepted, to reject their replay
+    pub used_signatures: UsedSignatures,
 }
 
 impl AppState {
@@ -21,14 +35,20 @@ impl AppState {
         let ai_agent = init_ai_agent()
             .await
             .expect("Failed to initialize AI agent");
+        // Open the history database and apply the migrations
+        let db = Db::open(&CONFIG.database_path).expect("Failed to open the database");
+
+        let tx_manager = TxManager::new(evm_provider.clone());
 
         Self {
             ai_agent,
             evm_provider,
+            tx_manager,
             all_vaults: dashmap::DashMap::new(),
+            strategies: StrategyRegistry::with_defaults(),
+            db,
+            vault_controls: dashmap::DashMap::new(),
+            used_signatures: UsedSignatures::default(),
         }
     }
 }
-
-// Global static that holds the UTC timestamp at program start
-pub static START_TIMESTAMP: Lazy<DateTime<Utc>> = Lazy::new(|| Utc::now());
@@ -1,17 +1,210 @@
 /*
    This Strategy is not supported on testnet cause it relies on coingecko pools data which is not available for testnet pools
+   The recommended ranges are checked (see `validate_ai_response`). On an invalid response a vault in range keeps its
+   range, otherwise it falls back to the basic strategy range of `fallback_range_percent`
 */
 
+use std::sync::Arc;
+
 use crate::{
     core, helpers,
-    types::{AiStrategyResponse, CoingeckoOhlcvRes, TickRange, VaultDetails},
+    strategies::{self, Strategy, basic},
+    types::{AiStrategyResponse, MarketContext, RebalanceDecision, TickRange, VaultDetails},
 };
+use async_trait::async_trait;
 use color_eyre::eyre::Result;
 
 use rig::{client::ProviderClient, completion::Prompt, providers::gemini};
+use serde::Deserialize;
 use serde_json::json;
 use tokio::time::Instant;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+#[derive(Debug, Deserialize)]
+pub struct AiStrategyParams {
+    /// Gemini model used to get the range recommendation
+    #[serde(default = "default_model")]
+    pub model: String,
+    /// Accepted width of the recommended range, in % of the current price
+    #[serde(default = "default_min_range_width_percent")]
+    pub min_range_width_percent: f64,
+    #[serde(default = "default_max_range_width_percent")]
+    pub max_range_width_percent: f64,
+    /// Max distance of a recommended range not containing the current price, in % of the price
+    #[serde(default = "default_max_price_distance_percent")]
+    pub max_price_distance_percent: f64,
+    /// Min confidence score of a rebalance recommendation
+    #[serde(default = "default_min_confidence")]
+    pub min_confidence: f64,
+    /// `range_percent` of the basic strategy range used when the response is rejected
+    #[serde(default = "default_fallback_range_percent")]
+    pub fallback_range_percent: f64,
+}
+
+fn default_model() -> String {
+    "gemini-2.0-flash".to_string()
+}
+
+fn default_min_range_width_percent() -> f64 {
+    0.2
+}
+
+fn default_max_range_width_percent() -> f64 {
+    50.0
+}
+
+fn default_max_price_distance_percent() -> f64 {
+    2.0
+}
+
+fn default_min_confidence() -> f64 {
+    0.5
+}
+
+fn default_fallback_range_percent() -> f64 {
+    1.0
+}
+
+pub struct AiStrategy {
+    pub params: AiStrategyParams,
+}
+
+impl AiStrategy {
+    pub fn from_params(params: &toml::Table) -> Result<Arc<dyn Strategy>> {
+        let params: AiStrategyParams = strategies::parse_params(params)?;
+
+        if params.min_range_width_percent <= 0.0
+            || params.min_range_width_percent >= params.max_range_width_percent
+        {
+            return Err(color_eyre::eyre::eyre!(
+                "AI strategy range width must be 0 < min_range_width_percent < max_range_width_percent, got {} and {}",
+                params.min_range_width_percent,
+                params.max_range_width_percent
+            ));
+        }
+
+        if params.fallback_range_percent <= 0.0 || params.fallback_range_percent >= 100.0 {
+            return Err(color_eyre::eyre::eyre!(
+                "AI strategy fallback_range_percent must be between 0 and 100, got {}",
+                params.fallback_range_percent
+            ));
+        }
+
+        Ok(Arc::new(Self { params }))
+    }
+
+    /// Decision used when the AI response is rejected: an active vault in range keeps its range,
+    /// otherwise the basic strategy range is used
+    fn fallback(&self, vault_details: &VaultDetails, reason: String) -> Result<RebalanceDecision> {
+        if vault_details.is_active
+            && helpers::math::is_tick_in_range(
+                vault_details.pool.current_tick,
+                vault_details.lower_tick,
+                vault_details.upper_tick,
+            )
+        {
+            warn!(
+                "AI response rejected for vault {}: {}. Keeping the current range",
+                vault_details.address, reason
+            );
+
+            return Ok(RebalanceDecision {
+                rebalance_required: false,
+                tick_range: TickRange {
+                    curent_tick: vault_details.pool.current_tick,
+                    lower_tick: vault_details.lower_tick,
+                    upper_tick: vault_details.upper_tick,
+                },
+                analysis: Some(format!(
+                    "AI response rejected ({}), the vault is in range and keeps its range",
+                    reason
+                )),
+            });
+        }
+
+        warn!(
+            "AI response rejected for vault {}: {}. Falling back to the basic strategy range",
+            vault_details.address, reason
+        );
+
+        let tick_range =
+            basic::get_range_around_price(vault_details, self.params.fallback_range_percent)?;
+
+        Ok(RebalanceDecision {
+            rebalance_required: true,
+            tick_range,
+            analysis: Some(format!(
+                "AI response rejected ({}), using the basic strategy range of {}% around the price",
+                reason, self.params.fallback_range_percent
+            )),
+        })
+    }
+}
+
+#[async_trait]
+impl Strategy for AiStrategy {
+    fn name(&self) -> &'static str {
+        "ai"
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    async fn decide(
+        &self,
+        vault_details: &VaultDetails,
+        market: &MarketContext,
+    ) -> Result<RebalanceDecision> {
+        let ai_strategy_result = match start(vault_details, market, &self.params.model).await {
+            Ok(response) => response,
+            Err(e) if e.is::<serde_json::Error>() => {
+                return self.fallback(vault_details, format!("Malformed response: {}", e));
+            }
+            Err(e) => return Err(e),
+        };
+
+        let analysis = Some(ai_strategy_result.analysis.clone());
+
+        if !ai_strategy_result.rebalance_required {
+            return Ok(RebalanceDecision {
+                rebalance_required: false,
+                tick_range: TickRange {
+                    curent_tick: vault_details.pool.current_tick,
+                    lower_tick: vault_details.lower_tick,
+                    upper_tick: vault_details.upper_tick,
+                },
+                analysis,
+            });
+        }
+
+        if let Err(reason) =
+            validate_ai_response(&ai_strategy_result, vault_details.pool.price1, &self.params)
+        {
+            return self.fallback(vault_details, reason);
+        }
+
+        let tick_range = match get_tick_range_from_ai_response(ai_strategy_result, vault_details)
+            .await
+            .and_then(|tick_range| {
+                core::vault_spawn::check_tick_range(
+                    tick_range.lower_tick,
+                    tick_range.upper_tick,
+                    vault_details.pool.tick_spacing,
+                )?;
+                Ok(tick_range)
+            }) {
+            Ok(tick_range) => tick_range,
+            Err(e) => return self.fallback(vault_details, e.to_string()),
+        };
+
+        Ok(RebalanceDecision {
+            rebalance_required: true,
+            tick_range,
+            analysis,
+        })
+    }
+}
 
 pub async fn get_best_range(_vault: &VaultDetails) -> Result<TickRange> {
     Ok(TickRange {
@@ -21,13 +214,25 @@ pub async fn get_best_range(_vault: &VaultDetails) -> Result<TickRange> {
     })
 }
 
-pub async fn start(vault_details: &VaultDetails) -> Result<AiStrategyResponse> {
+pub async fn start(
+    vault_details: &VaultDetails,
+    market: &MarketContext,
+    model: &str,
+) -> Result<AiStrategyResponse> {
     debug!("Start AI strategy...");
-    // 1. Fetch historical OHLCV price data from coingecko
-    let pool_gecko_data: CoingeckoOhlcvRes =
-        core::coingecko::get_pool_ohlcv_data(&vault_details.pool.address, vault_details).await?;
+    // 1. Use the provided OHLCV candles, or fetch the historical price data from coingecko
+    let pool_gecko_data = match &market.ohlcv {
+        Some(ohlcv_list) => ohlcv_list.clone(),
+        None => {
+            let ohlcv_res =
+                core::coingecko::get_pool_ohlcv_data(&vault_details.pool.address, vault_details)
+                    .await?;
+
+            debug!("Fetched historical OHLCV price data from coingecko");
 
-    debug!("Fetched historical OHLCV price data from coingecko");
+            ohlcv_res.data.attributes.ohlcv_list
+        }
+    };
 
     let current_price = vault_details.pool.price1;
     // Convert tick lower and upper to price
@@ -140,7 +345,7 @@ You are an advanced strategist for an automated liquidity manager. Your job is t
     let ai_client = gemini::Client::from_env();
 
     let ai_agent = ai_client
-        .agent("gemini-2.0-flash")
+        .agent(model)
         .preamble(ai_instruction_prompt)
         .temperature(0.0)
         .additional_params(json!({
@@ -198,6 +403,61 @@ Recommend the best pool liquidity range for the following pool by returning the
     Ok(ai_strategy_response)
 }
 
+/// Check a rebalance recommendation against the current price and the strategy params, returns
+/// the rejection reason
+pub fn validate_ai_response(
+    response: &AiStrategyResponse,
+    current_price: f64,
+    params: &AiStrategyParams,
+) -> Result<(), String> {
+    let lower_price = response.new_price_range.lower_price;
+    let upper_price = response.new_price_range.upper_price;
+
+    if response.confidence_score.is_nan() || response.confidence_score < params.min_confidence {
+        return Err(format!(
+            "Confidence score {} below {}",
+            response.confidence_score, params.min_confidence
+        ));
+    }
+
+    if !lower_price.is_finite()
+        || !upper_price.is_finite()
+        || lower_price <= 0.0
+        || lower_price >= upper_price
+    {
+        return Err(format!(
+            "Invalid price range [{}, {}]",
+            lower_price, upper_price
+        ));
+    }
+
+    let width_percent = (upper_price - lower_price) / current_price * 100.0;
+    if width_percent < params.min_range_width_percent
+        || width_percent > params.max_range_width_percent
+    {
+        return Err(format!(
+            "Range width {:.3}% outside [{}%, {}%]",
+            width_percent, params.min_range_width_percent, params.max_range_width_percent
+        ));
+    }
+
+    let distance_percent = if current_price < lower_price {
+        (lower_price - current_price) / current_price * 100.0
+    } else if current_price > upper_price {
+        (current_price - upper_price) / current_price * 100.0
+    } else {
+        0.0
+    };
+    if distance_percent > params.max_price_distance_percent {
+        return Err(format!(
+            "Range [{}, {}] is {:.3}% away from the current price {}",
+            lower_price, upper_price, distance_percent, current_price
+        ));
+    }
+
+    Ok(())
+}
+
 pub fn extract_json_from_markdown(md: &str) -> String {
     let json_block = md.replace("```json", "").replace("```", "");
     json_block.trim().to_string()
@@ -230,3 +490,137 @@ pub async fn get_tick_range_from_ai_response(
         upper_tick,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use crate::{
+        strategies,
+        types::{
+            AiStrategyResponse, Pool, Position, PriceRange, Token, VaultDetails, VaultFees,
+            VaultTVL,
+        },
+    };
+
+    use super::{AiStrategy, AiStrategyParams, validate_ai_response};
+
+    fn vault(current_tick: i32, lower_tick: i32, upper_tick: i32) -> VaultDetails {
+        let token = |symbol: &str, decimals: u8| Token {
+            address: format!("0x{}", symbol),
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            decimals,
+            is_native_wrapper: false,
+        };
+
+        VaultDetails {
+            address: "0xvault".to_string(),
+            pool: Pool {
+                address: "0xpool".to_string(),
+                token0: token("USDC", 6),
+                token1: token("USDT", 6),
+                fee: 0.05,
+                tick_spacing: 10,
+                current_tick,
+                sqrt_price_x96: U256::ZERO,
+                price1: 1.0001f64.powi(current_tick),
+                price0: 1.0001f64.powi(-current_tick),
+            },
+            name: "Yieldera Vault".to_string(),
+            symbol: "YV".to_string(),
+            decimals: 18,
+            total_supply: 0.0,
+            lower_tick,
+            upper_tick,
+            is_active: true,
+            is_vault_tokens_associated: true,
+            position: Position::default(),
+            tvl: VaultTVL {
+                tvl0: 0.0,
+                tvl1: 0.0,
+            },
+            fees: VaultFees::default(),
+        }
+    }
+
+    fn response(lower_price: f64, upper_price: f64, confidence_score: f64) -> AiStrategyResponse {
+        AiStrategyResponse {
+            rebalance_required: true,
+            new_price_range: PriceRange {
+                lower_price,
+                upper_price,
+            },
+            analysis: String::new(),
+            market_outlook: String::new(),
+            confidence_score,
+        }
+    }
+
+    #[test]
+    fn test_validate_ai_response() {
+        // Defaults: width within [0.2%, 50%], at most 2% away from the price, confidence >= 0.5
+        let params: AiStrategyParams = strategies::parse_params(&toml::Table::new()).unwrap();
+
+        assert!(validate_ai_response(&response(0.99, 1.01, 0.8), 1.0, &params).is_ok());
+        // Near the price without containing it
+        assert!(validate_ai_response(&response(1.01, 1.05, 0.8), 1.0, &params).is_ok());
+
+        // Inverted, empty, negative or not a number
+        assert!(validate_ai_response(&response(1.01, 0.99, 0.8), 1.0, &params).is_err());
+        assert!(validate_ai_response(&response(1.0, 1.0, 0.8), 1.0, &params).is_err());
+        assert!(validate_ai_response(&response(-1.0, 1.01, 0.8), 1.0, &params).is_err());
+        assert!(validate_ai_response(&response(f64::NAN, 1.01, 0.8), 1.0, &params).is_err());
+
+        // Too narrow, too wide, too far
+        assert!(validate_ai_response(&response(0.9995, 1.0005, 0.8), 1.0, &params).is_err());
+        assert!(validate_ai_response(&response(0.5, 1.5, 0.8), 1.0, &params).is_err());
+        assert!(validate_ai_response(&response(1.1, 1.2, 0.8), 1.0, &params).is_err());
+
+        // Low confidence
+        assert!(validate_ai_response(&response(0.99, 1.01, 0.3), 1.0, &params).is_err());
+        assert!(validate_ai_response(&response(0.99, 1.01, f64::NAN), 1.0, &params).is_err());
+    }
+
+    #[test]
+    fn test_fallback() {
+        let strategy = AiStrategy {
+            params: strategies::parse_params(&toml::Table::new()).unwrap(),
+        };
+
+        // In range: the current range is kept
+        let decision = strategy
+            .fallback(&vault(0, -500, 500), "Malformed".to_string())
+            .unwrap();
+        assert!(!decision.rebalance_required);
+        assert_eq!(
+            (
+                decision.tick_range.lower_tick,
+                decision.tick_range.upper_tick
+            ),
+            (-500, 500)
+        );
+
+        // Out of range (the upper tick is out) or inactive: basic range of 1% around the price
+        let basic_range = |vault_details: VaultDetails| {
+            let decision = strategy
+                .fallback(&vault_details, "Malformed".to_string())
+                .unwrap();
+            assert!(decision.rebalance_required);
+            (
+                decision.tick_range.lower_tick,
+                decision.tick_range.upper_tick,
+            )
+        };
+        let (lower_tick, upper_tick) = basic_range(vault(500, -500, 500));
+        assert!(lower_tick < 500 && 500 < upper_tick);
+        assert_ne!((lower_tick, upper_tick), (-500, 500));
+
+        let inactive = VaultDetails {
+            is_active: false,
+            ..vault(0, -500, 500)
+        };
+        let (lower_tick, upper_tick) = basic_range(inactive);
+        assert!(lower_tick < 0 && 0 < upper_tick && upper_tick - lower_tick < 1_000);
+    }
+}
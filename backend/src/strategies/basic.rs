@@ -1,22 +1,81 @@
 /*
     This Strategy will be very simple calculation that will always put a range of -1% at the left of the price and 1% at the right of the price
+    The range width can be changed with the `range_percent` strategy param
 */
 
+use std::sync::Arc;
+
 use crate::{
     helpers,
-    types::{TickRange, VaultDetails},
+    strategies::{self, Strategy},
+    types::{MarketContext, RebalanceDecision, TickRange, VaultDetails},
 };
+use async_trait::async_trait;
 use color_eyre::eyre::Result;
+use serde::Deserialize;
 use tracing::info;
 
-pub async fn get_best_range(vault: &VaultDetails) -> Result<TickRange> {
+#[derive(Debug, Deserialize)]
+pub struct BasicStrategyParams {
+    /// Distance of each range edge from the current price, in %
+    #[serde(default = "default_range_percent")]
+    pub range_percent: f64,
+}
+
+fn default_range_percent() -> f64 {
+    1.0
+}
+
+pub struct BasicStrategy {
+    pub range_percent: f64,
+}
+
+impl BasicStrategy {
+    pub fn from_params(params: &toml::Table) -> Result<Arc<dyn Strategy>> {
+        let params: BasicStrategyParams = strategies::parse_params(params)?;
+
+        if params.range_percent <= 0.0 || params.range_percent >= 100.0 {
+            return Err(color_eyre::eyre::eyre!(
+                "Basic strategy range_percent must be between 0 and 100, got {}",
+                params.range_percent
+            ));
+        }
+
+        Ok(Arc::new(Self {
+            range_percent: params.range_percent,
+        }))
+    }
+}
+
+#[async_trait]
+impl Strategy for BasicStrategy {
+    fn name(&self) -> &'static str {
+        "basic"
+    }
+
+    async fn decide(
+        &self,
+        vault_details: &VaultDetails,
+        _market: &MarketContext,
+    ) -> Result<RebalanceDecision> {
+        let tick_range = get_range_around_price(vault_details, self.range_percent)?;
+
+        Ok(RebalanceDecision {
+            rebalance_required: true,
+            tick_range,
+            analysis: None,
+        })
+    }
+}
+
+pub fn get_range_around_price(vault: &VaultDetails, range_percent: f64) -> Result<TickRange> {
     let current_price = vault.pool.price1;
     let pool_tick_spacing = vault.pool.tick_spacing;
 
-    // Low = current price - 1%
-    let low_price = current_price - current_price * 0.01;
-    // High = current price + 1%
-    let high_price = current_price + current_price * 0.01;
+    // Low = current price - range %
+    let low_price = current_price - current_price * range_percent / 100.0;
+    // High = current price + range %
+    let high_price = current_price + current_price * range_percent / 100.0;
 
     info!("Basic Strategy Low price: {}", low_price);
     info!("Basic Strategy High price: {}", high_price);
@@ -1,2 +1,90 @@
 pub mod ai;
-pub mod basic;
\ No newline at end of file
+pub mod basic;
+
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use color_eyre::eyre::Result;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    error::YielderaError,
+    types::{MarketContext, RebalanceDecision, VaultDetails},
+};
+
+/// A liquidity range strategy. Given the vault state and the market context it decides
+/// whether the vault position should be moved, and to which tick range.
+#[async_trait]
+pub trait Strategy: Send + Sync {
+    /// Name used to reference the strategy from the TOML config
+    fn name(&self) -> &'static str;
+
+    /// The decisions call a remote (paid, rate limited) service. Such a strategy is not
+    /// backtested from the public API, a backtest asks it for hundreds of decisions
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    async fn decide(
+        &self,
+        vault_details: &VaultDetails,
+        market: &MarketContext,
+    ) -> Result<RebalanceDecision>;
+}
+
+/// Builds a strategy from the `strategy_params` table of a vault config block
+pub type StrategyFactory = fn(&toml::Table) -> Result<Arc<dyn Strategy>>;
+
+/// Holds every strategy implementation available to the vaults, keyed by name
+#[derive(Clone, Default)]
+pub struct StrategyRegistry {
+    factories: HashMap<String, StrategyFactory>,
+}
+
+impl StrategyRegistry {
+    pub fn new() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Registry with the built-in strategies (basic and ai)
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register("basic", basic::BasicStrategy::from_params);
+        registry.register("ai", ai::AiStrategy::from_params);
+        registry
+    }
+
+    /// Register a strategy factory. A factory with the same name is replaced
+    pub fn register(&mut self, name: &str, factory: StrategyFactory) {
+        self.factories.insert(name.to_string(), factory);
+    }
+
+    /// Build the strategy `name` with the given parameters
+    pub fn build(&self, name: &str, params: &toml::Table) -> Result<Arc<dyn Strategy>> {
+        let factory = self.factories.get(name).ok_or_else(|| {
+            YielderaError::Config(format!(
+                "Unknown strategy '{}'. Available strategies: {:?}",
+                name,
+                self.names()
+            ))
+        })?;
+
+        factory(params).map_err(|e| {
+            YielderaError::Config(format!("Invalid params of strategy '{}': {:#}", name, e)).into()
+        })
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.factories.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        names
+    }
+}
+
+/// Deserialize the strategy parameters table into the strategy params struct
+pub fn parse_params<T: DeserializeOwned>(params: &toml::Table) -> Result<T> {
+    let params = toml::Value::Table(params.clone()).try_into()?;
+    Ok(params)
+}
@@ -1,9 +1,14 @@
 use actix_web::web;
-use alloy::primitives::{Address, U256};
+use alloy::primitives::{Address, Bytes, U256};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::state::AppState;
+use crate::{
+    backtest::BacktestConfig,
+    config::{DEFAULT_STRATEGY, MONITOR_VAULT_INTERVAL_SECONDS},
+    error::Severity,
+    state::AppState,
+};
 
 pub type EvmProvider = alloy::providers::fillers::FillProvider<
     alloy::providers::fillers::JoinFill<
@@ -44,6 +49,7 @@ pub struct VaultDetails {
     pub is_vault_tokens_associated: bool,
     pub position: Position,
     pub tvl: VaultTVL,
+    pub fees: VaultFees,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -109,30 +115,219 @@ pub struct VaultTokenBalances {
     pub token1_balance_u256: U256,
 }
 
-#[derive(Debug, Clone)]
-pub struct PrepareSwapArgs {
-    pub exact_amount_out: f64,
-    pub parsed_exact_amount_out: U256,
-    pub token_in: Token,
-    pub token_out: Token,
-    pub is_swap_0_to_1: bool,
-    pub max_amount_in: U256,
-    pub formatted_max_amount_in: f64,
-}
-
 /// Top-level config struct matching the TOML file structure
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TomlConfig {
     pub rpc_url: String,
+    /// Endpoints used when `rpc_url` fails, in order of preference
+    #[serde(default)]
+    pub fallback_rpc_urls: Vec<String>,
     pub chain_id: u64,
     pub non_fungible_position_manager_address: String,
     pub hbar_evm_address: String,
-    pub vaults: Vec<String>,
+    /// Multicall3 contract batching the vault state reads. Without it the reads are sent in
+    /// parallel
+    pub multicall_address: Option<Address>,
+    pub vaults: Vec<VaultConfig>,
+}
+
+impl TomlConfig {
+    /// Get the config block of the given vault address
+    pub fn vault_config(&self, vault_address: &str) -> Option<&VaultConfig> {
+        self.vaults
+            .iter()
+            .find(|vault| vault.address.eq_ignore_ascii_case(vault_address))
+    }
+
+    /// Get the name of the strategy configured for the given vault address
+    pub fn strategy_for(&self, vault_address: &str) -> &str {
+        self.vault_config(vault_address)
+            .map(|vault| vault.strategy.as_str())
+            .unwrap_or(DEFAULT_STRATEGY)
+    }
+}
+
+/// A `[[vaults]]` block of the TOML config
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct VaultConfig {
+    pub address: String,
+    /// Strategy name, `ai` when not set
+    #[serde(default = "default_strategy_name")]
+    pub strategy: String,
+    /// Free-form parameters handed to the strategy when it is built
+    #[serde(default)]
+    pub strategy_params: toml::Table,
+    #[serde(default = "default_monitor_interval_seconds")]
+    pub monitor_interval_seconds: u64,
+    /// While the position is in range, skip the rebalance if the fees are below these amounts
+    #[serde(default = "default_min_fees")]
+    pub min_fees0: f64,
+    #[serde(default = "default_min_fees")]
+    pub min_fees1: f64,
+    /// Max slippage (in %) accepted on the swap done during a rebalance
+    #[serde(default = "default_max_slippage_percent")]
+    pub max_slippage_percent: f64,
+    /// Send the rebalance transactions or only log them. Falls back to `IS_EXECUTE` when not set
+    pub execute: Option<bool>,
+    /// HBAR sent along the rebalance transaction
+    #[serde(default = "default_rebalance_value_hbar")]
+    pub rebalance_value_hbar: f64,
+    /// Alert emails recipients. Falls back to `ADMIN_EMAIL` when empty
+    #[serde(default)]
+    pub alert_recipients: Vec<String>,
+    /// Block the event indexer starts from when it has no cursor yet. Defaults to the vault
+    /// deployment block, looked up on the node
+    pub indexer_start_block: Option<u64>,
+    /// When the vault is evaluated, see `RebalanceTrigger`
+    #[serde(default)]
+    pub trigger: RebalanceTrigger,
+    /// With the `swap` trigger, evaluate once a swap moves the pool tick within this distance of
+    /// a range edge, in % of the range width
+    #[serde(default = "default_trigger_edge_percent")]
+    pub trigger_edge_percent: f64,
+    /// Min time between two rebalance transactions
+    #[serde(default = "default_min_rebalance_interval_seconds")]
+    pub min_rebalance_interval_seconds: u64,
+    /// Max rebalance transactions over the last 24 hours
+    #[serde(default = "default_max_rebalances_per_day")]
+    pub max_rebalances_per_day: u64,
+    /// Max HBAR spent by the rebalance transactions (gas and value) over the last 24 hours
+    #[serde(default = "default_max_daily_hbar_spent")]
+    pub max_daily_hbar_spent: f64,
+    /// Consecutive failed rebalance transactions pausing the vault until an admin reset
+    #[serde(default = "default_max_consecutive_failures")]
+    pub max_consecutive_failures: u32,
+    /// Window of the pool TWAP the spot price is checked against before a rebalance, 0 disables
+    /// the check. The pool oracle must keep observations that old (see
+    /// `increaseObservationCardinalityNext`), the rebalance is blocked otherwise
+    #[serde(default = "default_twap_window_seconds")]
+    pub twap_window_seconds: u32,
+    /// Max gap between the spot and TWAP prices (in %) a rebalance is sent with, above it the
+    /// rebalance is postponed
+    #[serde(default = "default_max_twap_deviation_percent")]
+    pub max_twap_deviation_percent: f64,
+    /// Lowest severity of the failures sending an alert email
+    #[serde(default)]
+    pub alert_min_severity: Severity,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RebalanceTrigger {
+    /// Every `monitor_interval_seconds`
+    #[default]
+    Interval,
+    /// When a pool swap gets close to a range edge, and at least every
+    /// `monitor_interval_seconds`. The swaps are polled with eth_getLogs
+    Swap,
+}
+
+fn default_strategy_name() -> String {
+    DEFAULT_STRATEGY.to_string()
+}
+
+fn default_monitor_interval_seconds() -> u64 {
+    MONITOR_VAULT_INTERVAL_SECONDS
+}
+
+fn default_min_fees() -> f64 {
+    0.01
+}
+
+fn default_max_slippage_percent() -> f64 {
+    1.0
+}
+
+fn default_rebalance_value_hbar() -> f64 {
+    0.2
+}
+
+fn default_trigger_edge_percent() -> f64 {
+    10.0
+}
+
+fn default_min_rebalance_interval_seconds() -> u64 {
+    60 * 60
+}
+
+fn default_max_rebalances_per_day() -> u64 {
+    12
+}
+
+fn default_max_daily_hbar_spent() -> f64 {
+    100.0
+}
+
+fn default_max_consecutive_failures() -> u32 {
+    3
+}
+
+fn default_twap_window_seconds() -> u32 {
+    10 * 60
+}
+
+fn default_max_twap_deviation_percent() -> f64 {
+    2.0
+}
+
+/// One admin request of the audit log
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct AdminAuditEntry {
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+    /// `api_key:<name>` or `owner:<address>`, None when the authentication failed
+    pub principal: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    /// Why the authentication failed
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AdminAuditPage {
+    pub items: Vec<AdminAuditEntry>,
+    pub page: u32,
+    pub page_size: u32,
+    pub total: u64,
+}
+
+/// Admin API key of `ADMIN_API_KEYS`, only its SHA-256 hash is kept
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdminApiKey {
+    /// Name of the key holder, written in the audit log
+    pub name: String,
+    pub hash: [u8; 32],
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
-pub struct AdminAssociateVaultTokensRequest {
-    pub password: String,
+pub struct AdminForceRebalanceRequest {
+    pub lower_tick: i32,
+    pub upper_tick: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct VaultControlStatus {
+    pub vault_address: String,
+    /// Evaluations are skipped while paused, the admin actions still run
+    pub paused: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AdminTransactionResponse {
+    pub tx_hash: String,
+}
+
+/// Consecutive failed rebalance transactions of a vault. A tripped breaker pauses the vault
+/// rebalances until an admin reset
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, ToSchema)]
+pub struct CircuitBreaker {
+    pub vault_address: String,
+    pub consecutive_failures: u32,
+    /// Unix timestamp in seconds
+    pub tripped_at: Option<i64>,
+    /// Error of the last failed transaction
+    pub reason: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -143,7 +338,7 @@ pub struct ApiErrorResponse {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CoingeckoOhlcvRes {
-    data: CoingeckoResData,
+    pub data: CoingeckoResData,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -157,16 +352,38 @@ pub struct CoingeckoResDataAttributes {
     pub ohlcv_list: Vec<OhlcvEntry>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct OhlcvEntry(
-    i64, // timestamp (UNIX)
-    f64, // open
-    f64, // high
-    f64, // low
-    f64, // close
-    f64, // volume
+    pub i64, // timestamp (UNIX)
+    pub f64, // open
+    pub f64, // high
+    pub f64, // low
+    pub f64, // close
+    pub f64, // volume
 );
 
+impl OhlcvEntry {
+    pub fn timestamp(&self) -> i64 {
+        self.0
+    }
+
+    pub fn high(&self) -> f64 {
+        self.2
+    }
+
+    pub fn low(&self) -> f64 {
+        self.3
+    }
+
+    pub fn close(&self) -> f64 {
+        self.4
+    }
+
+    pub fn volume(&self) -> f64 {
+        self.5
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AiStrategyResponse {
     pub rebalance_required: bool,
@@ -182,15 +399,517 @@ pub struct PriceRange {
     pub upper_price: f64,
 }
 
+/// Market data handed to a strategy alongside the vault details
+#[derive(Debug, Clone)]
+pub struct MarketContext {
+    /// Recent OHLCV candles. When `None`, strategies that need them fetch them on their own
+    pub ohlcv: Option<Vec<OhlcvEntry>>,
+}
+
+impl MarketContext {
+    pub fn live() -> Self {
+        Self { ohlcv: None }
+    }
+}
+
+/// Output of a strategy: whether to move the position and where to move it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebalanceDecision {
+    pub rebalance_required: bool,
+    pub tick_range: TickRange,
+    pub analysis: Option<String>,
+}
+
+/// What the bot would do for a vault on its next rebalance: strategy output, swap and resulting position
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RebalancePlan {
+    pub vault_address: String,
+    pub strategy: String,
+    pub rebalance_required: bool,
+    /// Why the vault is not rebalanced, when rebalance_required is false
+    pub skip_reason: Option<String>,
+    /// Why the required rebalance is postponed (rebalance limits, TWAP deviation)
+    #[serde(default)]
+    pub blocked_reason: Option<String>,
+    pub current_tick: i32,
+    pub current_lower_tick: i32,
+    pub current_upper_tick: i32,
+    pub lower_tick: i32,
+    pub upper_tick: i32,
+    pub price1: f64,
+    pub lower_price1: f64,
+    pub upper_price1: f64,
+    pub swap: Option<RebalanceSwapPlan>,
+    pub expected_liquidity: u128,
+    pub expected_amount0: f64,
+    pub expected_amount1: f64,
+    pub analysis: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RebalanceSwapPlan {
+    pub is_swap_0_to_1: bool,
+    pub token_in: String,
+    pub token_out: String,
+    #[schema(value_type = String)]
+    pub amount_in: U256,
+    pub formatted_amount_in: f64,
+    #[schema(value_type = String)]
+    pub amount_out: U256,
+    pub formatted_amount_out: f64,
+    /// Simulated amount in plus the allowed slippage, capped at the vault balance
+    #[schema(value_type = String)]
+    pub max_amount_in: U256,
+    pub formatted_max_amount_in: f64,
+    pub price1_after: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RebalanceStatus {
+    /// The plan did not require a rebalance
+    Skipped,
+    /// Rebalance required but execution is disabled for the vault
+    DryRun,
+    Success,
+    /// The rebalance transaction was mined but reverted
+    Failed,
+    /// The rebalance transaction could not be sent or confirmed
+    Error,
+    /// Rebalance required but stopped by a guardrail (cooldown, daily budget, circuit breaker)
+    Blocked,
+}
+
+/// One rebalance attempt of the history store
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RebalanceRecord {
+    pub id: i64,
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+    pub vault_address: String,
+    pub strategy: String,
+    pub status: RebalanceStatus,
+    pub skip_reason: Option<String>,
+    pub current_tick: i32,
+    pub price1: f64,
+    pub lower_tick: i32,
+    pub upper_tick: i32,
+    pub analysis: Option<String>,
+    pub plan: RebalancePlan,
+    pub tx_hash: Option<String>,
+    pub error: Option<String>,
+    pub position_before: Position,
+    pub position_after: Option<Position>,
+    /// HBAR paid for the rebalance transaction: gas fee and value sent
+    pub hbar_spent: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RebalanceHistoryPage {
+    pub items: Vec<RebalanceRecord>,
+    pub page: u32,
+    pub page_size: u32,
+    pub total: u64,
+}
+
+#[derive(Deserialize, Debug, Clone, IntoParams)]
+pub struct PaginationQuery {
+    /// Page number, starting at 1
+    pub page: Option<u32>,
+    /// Items per page, max 100
+    pub page_size: Option<u32>,
+}
+
+/// Vault state recorded on each monitor loop iteration
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct VaultSnapshot {
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+    pub vault_address: String,
+    pub tvl0: f64,
+    pub tvl1: f64,
+    /// Whole TVL valued in token0
+    pub tvl_in_token0: f64,
+    /// Whole TVL valued in token1
+    pub tvl_in_token1: f64,
+    pub total_supply: f64,
+    /// Value of one share in token0, 0 when there are no shares
+    pub share_price0: f64,
+    /// Value of one share in token1, 0 when there are no shares
+    pub share_price1: f64,
+    pub price1: f64,
+    pub current_tick: i32,
+    pub lower_tick: i32,
+    pub upper_tick: i32,
+    pub is_active: bool,
+    /// Fees owed by the position, not collected yet
+    pub fees0: f64,
+    pub fees1: f64,
+    /// Fees collected since the vault creation
+    pub collected_fees0: f64,
+    pub collected_fees1: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum SnapshotInterval {
+    /// Every recorded snapshot
+    #[serde(rename = "raw")]
+    Raw,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+#[derive(Deserialize, Debug, Clone, IntoParams)]
+pub struct VaultHistoryQuery {
+    /// Start unix timestamp in seconds, defaults to 7 days before `to`
+    pub from: Option<i64>,
+    /// End unix timestamp in seconds, defaults to now
+    pub to: Option<i64>,
+    /// Bucket size, the last snapshot of each bucket is returned. Defaults to 1h
+    #[param(value_type = Option<String>, example = "1h")]
+    pub interval: Option<SnapshotInterval>,
+}
+
+#[derive(Deserialize, Debug, Clone, IntoParams)]
+pub struct TimeRangeQuery {
+    /// Start unix timestamp in seconds, defaults to 7 days before `to`
+    pub from: Option<i64>,
+    /// End unix timestamp in seconds, defaults to now
+    pub to: Option<i64>,
+}
+
+/// Yield analytics of a vault over a period, computed from the recorded snapshots
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct VaultStats {
+    pub vault_address: String,
+    pub from: i64,
+    pub to: i64,
+    /// Time covered by the snapshots of the period, in seconds
+    pub covered_seconds: i64,
+    pub snapshots: u32,
+    /// Fees earned by the position over the period, collected or not
+    pub fees_earned0: f64,
+    pub fees_earned1: f64,
+    /// Fees earned valued in token1 at the price of each snapshot
+    pub fees_earned_in_token1: f64,
+    /// Time weighted TVL valued in token1
+    pub average_tvl_in_token1: f64,
+    /// Annualized fee yield, in percent
+    pub fee_apr: f64,
+    /// Fee APR minus the performance fee, in percent
+    pub net_apr: f64,
+    /// Net APR compounded daily, in percent
+    pub net_apy: f64,
+    /// Performance fee in percent
+    pub performance_fee: f64,
+    /// Share of the covered time the position was active and in range, in percent
+    pub time_in_range: f64,
+    /// Successful rebalances over the period
+    pub rebalance_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct VaultHistory {
+    pub vault_address: String,
+    pub from: i64,
+    pub to: i64,
+    pub interval: SnapshotInterval,
+    pub items: Vec<VaultSnapshot>,
+}
+
+/// A decoded YielderaVault log
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct VaultEvent {
+    pub vault_address: String,
+    pub block_number: u64,
+    /// Unix timestamp of the block in seconds, when the node returned it
+    pub block_timestamp: Option<i64>,
+    pub tx_hash: String,
+    pub log_index: u64,
+    pub event: VaultEventData,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(tag = "name", rename_all = "snake_case")]
+pub enum VaultEventData {
+    Deposit {
+        sender: String,
+        to: String,
+        #[schema(value_type = String)]
+        shares: U256,
+        #[schema(value_type = String)]
+        amount0: U256,
+        #[schema(value_type = String)]
+        amount1: U256,
+    },
+    Withdraw {
+        sender: String,
+        to: String,
+        #[schema(value_type = String)]
+        shares: U256,
+        #[schema(value_type = String)]
+        amount0: U256,
+        #[schema(value_type = String)]
+        amount1: U256,
+    },
+    Rebalance {
+        tick: i32,
+        #[schema(value_type = String)]
+        total_amount0: U256,
+        #[schema(value_type = String)]
+        total_amount1: U256,
+        #[schema(value_type = String)]
+        fee_amount0: U256,
+        #[schema(value_type = String)]
+        fee_amount1: U256,
+        #[schema(value_type = String)]
+        total_supply: U256,
+    },
+    MintLiquidity {
+        sender: String,
+        liquidity: u128,
+        #[schema(value_type = String)]
+        amount0: U256,
+        #[schema(value_type = String)]
+        amount1: U256,
+    },
+    BurnAllLiquidity {
+        sender: String,
+        #[schema(value_type = String)]
+        amount0: U256,
+        #[schema(value_type = String)]
+        amount1: U256,
+    },
+    CollectFees {
+        sender: String,
+        #[schema(value_type = String)]
+        fees0: U256,
+        #[schema(value_type = String)]
+        fees1: U256,
+    },
+    AssociateToken {
+        token: String,
+    },
+    /// Share transfer between two accounts
+    Transfer {
+        from: String,
+        to: String,
+        #[schema(value_type = String)]
+        value: U256,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct VaultEventsPage {
+    pub items: Vec<VaultEvent>,
+    pub page: u32,
+    pub page_size: u32,
+    pub total: u64,
+}
+
+/// Position of an account in a vault, the cost basis comes from the indexed events
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct UserPosition {
+    pub vault_address: String,
+    pub vault_symbol: String,
+    pub token0_symbol: String,
+    pub token1_symbol: String,
+    pub shares: f64,
+    /// Share of the vault total supply, in percent
+    pub share_of_vault: f64,
+    /// Amounts redeemable now for the shares
+    pub amount0: f64,
+    pub amount1: f64,
+    pub value_in_token1: f64,
+    /// Totals of the indexed deposits and withdrawals
+    pub deposited0: f64,
+    pub deposited1: f64,
+    pub withdrawn0: f64,
+    pub withdrawn1: f64,
+    /// Deposited amounts still backing the current shares (average cost)
+    pub cost_basis0: f64,
+    pub cost_basis1: f64,
+    /// Value of the cost basis held outside the vault, at the current price
+    pub hodl_value_in_token1: f64,
+    /// Value minus the HODL value
+    pub pnl_in_token1: f64,
+    pub pnl_percent: f64,
+    /// False when the account holds shares that were not deposited by it in the indexed
+    /// blocks (received transfers) or when the vault is not indexed from its deployment block,
+    /// the PnL is then partial
+    pub is_cost_basis_complete: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DepositQuoteRequest {
+    /// Amount of token0 to deposit, in token units
+    #[schema(value_type = String, example = "1.5")]
+    pub amount0: TokenAmount,
+    /// Amount of token1 to deposit, in token units
+    #[schema(value_type = String, example = "1.5")]
+    pub amount1: TokenAmount,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DepositQuote {
+    pub vault_address: String,
+    pub amount0: f64,
+    pub amount1: f64,
+    /// Amounts in the token decimals, as passed to `deposit`
+    #[schema(value_type = String)]
+    pub amount0_raw: U256,
+    #[schema(value_type = String)]
+    pub amount1_raw: U256,
+    /// Shares minted. The fees accrued since the last update of the position are not counted,
+    /// which can lower it slightly
+    pub shares: f64,
+    #[schema(value_type = String)]
+    pub shares_raw: U256,
+    /// Share of the vault after the deposit, in percent
+    pub share_of_vault_after: f64,
+    /// Symbol of the token paid in native HBAR (WHBAR side of the vault), if deposited
+    pub native_token: Option<String>,
+    /// Transaction value in weibars (18 decimals, the relay converts it to 8 decimals tinybars)
+    #[schema(value_type = String)]
+    pub value: U256,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct WithdrawQuoteRequest {
+    /// Shares to burn, in share units
+    #[schema(value_type = String, example = "1.5")]
+    pub shares: TokenAmount,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct WithdrawQuote {
+    pub vault_address: String,
+    pub shares: f64,
+    #[schema(value_type = String)]
+    pub shares_raw: U256,
+    pub amount0: f64,
+    pub amount1: f64,
+    #[schema(value_type = String)]
+    pub amount0_raw: U256,
+    #[schema(value_type = String)]
+    pub amount1_raw: U256,
+    /// Symbol of the token received as native HBAR instead of WHBAR, if any
+    pub native_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DepositTxRequest {
+    /// Account signing the transaction
+    pub from: String,
+    /// Receiver of the shares, defaults to `from`
+    pub receiver: Option<String>,
+    /// Amount of token0 to deposit, in token units
+    #[schema(value_type = String, example = "1.5")]
+    pub amount0: TokenAmount,
+    /// Amount of token1 to deposit, in token units
+    #[schema(value_type = String, example = "1.5")]
+    pub amount1: TokenAmount,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct WithdrawTxRequest {
+    /// Owner of the shares, signing the transaction
+    pub from: String,
+    /// Receiver of the tokens, defaults to `from`
+    pub receiver: Option<String>,
+    /// Shares to burn, in share units
+    #[schema(value_type = String, example = "1.5")]
+    pub shares: TokenAmount,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ApproveTxRequest {
+    /// Account signing the transaction
+    pub from: String,
+    /// Address of the vault token to approve
+    pub token: String,
+    /// Allowance given to the vault, in token units
+    #[schema(value_type = String, example = "1.5")]
+    pub amount: TokenAmount,
+}
+
+/// Amount of a transaction request in token units: a decimal string ("1.5", "100") or a JSON
+/// number. It is converted to the raw amount with the token decimals, never through a f64
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "TokenAmountInput")]
+pub struct TokenAmount(pub String);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TokenAmountInput {
+    Text(String),
+    Number(serde_json::Number),
+}
+
+impl From<TokenAmountInput> for TokenAmount {
+    fn from(input: TokenAmountInput) -> Self {
+        match input {
+            TokenAmountInput::Text(amount) => Self(amount),
+            TokenAmountInput::Number(amount) => Self(amount.to_string()),
+        }
+    }
+}
+
+/// EVM transaction ready to be signed by the `from` account
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct UnsignedTransaction {
+    pub chain_id: u64,
+    pub from: String,
+    pub to: String,
+    #[schema(value_type = String)]
+    pub data: Bytes,
+    /// Value in weibars (18 decimals, the relay converts it to 8 decimals tinybars)
+    #[schema(value_type = String)]
+    pub value: U256,
+    /// Gas estimate, None when the call reverts at the current state
+    pub gas: Option<u64>,
+    /// Revert reason of the gas estimate
+    pub gas_error: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct VaultTVL {
     pub tvl0: f64,
     pub tvl1: f64,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct VaultFees {
+    /// Fees collected from the position since the vault creation (vaultFees0)
+    pub collected0: f64,
+    /// Fees collected from the position since the vault creation (vaultFees1)
+    pub collected1: f64,
+    /// Performance fee in percent
+    pub performance_fee: f64,
+}
+
 #[derive(serde::Deserialize, serde::Serialize, utoipa::ToSchema)]
 pub struct ChatRequest {
     pub message: String,
     pub network: Option<String>,
     pub account_address: String,
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BacktestRequest {
+    /// Strategy to backtest. Defaults to the strategy configured for the vault, remote strategies (ai) are rejected
+    pub strategy: Option<String>,
+    /// Strategy params. Defaults to the params configured for the vault
+    #[schema(value_type = Option<Object>)]
+    pub strategy_params: Option<toml::Table>,
+    /// Candles to replay. Defaults to the pool daily candles from CoinGecko
+    pub candles: Option<Vec<OhlcvEntry>>,
+    /// Name of a candles file (.csv or .json) in the backend `backtest_data` directory, used when `candles` is not set
+    pub candles_file: Option<String>,
+    #[serde(flatten)]
+    pub config: BacktestConfig,
+}